@@ -0,0 +1,187 @@
+use chrono::Local;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::manifest;
+use crate::mods;
+
+/// Сколько снимков хранить по умолчанию
+pub const DEFAULT_BACKUP_LIMIT: usize = 5;
+
+const BACKUPS_DIR: &str = "backups";
+const MODS_DIR: &str = "mods";
+const MANIFEST_FILE: &str = "manifest.json";
+/// Формат имени снимка; при нескольких снимках в одну секунду добавляется `_N`
+const STAMP_FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
+/// Длина имени по `STAMP_FORMAT`: `2024-05-01_12-30-00`
+const STAMP_LEN: usize = 19;
+
+/// Папка снимков экземпляра (`.stm/backups`)
+pub fn backups_dir(mods_dir: &Path) -> PathBuf {
+    manifest::stm_dir(mods_dir).join(BACKUPS_DIR)
+}
+
+/// Снимок папки модов и манифеста перед изменениями.
+/// Файлы сохраняются жёсткими ссылками (или копируются, если ссылку создать нельзя),
+/// поэтому снимок почти не занимает места. Старые снимки сверх `limit` удаляются.
+pub fn snapshot(mods_dir: &Path, limit: usize) -> io::Result<PathBuf> {
+    let root = backups_dir(mods_dir);
+    fs::create_dir_all(&root)?;
+
+    // Имя по времени; при нескольких снимках в одну секунду добавляется номер
+    let stamp = Local::now().format(STAMP_FORMAT).to_string();
+    let mut backup = root.join(&stamp);
+    let mut suffix = 1;
+    while backup.exists() {
+        backup = root.join(format!("{}_{}", stamp, suffix));
+        suffix += 1;
+    }
+
+    mods::link_tree(mods_dir, &backup.join(MODS_DIR))?;
+
+    let manifest_path = manifest::manifest_path(mods_dir);
+    if manifest_path.exists() {
+        fs::copy(&manifest_path, backup.join(MANIFEST_FILE))?;
+    }
+
+    prune(mods_dir, limit)?;
+    Ok(backup)
+}
+
+/// Снимки экземпляра, от новых к старым
+pub fn list(mods_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let root = backups_dir(mods_dir);
+    if !root.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups: Vec<PathBuf> = fs::read_dir(&root)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.join(MODS_DIR).is_dir())
+        .collect();
+
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup_order(backup)));
+    Ok(backups)
+}
+
+/// Порядок снимка: время из имени, затем номер снимка в эту секунду.
+/// Номер сравнивается как число, иначе `_10` оказался бы раньше `_2`
+fn backup_order(backup: &Path) -> (String, u32) {
+    let name = backup.file_name().unwrap_or_default().to_string_lossy();
+    match name.get(STAMP_LEN..).and_then(|rest| rest.strip_prefix('_')) {
+        Some(number) => (name[..STAMP_LEN].to_string(), number.parse().unwrap_or(0)),
+        None => (name.to_string(), 0),
+    }
+}
+
+/// Восстановление папки модов и манифеста из снимка.
+/// Текущее состояние сначала сохраняется новым снимком, чтобы откат можно было
+/// отменить, а папка модов подменяется целиком, как при установке.
+/// Возвращает количество восстановленных файлов.
+pub fn restore(mods_dir: &Path, backup: &Path, limit: usize) -> io::Result<u32> {
+    let saved_mods = backup.join(MODS_DIR);
+    if !saved_mods.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Снимок повреждён: {}", backup.display()),
+        ));
+    }
+
+    // Манифест читается заранее: новый снимок может вытеснить этот из лимита
+    let saved_manifest = backup.join(MANIFEST_FILE);
+    let saved_manifest = if saved_manifest.exists() {
+        Some(fs::read(&saved_manifest)?)
+    } else {
+        None
+    };
+
+    let count = mods::replace_with_tree(mods_dir, &saved_mods, || {
+        snapshot(mods_dir, limit).map(|_| ())
+    })?;
+
+    let manifest_path = manifest::manifest_path(mods_dir);
+    match saved_manifest {
+        Some(content) => fs::write(&manifest_path, content)?,
+        None if manifest_path.exists() => fs::remove_file(&manifest_path)?,
+        None => {}
+    }
+
+    Ok(count)
+}
+
+/// Удаление снимков сверх лимита
+fn prune(mods_dir: &Path, limit: usize) -> io::Result<()> {
+    for old in list(mods_dir)?.into_iter().skip(limit.max(1)) {
+        fs::remove_dir_all(old)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_game(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("stm-backup-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("mods")).unwrap();
+        root
+    }
+
+    #[test]
+    fn snapshots_in_one_second_are_ordered_by_number() {
+        let mut backups: Vec<PathBuf> = [
+            "2024-05-01_12-30-00_2",
+            "2024-05-01_12-30-00",
+            "2024-05-01_12-30-00_10",
+            "2024-04-30_23-59-59_11",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+        backups.sort_by_key(|backup| std::cmp::Reverse(backup_order(backup)));
+
+        let names: Vec<String> = backups.iter().map(|b| b.display().to_string()).collect();
+        assert_eq!(
+            names,
+            ["2024-05-01_12-30-00_10", "2024-05-01_12-30-00_2", "2024-05-01_12-30-00", "2024-04-30_23-59-59_11"]
+        );
+    }
+
+    #[test]
+    fn restore_keeps_current_state_in_a_new_snapshot() {
+        let root = temp_game("restore");
+        let mods_dir = root.join("mods");
+        fs::write(mods_dir.join("old.jar"), "old").unwrap();
+        let saved = snapshot(&mods_dir, 5).unwrap();
+
+        fs::remove_file(mods_dir.join("old.jar")).unwrap();
+        fs::write(mods_dir.join("new.jar"), "new").unwrap();
+
+        assert_eq!(restore(&mods_dir, &saved, 5).unwrap(), 1);
+        assert!(mods_dir.join("old.jar").is_file());
+        assert!(!mods_dir.join("new.jar").exists());
+
+        // Новый снимок хранит состояние до отката
+        let backups = list(&mods_dir).unwrap();
+        assert_eq!(backups.len(), 2);
+        assert!(backups[0].join(MODS_DIR).join("new.jar").is_file());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn restore_of_broken_snapshot_leaves_mods_untouched() {
+        let root = temp_game("broken");
+        let mods_dir = root.join("mods");
+        fs::write(mods_dir.join("a.jar"), "a").unwrap();
+
+        assert!(restore(&mods_dir, &root.join("missing"), 5).is_err());
+        assert!(mods_dir.join("a.jar").is_file());
+        assert!(list(&mods_dir).unwrap().is_empty());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
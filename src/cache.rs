@@ -0,0 +1,557 @@
+use git2::{build::CheckoutBuilder, Commit, Delta, ErrorClass, ErrorCode, FetchOptions, Repository, ResetType};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use walkdir::WalkDir;
+
+/// Файл внутри `.git`, по времени изменения которого считается последнее использование клона
+const LAST_USED_FILE: &str = "stm-last-used";
+/// Папка кеша для файлов, подготовленных из источников без клона:
+/// моды из .mrpack, копии сборок packwiz по ссылке
+const DERIVED_DIR: &str = "derived";
+/// Суффикс отметки использования рядом с подготовленной папкой
+/// (внутри неё лежат только моды, которые копируются в экземпляр)
+const DERIVED_MARKER_SUFFIX: &str = ".last-used";
+/// Суффикс файла блокировки рядом с клоном
+const LOCK_SUFFIX: &str = ".lock";
+
+/// Папка кеша клонов сборок (`$XDG_CACHE_HOME/storytime-launcher/packs`)
+pub fn cache_root() -> PathBuf {
+    dirs::cache_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("storytime-launcher")
+        .join("packs")
+}
+
+/// Папка клона для репозитория: имя репозитория + короткий хеш URL
+pub fn repo_dir(url: &str) -> PathBuf {
+    let name = url
+        .trim_end_matches('/')
+        .trim_end_matches(".git")
+        .rsplit('/')
+        .next()
+        .unwrap_or("pack");
+    let hash = format!("{:x}", Sha256::digest(url.as_bytes()));
+
+    cache_root().join(format!("{}-{}", name, &hash[..12]))
+}
+
+/// Папка подготовленных файлов `<кеш>/derived/<вид>/<имя>`.
+/// Каждый вызов отмечает её как использованную, чтобы `gc` удалял только заброшенные.
+pub fn derived_dir(kind: &str, name: &str) -> PathBuf {
+    let dir = cache_root().join(DERIVED_DIR).join(kind).join(name);
+    if let Some(parent) = dir.parent() {
+        let _ = fs::create_dir_all(parent);
+        let _ = fs::write(derived_marker(&dir), b"");
+    }
+    dir
+}
+
+/// Клон репозитория сборки в кеше, обновлённый один раз: теги, разница версий
+/// и переключение на нужный коммит работают с ним без повторного fetch.
+/// Обновление и переключение идут под блокировкой `<клон>.lock`,
+/// чтобы несколько запусков stm (например, из cron) не мешали друг другу.
+#[derive(Clone)]
+pub struct PackRepo {
+    url: String,
+    path: PathBuf,
+}
+
+impl PackRepo {
+    /// Fetch закешированного клона или клонирование с нуля.
+    /// Если клон повреждён — он удаляется и скачивается заново.
+    pub fn fetch(url: &str) -> Result<Self, String> {
+        Self::fetch_into(url, repo_dir(url))
+    }
+
+    fn fetch_into(url: &str, path: PathBuf) -> Result<Self, String> {
+        let _lock = lock(&path)?;
+        open_or_clone(url, &path)?;
+        Ok(PackRepo { url: url.to_string(), path })
+    }
+
+    fn open(&self) -> Result<Repository, String> {
+        Repository::open(&self.path).map_err(|e| format!("Ошибка открытия кеша: {}", e))
+    }
+
+    /// Переключение рабочей копии на `git_ref` (тег, ветка или коммит;
+    /// без него — ветка по умолчанию). Клон, который не открывается или не
+    /// переключается, клонируется заново: у испорченного клона ссылка может
+    /// не находиться, даже если она есть на сервере.
+    /// Возвращает путь к рабочей копии и хеш выбранного коммита.
+    pub fn checkout(&self, git_ref: Option<&str>) -> Result<(PathBuf, String), String> {
+        let _lock = lock(&self.path)?;
+        let switched = Repository::open(&self.path)
+            .map_err(|e| e.to_string())
+            .and_then(|repo| checkout(&repo, git_ref));
+        let commit = match switched {
+            Ok(commit) => commit,
+            Err(_) => checkout(&clone_fresh(&self.url, &self.path)?, git_ref)?,
+        };
+
+        touch_last_used(&self.path);
+        Ok((self.path.clone(), commit))
+    }
+
+    /// Теги репозитория, от новых к старым
+    pub fn tags(&self) -> Result<Vec<String>, String> {
+        let repo = self.open()?;
+        let names = repo.tag_names(None).map_err(|e| e.to_string())?;
+        let mut tags: Vec<(i64, String)> = names
+            .iter()
+            .flatten()
+            .filter_map(|name| {
+                let commit = repo
+                    .find_reference(&format!("refs/tags/{}", name))
+                    .and_then(|r| r.peel_to_commit())
+                    .ok()?;
+                Some((commit.time().seconds(), name.to_string()))
+            })
+            .collect();
+
+        tags.sort_by(|a, b| b.cmp(a));
+        Ok(tags.into_iter().map(|(_, name)| name).collect())
+    }
+
+    /// Самый свежий тег, подходящий под шаблон (`*` — любая подстрока)
+    pub fn latest_tag_matching(&self, pattern: &str) -> Result<Option<String>, String> {
+        Ok(self.tags()?.into_iter().find(|tag| glob_match(pattern, tag)))
+    }
+
+    /// Какие .jar файлы в корне репозитория изменятся при переходе
+    /// с коммита `old_commit` (`None` — с пустой папки) на ссылку `new_ref`
+    pub fn diff_jars(&self, old_commit: Option<&str>, new_ref: Option<&str>) -> Result<Vec<JarChange>, String> {
+        let repo = self.open()?;
+
+        let old = match old_commit {
+            Some(commit) => Some(
+                repo.revparse_single(commit)
+                    .and_then(|o| o.peel_to_tree())
+                    .map_err(|_| format!("Коммит '{}' не найден", commit))?,
+            ),
+            None => None,
+        };
+        let new = resolve_commit(&repo, new_ref)?
+            .tree()
+            .map_err(|e| e.to_string())?;
+
+        let diff = repo
+            .diff_tree_to_tree(old.as_ref(), Some(&new), None)
+            .map_err(|e| e.to_string())?;
+
+        let mut changes = Vec::new();
+        for delta in diff.deltas() {
+            let file = match delta.status() {
+                Delta::Deleted => delta.old_file(),
+                _ => delta.new_file(),
+            };
+            let name = match file.path().and_then(|p| p.to_str()) {
+                Some(name) => name.to_string(),
+                None => continue,
+            };
+
+            // Только моды из корня репозитория, как при установке
+            if name.contains('/') || !name.ends_with(".jar") || name.starts_with('&') {
+                continue;
+            }
+
+            match delta.status() {
+                Delta::Added => changes.push(JarChange::Added(name)),
+                Delta::Deleted => changes.push(JarChange::Removed(name)),
+                Delta::Modified => changes.push(JarChange::Changed(name)),
+                _ => {}
+            }
+        }
+
+        Ok(changes)
+    }
+}
+
+/// Блокировка клона на время обновления или переключения: файл `<клон>.lock`
+/// рядом с ним (сам клон может быть удалён и склонирован заново).
+/// Снимается, когда возвращённый файл закрывается.
+fn lock(path: &Path) -> Result<fs::File, String> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(LOCK_SUFFIX);
+    let lock_path = path.with_file_name(name);
+    if let Some(parent) = lock_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Ошибка создания кеша: {}", e))?;
+    }
+
+    let file = fs::File::create(&lock_path)
+        .map_err(|e| format!("Ошибка блокировки кеша {}: {}", lock_path.display(), e))?;
+    file.lock()
+        .map_err(|e| format!("Ошибка блокировки кеша {}: {}", lock_path.display(), e))?;
+    Ok(file)
+}
+
+/// Открытие клона с fetch или клонирование, если клона нет или он повреждён.
+/// Ошибка fetch (сеть, HTTP, TLS, авторизация) возвращается как есть: рабочий клон
+/// остаётся на месте, а заново клонируется только то, что не открывается или испорчено.
+fn open_or_clone(url: &str, path: &Path) -> Result<Repository, String> {
+    if path.exists() {
+        if let Ok(repo) = Repository::open(path) {
+            match fetch_repo(&repo) {
+                Ok(()) => return Ok(repo),
+                Err(e) if !is_corrupted(&e) => return Err(format!("Ошибка обновления: {}", e)),
+                Err(_) => {}
+            }
+        }
+    }
+    clone_fresh(url, path)
+}
+
+/// Клонирование с нуля; прежняя папка клона удаляется
+fn clone_fresh(url: &str, path: &Path) -> Result<Repository, String> {
+    if path.exists() {
+        fs::remove_dir_all(path).map_err(|e| format!("Ошибка удаления повреждённого кеша: {}", e))?;
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Ошибка создания кеша: {}", e))?;
+    }
+
+    Repository::clone(url, path).map_err(|e| format!("Ошибка клонирования: {}", e))
+}
+
+/// Fetch всех веток и тегов из origin
+fn fetch_repo(repo: &Repository) -> Result<(), git2::Error> {
+    let mut remote = repo.find_remote("origin")?;
+    remote.fetch(
+        &["+refs/heads/*:refs/remotes/origin/*", "+refs/tags/*:refs/tags/*"],
+        Some(&mut FetchOptions::new()),
+        None,
+    )
+}
+
+/// Ошибка говорит о повреждённом клоне, а не о проблеме с сервером:
+/// испорченные объекты, ссылки или индекс, пропавший origin
+fn is_corrupted(error: &git2::Error) -> bool {
+    if error.code() == ErrorCode::Locked {
+        // Ссылку держит другой процесс — клон цел
+        return false;
+    }
+    match error.class() {
+        ErrorClass::Odb
+        | ErrorClass::Object
+        | ErrorClass::Repository
+        | ErrorClass::Reference
+        | ErrorClass::Index
+        | ErrorClass::Zlib => true,
+        ErrorClass::Config => error.code() == ErrorCode::NotFound,
+        _ => false,
+    }
+}
+
+/// Переключение рабочей копии на нужный коммит
+fn checkout(repo: &Repository, git_ref: Option<&str>) -> Result<String, String> {
+    let target = resolve_commit(repo, git_ref)?;
+
+    // Кеш только для чтения: HEAD всегда отсоединён и сбрасывается на нужный коммит
+    repo.set_head_detached(target.id())
+        .and_then(|_| {
+            repo.reset(
+                target.as_object(),
+                ResetType::Hard,
+                Some(CheckoutBuilder::new().force().remove_untracked(true)),
+            )
+        })
+        .map_err(|e| format!("Ошибка переключения на {}: {}", target.id(), e))?;
+
+    Ok(target.id().to_string())
+}
+
+/// Поиск коммита по ссылке: ветка origin, затем тег, затем коммит.
+/// Без ссылки берётся ветка по умолчанию.
+fn resolve_commit<'r>(repo: &'r Repository, git_ref: Option<&str>) -> Result<Commit<'r>, String> {
+    match git_ref {
+        None => repo
+            .find_reference("refs/remotes/origin/HEAD")
+            .and_then(|r| r.peel_to_commit())
+            .map_err(|e| format!("Ошибка чтения ветки по умолчанию: {}", e)),
+        Some(name) => repo
+            .find_reference(&format!("refs/remotes/origin/{}", name))
+            .or_else(|_| repo.find_reference(&format!("refs/tags/{}", name)))
+            .and_then(|r| r.peel_to_commit())
+            .or_else(|_| repo.revparse_single(name).and_then(|o| o.peel_to_commit()))
+            .map_err(|_| format!("Тег, ветка или коммит '{}' не найден", name)),
+    }
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !text.starts_with(first) || !text[first.len()..].ends_with(last) {
+        return false;
+    }
+
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Изменение .jar файла сборки между двумя версиями
+pub enum JarChange {
+    Added(String),
+    Removed(String),
+    Changed(String),
+}
+
+fn touch_last_used(path: &Path) {
+    let marker = path.join(".git").join(LAST_USED_FILE);
+    let _ = fs::write(marker, b"");
+}
+
+/// Итог очистки кеша
+pub struct GcReport {
+    /// Удалённые клоны и подготовленные папки
+    pub removed: u32,
+    pub freed_bytes: u64,
+}
+
+/// Очистка кеша: удаляет клоны чужих репозиториев (не из `keep_urls`),
+/// клоны, которые не использовались дольше `max_age`, и повреждённые клоны.
+/// Подготовленные папки (`derived`) удаляются по тем же правилам.
+/// При `remove_all` удаляется весь кеш.
+pub fn gc(keep_urls: &[&str], max_age: Duration, remove_all: bool) -> io::Result<GcReport> {
+    let mut report = GcReport { removed: 0, freed_bytes: 0 };
+    let root = cache_root();
+
+    if !root.exists() {
+        return Ok(report);
+    }
+
+    for entry in fs::read_dir(&root)? {
+        let path = entry?.path();
+        if !path.is_dir() {
+            // Блокировка без клона осталась от удалённого клона
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            if let Some(dir_name) = name.strip_suffix(LOCK_SUFFIX) {
+                if !root.join(dir_name).exists() {
+                    fs::remove_file(&path)?;
+                }
+            }
+            continue;
+        }
+        if path.file_name() == Some(DERIVED_DIR.as_ref()) {
+            gc_derived(&path, keep_urls, max_age, remove_all, &mut report)?;
+            continue;
+        }
+
+        let keep = !remove_all
+            && keep_urls.iter().any(|url| repo_dir(url) == path)
+            && Repository::open(&path).is_ok()
+            && last_used(&path)
+                .and_then(|t| t.elapsed().ok())
+                .is_some_and(|age| age <= max_age);
+
+        if !keep {
+            report.freed_bytes += dir_size(&path);
+            fs::remove_dir_all(&path)?;
+            report.removed += 1;
+        }
+    }
+
+    Ok(report)
+}
+
+/// Очистка `derived/<вид>/<имя>`: папка остаётся, только если её имя начинается
+/// с имени папки одного из `keep_urls` и она использовалась не дольше `max_age`
+fn gc_derived(
+    root: &Path,
+    keep_urls: &[&str],
+    max_age: Duration,
+    remove_all: bool,
+    report: &mut GcReport,
+) -> io::Result<()> {
+    let keep_names: Vec<String> = keep_urls
+        .iter()
+        .filter_map(|url| Some(repo_dir(url).file_name()?.to_string_lossy().to_string()))
+        .collect();
+
+    for kind in fs::read_dir(root)? {
+        let kind = kind?.path();
+        if !kind.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(&kind)? {
+            let path = entry?.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            if !path.is_dir() {
+                // Отметка без папки осталась от удалённой записи
+                if let Some(dir_name) = name.strip_suffix(DERIVED_MARKER_SUFFIX) {
+                    if !kind.join(dir_name).exists() {
+                        fs::remove_file(&path)?;
+                    }
+                }
+                continue;
+            }
+
+            let marker = derived_marker(&path);
+            let keep = !remove_all
+                && keep_names.iter().any(|keep| name.starts_with(keep.as_str()))
+                && fs::metadata(&marker)
+                    .and_then(|m| m.modified())
+                    .ok()
+                    .and_then(|t| t.elapsed().ok())
+                    .is_some_and(|age| age <= max_age);
+
+            if !keep {
+                report.freed_bytes += dir_size(&path);
+                fs::remove_dir_all(&path)?;
+                let _ = fs::remove_file(&marker);
+                report.removed += 1;
+            }
+        }
+    }
+
+    if remove_all {
+        fs::remove_dir_all(root)?;
+    }
+    Ok(())
+}
+
+/// Отметка использования подготовленной папки: `<имя>.last-used` рядом с ней
+fn derived_marker(dir: &Path) -> PathBuf {
+    let mut name = dir.file_name().unwrap_or_default().to_os_string();
+    name.push(DERIVED_MARKER_SUFFIX);
+    dir.with_file_name(name)
+}
+
+fn last_used(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path.join(".git").join(LAST_USED_FILE))
+        .and_then(|m| m.modified())
+        .ok()
+}
+
+fn dir_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{Signature, Time};
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("stm-cache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    /// Коммит всех файлов рабочей копии с тегом `tag`
+    fn commit(repo: &Repository, files: &[(&str, &str)], tag: &str, time: i64) -> git2::Oid {
+        let workdir = repo.workdir().unwrap();
+        let mut index = repo.index().unwrap();
+        for (name, content) in files {
+            fs::write(workdir.join(name), content).unwrap();
+            index.add_path(Path::new(name)).unwrap();
+        }
+        index.write().unwrap();
+
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::new("stm", "stm@example.com", &Time::new(time, 0)).unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&Commit> = parent.iter().collect();
+        let id = repo
+            .commit(Some("HEAD"), &signature, &signature, tag, &tree, &parents)
+            .unwrap();
+        repo.tag_lightweight(tag, repo.find_commit(id).unwrap().as_object(), false)
+            .unwrap();
+        id
+    }
+
+    /// Исходный репозиторий с двумя версиями и его клон в «кеше»
+    fn setup(name: &str) -> (PathBuf, PackRepo, git2::Oid, git2::Oid) {
+        let root = temp_root(name);
+        let origin = root.join("origin");
+        let repo = Repository::init(&origin).unwrap();
+        let first = commit(&repo, &[("a.jar", "a1")], "v1.0", 1_000);
+        let second = commit(&repo, &[("a.jar", "a2"), ("b.jar", "b")], "v1.1", 2_000);
+
+        let url = origin.to_string_lossy().to_string();
+        let pack = PackRepo::fetch_into(&url, root.join("cache").join("origin")).unwrap();
+        (root, pack, first, second)
+    }
+
+    #[test]
+    fn one_fetch_serves_tags_diff_and_checkout() {
+        let (root, pack, first, second) = setup("repo");
+
+        assert_eq!(pack.tags().unwrap(), ["v1.1", "v1.0"]);
+        assert_eq!(pack.latest_tag_matching("v1.*").unwrap().as_deref(), Some("v1.1"));
+        assert_eq!(pack.latest_tag_matching("v2.*").unwrap(), None);
+
+        let first_id = first.to_string();
+        let changes = pack.diff_jars(Some(&first_id), Some("v1.1")).unwrap();
+        let mut names: Vec<String> = changes
+            .iter()
+            .map(|change| match change {
+                JarChange::Added(name) => format!("+{}", name),
+                JarChange::Removed(name) => format!("-{}", name),
+                JarChange::Changed(name) => format!("~{}", name),
+            })
+            .collect();
+        names.sort();
+        assert_eq!(names, ["+b.jar", "~a.jar"]);
+
+        let (path, commit) = pack.checkout(Some("v1.0")).unwrap();
+        assert_eq!(commit, first.to_string());
+        assert!(!path.join("b.jar").exists());
+        let (path, commit) = pack.checkout(Some("v1.1")).unwrap();
+        assert_eq!(commit, second.to_string());
+        assert_eq!(fs::read_to_string(path.join("a.jar")).unwrap(), "a2");
+
+        assert!(pack.checkout(Some("v9")).unwrap_err().contains("v9"));
+        // Блокировка лежит рядом с клоном
+        assert!(root.join("cache").join("origin.lock").is_file());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn broken_clone_is_cloned_again() {
+        let (root, pack, _, second) = setup("broken");
+
+        // Клон не открывается
+        fs::remove_file(pack.path.join(".git").join("HEAD")).unwrap();
+        let (path, commit) = pack.checkout(Some("v1.1")).unwrap();
+        assert_eq!(commit, second.to_string());
+        assert_eq!(fs::read_to_string(path.join("b.jar")).unwrap(), "b");
+
+        // Клон открывается, но объектов для переключения нет
+        fs::remove_dir_all(pack.path.join(".git").join("objects")).unwrap();
+        fs::create_dir_all(pack.path.join(".git").join("objects")).unwrap();
+        let pack = PackRepo::fetch_into(&pack.url, pack.path.clone()).unwrap();
+        let (_, commit) = pack.checkout(Some("v1.1")).unwrap();
+        assert_eq!(commit, second.to_string());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn glob_matches_stars() {
+        assert!(glob_match("v1.*", "v1.2"));
+        assert!(glob_match("*-beta*", "1.0-beta2"));
+        assert!(glob_match("release", "release"));
+        assert!(!glob_match("v1.*", "v2.0"));
+        assert!(!glob_match("a*b*c", "abxb"));
+    }
+}
@@ -0,0 +1,1233 @@
+use clap::{Args, Parser, Subcommand};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+
+use crate::backup;
+use crate::cache;
+use crate::config::{Config, Instance, PackSource, SourceKind};
+use crate::curseforge;
+use crate::duplicates::{self, Keep};
+use crate::git_ops::{self, BuildType, InstallOptions};
+use crate::inventory::{self, Filter, InstalledMod, Origin};
+use crate::launchers::{self, GameInfo};
+use crate::metadata::{self, DependencyKind, JarInfo};
+use crate::modrinth;
+use crate::mods;
+use crate::mrpack::{self, ExportOptions};
+use crate::plan::InstallPlan;
+use crate::ui;
+
+/// Операция завершилась ошибкой
+pub const EXIT_FAILURE: u8 = 1;
+/// Не хватает аргументов (например, не указана папка Minecraft)
+const EXIT_USAGE: u8 = 2;
+
+/// StoryTime Hub - менеджер модов для проекта StoryTime-SMP.
+/// Без аргументов запускается интерактивное меню.
+#[derive(Parser)]
+#[command(name = "stm", version)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Установить моды сборки поверх текущих
+    Install(InstallArgs),
+    /// Удалить все моды и установить сборку заново
+    Reinstall(InstallArgs),
+    /// Удалить моды сборки, оставив моды игрока
+    Clean {
+        #[command(flatten)]
+        paths: PathArgs,
+        /// Не спрашивать подтверждение
+        #[arg(short, long)]
+        yes: bool,
+        /// Только показать, какие файлы будут удалены
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Восстановить папку модов из последнего снимка
+    Rollback {
+        #[command(flatten)]
+        paths: PathArgs,
+        /// Показать снимки вместо восстановления
+        #[arg(long)]
+        list: bool,
+    },
+    /// Показать моды экземпляра
+    List {
+        #[command(flatten)]
+        paths: PathArgs,
+        /// Искать в названии, id, имени файла и источнике
+        #[arg(short = 'q', long)]
+        search: Option<String>,
+        /// Только моды из этого источника
+        #[arg(short, long, value_enum)]
+        origin: Option<Origin>,
+        /// Только моды для этого лоадера
+        #[arg(short, long, value_parser = modrinth::LOADERS)]
+        loader: Option<String>,
+        /// Только включённые моды
+        #[arg(long, conflicts_with = "disabled")]
+        enabled: bool,
+        /// Только отключённые моды
+        #[arg(long)]
+        disabled: bool,
+        /// Вывести JSON вместо таблицы
+        #[arg(long)]
+        json: bool,
+    },
+    /// Найти jar с одним и тем же модом и удалить лишние
+    Duplicates {
+        #[command(flatten)]
+        paths: PathArgs,
+        /// Какой jar оставить; без флага дубликаты только выводятся
+        #[arg(short, long, value_enum)]
+        keep: Option<Keep>,
+    },
+    /// Показать метаданные модов из jar-файлов
+    Inspect {
+        /// Пути к jar
+        #[arg(required = true)]
+        jars: Vec<PathBuf>,
+    },
+    /// Работа с модами Modrinth
+    #[command(subcommand)]
+    Modrinth(ModrinthCommand),
+    /// Сборки в формате Modrinth (.mrpack)
+    #[command(subcommand)]
+    Mrpack(MrpackCommand),
+    /// Импорт сборок из других форматов
+    #[command(subcommand)]
+    Import(ImportCommand),
+    /// Настройки stm
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Кеш клонов репозиториев сборок
+    #[command(subcommand)]
+    Cache(CacheCommand),
+    /// Источники сборок: репозитории, pack.toml и .mrpack
+    #[command(subcommand)]
+    Source(SourceCommand),
+    /// Сохранённые экземпляры игры
+    #[command(subcommand)]
+    Instance(InstanceCommand),
+    /// Переключить канал обновлений и установить сборку
+    Channel {
+        /// Имя канала (stable, beta или свой из конфига)
+        name: String,
+        #[command(flatten)]
+        paths: PathArgs,
+        /// Тип сборки
+        #[arg(short, long, value_enum, default_value = "client")]
+        build: BuildType,
+        /// Источник сборки (по умолчанию — первый для типа сборки)
+        #[arg(short, long)]
+        source: Option<String>,
+        /// Не спрашивать подтверждение плана установки
+        #[arg(short, long)]
+        yes: bool,
+        /// Только показать изменения, не переключая канал
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// Папка Minecraft и папка модов
+#[derive(Args)]
+pub struct PathArgs {
+    /// Сохранённый экземпляр (см. `stm instance list`)
+    #[arg(short, long, conflicts_with_all = ["path", "mods_dir"])]
+    pub instance: Option<String>,
+    /// Папка Minecraft (по умолчанию — из конфига)
+    #[arg(short, long)]
+    pub path: Option<PathBuf>,
+    /// Папка модов (по умолчанию — найденная внутри папки Minecraft)
+    #[arg(long)]
+    pub mods_dir: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct InstallArgs {
+    #[command(flatten)]
+    pub paths: PathArgs,
+    /// Тип сборки (по умолчанию — как у источника экземпляра, иначе клиентская)
+    #[arg(short, long, value_enum)]
+    pub build: Option<BuildType>,
+    /// Источник сборки (по умолчанию — источник экземпляра или первый для типа сборки)
+    #[arg(short, long)]
+    pub source: Option<String>,
+    /// Тег, ветка или коммит сборки; запоминается для экземпляра
+    #[arg(long = "ref", value_name = "REF")]
+    pub git_ref: Option<String>,
+    /// Снять закрепление и установить последнюю версию
+    #[arg(long, conflicts_with = "git_ref")]
+    pub unpin: bool,
+    /// Не спрашивать подтверждение плана установки
+    #[arg(short, long)]
+    pub yes: bool,
+    /// Только показать, какие файлы будут удалены, скопированы или пропущены
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+#[derive(Subcommand)]
+pub enum ModrinthCommand {
+    /// Скачать самую свежую совместимую версию мода
+    Add {
+        /// id или slug проекта на Modrinth
+        project: String,
+        #[command(flatten)]
+        paths: PathArgs,
+        /// Версия Minecraft (по умолчанию — из файлов лаунчера)
+        #[arg(short = 'g', long)]
+        game_version: Option<String>,
+        /// Лоадер (по умолчанию — из файлов лаунчера)
+        #[arg(short, long, value_parser = modrinth::LOADERS)]
+        loader: Option<String>,
+    },
+    /// Проверить обновления модов по хешам файлов
+    Update {
+        #[command(flatten)]
+        paths: PathArgs,
+        /// Версия Minecraft (по умолчанию — из файлов лаунчера)
+        #[arg(short = 'g', long)]
+        game_version: Option<String>,
+        /// Лоадер (по умолчанию — из файлов лаунчера)
+        #[arg(short, long, value_parser = modrinth::LOADERS)]
+        loader: Option<String>,
+        /// Установить все найденные обновления
+        #[arg(long)]
+        apply: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum MrpackCommand {
+    /// Установить сборку из .mrpack файла или по ссылке
+    Install {
+        /// Путь к .mrpack или ссылка на него
+        source: String,
+        #[command(flatten)]
+        paths: PathArgs,
+        /// Сторона: файлы, не нужные ей, пропускаются
+        #[arg(short, long, value_enum, default_value = "client")]
+        build: BuildType,
+    },
+    /// Экспортировать моды экземпляра в .mrpack
+    Export {
+        #[command(flatten)]
+        paths: PathArgs,
+        /// Куда сохранить архив
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Название сборки
+        #[arg(long, default_value = "StoryTime")]
+        name: String,
+        /// Версия сборки
+        #[arg(long = "pack-version", default_value = "1.0.0")]
+        version_id: String,
+        /// Версия Minecraft (по умолчанию — из файлов лаунчера)
+        #[arg(short = 'g', long)]
+        game_version: Option<String>,
+        /// Лоадер (по умолчанию — из файлов лаунчера)
+        #[arg(short, long, value_parser = modrinth::LOADERS)]
+        loader: Option<String>,
+        /// Версия лоадера (по умолчанию — из файлов лаунчера)
+        #[arg(long)]
+        loader_version: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ImportCommand {
+    /// Импортировать экспорт сборки CurseForge (.zip)
+    Curseforge {
+        /// Путь к .zip
+        zip: PathBuf,
+        #[command(flatten)]
+        paths: PathArgs,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Установить папку Minecraft по умолчанию
+    SetDefault {
+        /// Папка Minecraft
+        path: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommand {
+    /// Удалить неиспользуемые и повреждённые клоны
+    Gc {
+        /// Удалять клоны, не использовавшиеся дольше стольких дней
+        #[arg(long, default_value_t = 30)]
+        max_age_days: u64,
+        /// Удалить весь кеш
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SourceCommand {
+    /// Показать источники сборок
+    List,
+    /// Добавить источник
+    Add {
+        /// Имя источника
+        name: String,
+        /// Репозиторий, ссылка на pack.toml или .mrpack (можно file://)
+        #[arg(long)]
+        url: String,
+        /// Формат источника
+        #[arg(short = 't', long = "type", value_enum, default_value = "git")]
+        kind: SourceKind,
+        /// Ветка git-репозитория
+        #[arg(long)]
+        branch: Option<String>,
+        /// Сторона сборки
+        #[arg(short, long, value_enum, default_value = "client")]
+        side: BuildType,
+    },
+    /// Изменить источник; не указанные поля остаются прежними
+    Edit {
+        /// Имя источника
+        name: String,
+        /// Новое имя
+        #[arg(long)]
+        rename: Option<String>,
+        #[arg(long)]
+        url: Option<String>,
+        #[arg(short = 't', long = "type", value_enum)]
+        kind: Option<SourceKind>,
+        /// Ветка git-репозитория; пустая строка — ветка по умолчанию
+        #[arg(long)]
+        branch: Option<String>,
+        #[arg(short, long, value_enum)]
+        side: Option<BuildType>,
+    },
+    /// Удалить источник
+    Remove {
+        /// Имя источника
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum InstanceCommand {
+    /// Показать сохранённые экземпляры
+    List,
+    /// Сохранить экземпляр
+    Add {
+        /// Имя экземпляра
+        name: String,
+        /// Папка экземпляра
+        #[arg(short, long)]
+        path: PathBuf,
+        /// Папка модов относительно папки экземпляра
+        #[arg(long, default_value = "mods")]
+        mods_dir: String,
+        /// Источник сборки, которому следует экземпляр
+        #[arg(short, long)]
+        source: Option<String>,
+        /// Версия Minecraft
+        #[arg(short = 'g', long)]
+        game_version: Option<String>,
+        /// Лоадер
+        #[arg(short, long, value_parser = modrinth::LOADERS)]
+        loader: Option<String>,
+    },
+    /// Изменить экземпляр; не указанные поля остаются прежними
+    Edit {
+        /// Имя экземпляра
+        name: String,
+        /// Новое имя
+        #[arg(long)]
+        rename: Option<String>,
+        /// Источник сборки; пустая строка — не следовать сборке
+        #[arg(short, long)]
+        source: Option<String>,
+        #[arg(short = 'g', long)]
+        game_version: Option<String>,
+        #[arg(short, long, value_parser = modrinth::LOADERS)]
+        loader: Option<String>,
+    },
+    /// Забыть экземпляр (файлы не удаляются)
+    Remove {
+        /// Имя экземпляра
+        name: String,
+    },
+}
+
+/// Выполнение подкоманды и получение кода завершения
+pub fn run(command: Command, config: &mut Config) -> ExitCode {
+    match command {
+        Command::Install(args) => run_install(&args, config, false),
+        Command::Reinstall(args) => run_install(&args, config, true),
+        Command::Clean { paths, yes, dry_run } => {
+            let (_, mods_path) = match resolve_paths(&paths, config) {
+                Ok(paths) => paths,
+                Err(code) => return code,
+            };
+
+            let confirm = |plan: &InstallPlan| yes || ui::confirm_plan(plan);
+            match git_ops::clean_pack_mods(&mods_path, dry_run, config.backup_limit, &confirm) {
+                Ok(count) => {
+                    if !dry_run {
+                        println!("󰄬 Удалено {} модов", count);
+                    }
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    eprintln!("󰅖 {}", e);
+                    ExitCode::from(EXIT_FAILURE)
+                }
+            }
+        }
+        Command::Rollback { paths, list } => {
+            let (_, mods_path) = match resolve_paths(&paths, config) {
+                Ok(paths) => paths,
+                Err(code) => return code,
+            };
+            run_rollback(&mods_path, list, config.backup_limit)
+        }
+        Command::Modrinth(ModrinthCommand::Add { project, paths, game_version, loader }) => {
+            let (game_path, mods_path) = match resolve_paths(&paths, config) {
+                Ok(paths) => paths,
+                Err(code) => return code,
+            };
+            let game = match resolve_game(config, &game_path, &mods_path, game_version, loader) {
+                Ok(game) => game,
+                Err(code) => return code,
+            };
+            let (game_version, loader) = (game.game_version, game.loader.unwrap_or_default());
+
+            match modrinth::add_project(&mods_path, &project, &game_version, &loader, config.download_concurrency) {
+                Ok(filenames) => {
+                    for filename in filenames {
+                        println!("󰄬 Успешно скачан: {}", filename);
+                    }
+                    warn_duplicates(&mods_path);
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    eprintln!("󰅖 Ошибка: {}", e);
+                    ExitCode::from(EXIT_FAILURE)
+                }
+            }
+        }
+        Command::Modrinth(ModrinthCommand::Update { paths, game_version, loader, apply }) => {
+            let (game_path, mods_path) = match resolve_paths(&paths, config) {
+                Ok(paths) => paths,
+                Err(code) => return code,
+            };
+            let game = match resolve_game(config, &game_path, &mods_path, game_version, loader) {
+                Ok(game) => game,
+                Err(code) => return code,
+            };
+            let loader = game.loader.unwrap_or_default();
+            run_modrinth_update(&mods_path, &game.game_version, &loader, apply, config)
+        }
+        Command::Mrpack(MrpackCommand::Install { source, paths, build }) => {
+            let (_, mods_path) = match resolve_paths(&paths, config) {
+                Ok(paths) => paths,
+                Err(code) => return code,
+            };
+
+            let result = mrpack::install(
+                &source,
+                &mods_path,
+                build,
+                config.download_concurrency,
+                config.backup_limit,
+            );
+            if let Ok(report) = &result {
+                if let Some(game_version) = report.dependencies.get("minecraft") {
+                    save_game_info(config, &mods_path, game_version, report.loader());
+                }
+            }
+            let wrote_files = result.as_ref().is_ok_and(mrpack::InstallReport::wrote_files);
+            let complete = mrpack::print_install_report(result);
+            // Дубликаты проверяются и после частичной установки: файлы уже в папке
+            if wrote_files {
+                warn_duplicates(&mods_path);
+            }
+            if complete {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::from(EXIT_FAILURE)
+            }
+        }
+        Command::Mrpack(MrpackCommand::Export {
+            paths,
+            output,
+            name,
+            version_id,
+            game_version,
+            loader,
+            loader_version,
+        }) => {
+            let (game_path, mods_path) = match resolve_paths(&paths, config) {
+                Ok(paths) => paths,
+                Err(code) => return code,
+            };
+            let game = match resolve_game(config, &game_path, &mods_path, game_version, loader) {
+                Ok(game) => game,
+                Err(code) => return code,
+            };
+            let loader_version = match loader_version.or(game.loader_version) {
+                Some(version) => version,
+                None => {
+                    eprintln!("󰅖 Не удалось определить версию лоадера, укажите --loader-version");
+                    return ExitCode::from(EXIT_USAGE);
+                }
+            };
+
+            let options = ExportOptions {
+                name,
+                version_id,
+                game_version: game.game_version,
+                loader: game.loader.unwrap_or_default(),
+                loader_version,
+            };
+            match mrpack::export(&mods_path, &output, &options) {
+                Ok(report) => {
+                    println!("󰄬 Сборка сохранена: {}", output.display());
+                    println!("   С Modrinth: {}, в overrides: {}", report.downloads, report.overrides);
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    eprintln!("󰅖 {}", e);
+                    ExitCode::from(EXIT_FAILURE)
+                }
+            }
+        }
+        Command::Import(ImportCommand::Curseforge { zip, paths }) => {
+            let (_, mods_path) = match resolve_paths(&paths, config) {
+                Ok(paths) => paths,
+                Err(code) => return code,
+            };
+
+            let result = curseforge::import(
+                &zip,
+                &mods_path,
+                &config.curseforge,
+                config.download_concurrency,
+                config.backup_limit,
+            );
+            if let Ok(report) = &result {
+                let loader = report.loader.as_ref().map(|(loader, _)| loader.as_str());
+                save_game_info(config, &mods_path, &report.game_version, loader);
+            }
+            let wrote_files = result.as_ref().is_ok_and(curseforge::ImportReport::wrote_files);
+            let complete = curseforge::print_import_report(result);
+            if wrote_files {
+                warn_duplicates(&mods_path);
+            }
+            if complete {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::from(EXIT_FAILURE)
+            }
+        }
+        Command::Config(ConfigCommand::SetDefault { path }) => {
+            if !path.is_dir() {
+                eprintln!("󰅖 Папка не существует: {}", path.display());
+                return ExitCode::from(EXIT_USAGE);
+            }
+
+            match config.set_default_path(&path) {
+                Ok(()) => {
+                    println!("󰄬 Папка по умолчанию установлена: {}", path.display());
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    eprintln!("󰅖 Ошибка сохранения конфига: {}", e);
+                    ExitCode::from(EXIT_FAILURE)
+                }
+            }
+        }
+        Command::Channel { name, paths, build, source, yes, dry_run } => {
+            let source = match find_source(config, source.as_deref(), build) {
+                Ok(source) => source,
+                Err(code) => return code,
+            };
+            run_switch_channel(&name, &paths, source, yes, dry_run, config)
+        }
+        Command::List { paths, search, origin, loader, enabled, disabled, json } => {
+            let (_, mods_path) = match resolve_paths(&paths, config) {
+                Ok(paths) => paths,
+                Err(code) => return code,
+            };
+            let filter = Filter {
+                query: search,
+                origin,
+                loader,
+                enabled: if enabled { Some(true) } else if disabled { Some(false) } else { None },
+            };
+            run_list(&mods_path, &filter, json)
+        }
+        Command::Duplicates { paths, keep } => {
+            let (_, mods_path) = match resolve_paths(&paths, config) {
+                Ok(paths) => paths,
+                Err(code) => return code,
+            };
+            run_duplicates(&mods_path, keep, config)
+        }
+        Command::Inspect { jars } => run_inspect(&jars),
+        Command::Source(command) => run_source(command, config),
+        Command::Instance(command) => run_instance(command, config),
+        Command::Cache(CacheCommand::Gc { max_age_days, all }) => {
+            let keep_urls: Vec<&str> = config.sources.iter().map(|s| s.url.as_str()).collect();
+            let max_age = Duration::from_secs(max_age_days * 24 * 60 * 60);
+
+            match cache::gc(&keep_urls, max_age, all) {
+                Ok(report) => {
+                    println!(
+                        "󰄬 Удалено из кеша (клоны и подготовленные сборки): {}, освобождено {:.1} МБ",
+                        report.removed,
+                        report.freed_bytes as f64 / 1024.0 / 1024.0
+                    );
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    eprintln!("󰅖 Ошибка очистки кеша: {}", e);
+                    ExitCode::from(EXIT_FAILURE)
+                }
+            }
+        }
+    }
+}
+
+/// Установка или переустановка сборки
+fn run_install(args: &InstallArgs, config: &mut Config, clean_install: bool) -> ExitCode {
+    let (_, mods_path) = match resolve_paths(&args.paths, config) {
+        Ok(paths) => paths,
+        Err(code) => return code,
+    };
+    let source = match install_source(config, &mods_path, args) {
+        Ok(source) => source,
+        Err(code) => return code,
+    };
+
+    // Без --ref используется закреплённая версия экземпляра
+    let git_ref = if args.unpin {
+        None
+    } else {
+        args.git_ref.clone().or_else(|| config.get_git_ref(&mods_path))
+    };
+
+    let options = InstallOptions {
+        source,
+        clean_install,
+        git_ref,
+        channel: config.channel_spec(&mods_path),
+        dry_run: args.dry_run,
+        backup_limit: config.backup_limit,
+        download_concurrency: config.download_concurrency,
+        curseforge: config.curseforge.clone(),
+        repo: None,
+    };
+
+    let confirm = |plan: &InstallPlan| {
+        if args.yes {
+            ui::print_plan(plan);
+            true
+        } else {
+            ui::confirm_plan(plan)
+        }
+    };
+
+    match git_ops::install_mods(&mods_path, &options, &confirm) {
+        Ok(_) if args.dry_run => ExitCode::SUCCESS,
+        Ok(_) => {
+            if args.git_ref.is_some() || args.unpin {
+                if let Err(e) = config.set_git_ref(&mods_path, options.git_ref.as_deref()) {
+                    eprintln!("󰅖 Ошибка сохранения конфига: {}", e);
+                    return ExitCode::from(EXIT_FAILURE);
+                }
+            }
+            if let Err(e) = config.record_sync(&mods_path, &options.source) {
+                eprintln!("󰅖 Ошибка сохранения конфига: {}", e);
+                return ExitCode::from(EXIT_FAILURE);
+            }
+            println!("󰄬 Установка завершена!");
+            warn_duplicates(&mods_path);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("󰅖 Ошибка: {}", e);
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
+
+/// Источник для установки: `--source`, иначе источник, которому следует экземпляр,
+/// иначе первый для `--build`. `--build` другой стороны, чем у источника
+/// экземпляра, отклоняется: иначе экземпляр молча получил бы не ту сборку.
+fn install_source(config: &Config, mods_path: &Path, args: &InstallArgs) -> Result<PackSource, ExitCode> {
+    let build = args.build.unwrap_or(BuildType::Client);
+    if args.source.is_some() {
+        return find_source(config, args.source.as_deref(), build);
+    }
+
+    let followed = config.instance_for(mods_path).and_then(|instance| instance.source.as_deref());
+    let source = find_source(config, followed, build)?;
+    match args.build {
+        Some(build) if followed.is_some() && source.side != build => {
+            eprintln!(
+                "󰅖 Экземпляр следует источнику '{}' другой стороны; чтобы сменить сборку, укажите --source",
+                source.name
+            );
+            Err(ExitCode::from(EXIT_USAGE))
+        }
+        _ => Ok(source),
+    }
+}
+
+/// Поиск обновлений модов и установка всех найденных при `apply`
+fn run_modrinth_update(
+    mods_path: &Path,
+    game_version: &str,
+    loader: &str,
+    apply: bool,
+    config: &Config,
+) -> ExitCode {
+    let updates = match modrinth::find_updates(mods_path, game_version, loader) {
+        Ok(updates) => updates,
+        Err(e) => {
+            eprintln!("󰅖 Ошибка проверки обновлений: {}", e);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+
+    if updates.is_empty() {
+        println!("󰄬 Все моды с Modrinth в актуальном состоянии");
+        return ExitCode::SUCCESS;
+    }
+
+    for update in &updates {
+        println!("󰏗 {}: {} → {}", update.file_name, update.current_version, update.new_version_name());
+    }
+
+    if !apply {
+        return ExitCode::SUCCESS;
+    }
+
+    if let Err(e) = backup::snapshot(mods_path, config.backup_limit) {
+        eprintln!("󰅖 Не удалось сохранить снимок: {}", e);
+        return ExitCode::from(EXIT_FAILURE);
+    }
+
+    let mut failed = false;
+    let updates: Vec<&modrinth::ModUpdate> = updates.iter().collect();
+    let results = modrinth::apply_updates(mods_path, &updates, config.download_concurrency);
+    for (update, result) in updates.iter().zip(results) {
+        match result {
+            Ok(filename) => println!("󰄬 Обновлён: {}", filename),
+            Err(e) => {
+                eprintln!("󰅖 Ошибка обновления {}: {}", update.file_name, e);
+                failed = true;
+            }
+        }
+    }
+    warn_duplicates(mods_path);
+
+    if failed {
+        ExitCode::from(EXIT_FAILURE)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Вывод дубликатов модов или удаление лишних jar по правилу `keep`
+fn run_duplicates(mods_path: &Path, keep: Option<Keep>, config: &Config) -> ExitCode {
+    let found = match duplicates::find(mods_path) {
+        Ok(found) => found,
+        Err(e) => {
+            eprintln!("󰅖 Ошибка чтения {}: {}", mods_path.display(), e);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+
+    if found.is_empty() {
+        println!("󰄬 Дубликатов нет");
+        return ExitCode::SUCCESS;
+    }
+    ui::print_duplicates(&found);
+
+    let keep = match keep {
+        Some(keep) => keep,
+        None => return ExitCode::SUCCESS,
+    };
+
+    if let Err(e) = backup::snapshot(mods_path, config.backup_limit) {
+        eprintln!("󰅖 Не удалось сохранить снимок: {}", e);
+        return ExitCode::from(EXIT_FAILURE);
+    }
+    match duplicates::resolve(mods_path, &found, keep) {
+        Ok(removed) => {
+            for file_name in &removed {
+                println!("  - {}", file_name);
+            }
+            println!("󰄬 Удалено дубликатов: {}", removed.len());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("󰅖 Ошибка удаления дубликатов: {}", e);
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
+
+/// Предупреждение о дубликатах после установки; удаляет их `stm duplicates --keep`
+fn warn_duplicates(mods_path: &Path) {
+    match duplicates::find(mods_path) {
+        Ok(found) if !found.is_empty() => {
+            println!();
+            ui::print_duplicates(&found);
+            println!("󰝚 Удалить лишние jar: `stm duplicates --keep newest` или `--keep pack` для той же папки");
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("󰅖 Ошибка проверки дубликатов: {}", e),
+    }
+}
+
+/// Вывод модов экземпляра таблицей или в JSON
+fn run_list(mods_path: &Path, filter: &Filter, json: bool) -> ExitCode {
+    let mods: Vec<InstalledMod> = match inventory::scan(mods_path) {
+        Ok(mods) => mods.into_iter().filter(|m| filter.matches(m)).collect(),
+        Err(e) => {
+            eprintln!("󰅖 Ошибка чтения {}: {}", mods_path.display(), e);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+
+    if json {
+        match serde_json::to_string_pretty(&mods) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("󰅖 Ошибка: {}", e);
+                return ExitCode::from(EXIT_FAILURE);
+            }
+        }
+    } else {
+        inventory::print_table(&mods);
+    }
+    ExitCode::SUCCESS
+}
+
+/// Вывод метаданных модов из jar; код ошибки, если хоть один jar не прочитан
+fn run_inspect(jars: &[PathBuf]) -> ExitCode {
+    let mut failed = false;
+    for path in jars {
+        match metadata::read_jar(path) {
+            Ok(info) => {
+                println!("󰏗 {}", path.display());
+                print_jar_info(&info, 1);
+            }
+            Err(e) => {
+                eprintln!("󰅖 {}", e);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::from(EXIT_FAILURE)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Моды jar и вложенных jar с отступом по глубине вложенности
+fn print_jar_info(info: &JarInfo, depth: usize) {
+    let indent = "  ".repeat(depth);
+    if info.mods.is_empty() {
+        println!("{}Метаданные мода не найдены", indent);
+    }
+
+    for mod_info in &info.mods {
+        match &mod_info.version {
+            Some(version) => println!("{}{} ({}) {}", indent, mod_info.name, mod_info.id, version),
+            None => println!("{}{} ({})", indent, mod_info.name, mod_info.id),
+        }
+        println!("{}  Лоадер: {}, сторона: {}", indent, mod_info.loader, mod_info.side.label());
+        if !mod_info.authors.is_empty() {
+            println!("{}  Авторы: {}", indent, mod_info.authors.join(", "));
+        }
+        for dependency in &mod_info.dependencies {
+            let marker = match dependency.kind {
+                DependencyKind::Required => "+",
+                DependencyKind::Optional => "?",
+                DependencyKind::Incompatible => "!",
+            };
+            match &dependency.versions {
+                Some(versions) => println!("{}  {} {} {}", indent, marker, dependency.id, versions),
+                None => println!("{}  {} {}", indent, marker, dependency.id),
+            }
+        }
+    }
+
+    for (path, nested) in &info.nested {
+        println!("{}󰏗 {}", indent, path);
+        print_jar_info(nested, depth + 1);
+    }
+}
+
+/// Откат к последнему снимку или вывод списка снимков
+fn run_rollback(mods_path: &Path, list: bool, backup_limit: usize) -> ExitCode {
+    let backups = match backup::list(mods_path) {
+        Ok(backups) => backups,
+        Err(e) => {
+            eprintln!("󰅖 Ошибка чтения снимков: {}", e);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+
+    if list {
+        for path in &backups {
+            println!("{}", path.display());
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    let latest = match backups.first() {
+        Some(latest) => latest,
+        None => {
+            eprintln!("󰅖 Снимков пока нет");
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    };
+
+    match backup::restore(mods_path, latest, backup_limit) {
+        Ok(count) => {
+            println!("󰄬 Восстановлено {} файлов из {}", count, latest.display());
+            println!("   Прежние моды сохранены в новый снимок");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("󰅖 Ошибка восстановления: {}", e);
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
+
+/// Переключение канала с выводом изменений модов
+fn run_switch_channel(
+    name: &str,
+    paths: &PathArgs,
+    source: PackSource,
+    yes: bool,
+    dry_run: bool,
+    config: &mut Config,
+) -> ExitCode {
+    let (_, mods_path) = match resolve_paths(paths, config) {
+        Ok(paths) => paths,
+        Err(code) => return code,
+    };
+
+    let channel = match config.channels.get(name) {
+        Some(channel) => channel.clone(),
+        None => {
+            let known: Vec<&str> = config.channels.keys().map(|k| k.as_str()).collect();
+            eprintln!("󰅖 Неизвестный канал '{}'. Доступны: {}", name, known.join(", "));
+            return ExitCode::from(EXIT_USAGE);
+        }
+    };
+
+    let mut options = InstallOptions {
+        source,
+        clean_install: false,
+        git_ref: None,
+        channel: Some(channel),
+        dry_run,
+        backup_limit: config.backup_limit,
+        download_concurrency: config.download_concurrency,
+        curseforge: config.curseforge.clone(),
+        repo: None,
+    };
+
+    match git_ops::pending_changes(&mods_path, &mut options) {
+        Ok(changes) => ui::print_jar_changes(&changes),
+        Err(e) => {
+            eprintln!("󰅖 Ошибка: {}", e);
+            return ExitCode::from(EXIT_FAILURE);
+        }
+    }
+
+    let confirm = |plan: &InstallPlan| {
+        if yes {
+            ui::print_plan(plan);
+            true
+        } else {
+            ui::confirm_plan(plan)
+        }
+    };
+
+    if let Err(e) = git_ops::install_mods(&mods_path, &options, &confirm) {
+        eprintln!("󰅖 Ошибка: {}", e);
+        return ExitCode::from(EXIT_FAILURE);
+    }
+
+    if dry_run {
+        return ExitCode::SUCCESS;
+    }
+
+    let saved = config
+        .set_channel(&mods_path, name)
+        .and_then(|()| config.record_sync(&mods_path, &options.source));
+    match saved {
+        Ok(()) => {
+            println!("󰄬 Канал переключён: {}", name);
+            warn_duplicates(&mods_path);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("󰅖 Ошибка сохранения конфига: {}", e);
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
+
+/// Версия игры и лоадер для команд Modrinth: из аргументов, из файлов лаунчера
+/// или из сохранённого экземпляра. Лоадер в результате всегда задан.
+fn resolve_game(
+    config: &Config,
+    game_path: &Path,
+    mods_path: &Path,
+    game_version: Option<String>,
+    loader: Option<String>,
+) -> Result<GameInfo, ExitCode> {
+    let detected = launchers::detect_game(game_path);
+    let saved = config.instance_for(mods_path);
+
+    // Версия лоадера из лаунчера годится, только если совпадает сам лоадер
+    let loader_version = detected
+        .as_ref()
+        .filter(|info| loader.is_none() || info.loader == loader)
+        .and_then(|info| info.loader_version.clone());
+    let game_version = game_version
+        .or_else(|| detected.as_ref().map(|info| info.game_version.clone()))
+        .or_else(|| saved.and_then(|instance| instance.game_version.clone()));
+    let loader = loader
+        .or_else(|| detected.and_then(|info| info.loader))
+        .or_else(|| saved.and_then(|instance| instance.loader.clone()))
+        .filter(|loader| modrinth::LOADERS.contains(&loader.as_str()));
+
+    match (game_version, loader) {
+        (Some(game_version), Some(loader)) => Ok(GameInfo {
+            game_version,
+            loader: Some(loader),
+            loader_version,
+        }),
+        (None, _) => {
+            eprintln!("󰅖 Не удалось определить версию Minecraft, укажите --game-version");
+            Err(ExitCode::from(EXIT_USAGE))
+        }
+        (_, None) => {
+            eprintln!("󰅖 Не удалось определить лоадер, укажите --loader");
+            Err(ExitCode::from(EXIT_USAGE))
+        }
+    }
+}
+
+/// Запоминание версии игры и лоадера экземпляра; ошибка конфига не прерывает установку
+fn save_game_info(config: &mut Config, mods_path: &Path, game_version: &str, loader: Option<&str>) {
+    if let Err(e) = config.set_game_info(mods_path, game_version, loader) {
+        eprintln!("󰅖 Ошибка сохранения конфига: {}", e);
+    }
+}
+
+/// Источник по имени из `--source` или первый источник для типа сборки
+fn find_source(config: &Config, name: Option<&str>, build: BuildType) -> Result<PackSource, ExitCode> {
+    let source = match name {
+        Some(name) => config.source(name),
+        None => config.default_source(build),
+    };
+
+    source.cloned().ok_or_else(|| {
+        match name {
+            Some(name) => eprintln!("󰅖 Неизвестный источник '{}': см. `stm source list`", name),
+            None => eprintln!("󰅖 Нет источника для этого типа сборки: добавьте его через `stm source add`"),
+        }
+        ExitCode::from(EXIT_USAGE)
+    })
+}
+
+/// Управление источниками сборок
+fn run_source(command: SourceCommand, config: &mut Config) -> ExitCode {
+    let result = match command {
+        SourceCommand::List => {
+            for source in &config.sources {
+                println!("{}", ui::format_source(source));
+            }
+            return ExitCode::SUCCESS;
+        }
+        SourceCommand::Add { name, url, kind, branch, side } => {
+            config.add_source(PackSource { name, url, kind, branch, side })
+        }
+        SourceCommand::Edit { name, rename, url, kind, branch, side } => {
+            let mut source = match config.source(&name) {
+                Some(source) => source.clone(),
+                None => {
+                    eprintln!("󰅖 Неизвестный источник '{}': см. `stm source list`", name);
+                    return ExitCode::from(EXIT_USAGE);
+                }
+            };
+            if let Some(rename) = rename {
+                source.name = rename;
+            }
+            if let Some(url) = url {
+                source.url = url;
+            }
+            if let Some(kind) = kind {
+                source.kind = kind;
+            }
+            if let Some(branch) = branch {
+                source.branch = (!branch.is_empty()).then_some(branch);
+            }
+            if let Some(side) = side {
+                source.side = side;
+            }
+            config.update_source(&name, source)
+        }
+        SourceCommand::Remove { name } => config.remove_source(&name),
+    };
+
+    match result {
+        Ok(()) => {
+            println!("󰄬 Источники сборок сохранены");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("󰅖 {}", e);
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
+
+/// Управление сохранёнными экземплярами
+fn run_instance(command: InstanceCommand, config: &mut Config) -> ExitCode {
+    let result = match command {
+        InstanceCommand::List => {
+            for instance in &config.instances {
+                let synced = instance.last_synced.as_deref().unwrap_or("ещё не было");
+                println!("{} (последняя установка: {})", ui::format_instance(instance), synced);
+            }
+            return ExitCode::SUCCESS;
+        }
+        InstanceCommand::Add { name, path, mods_dir, source, game_version, loader } => {
+            if !path.is_dir() {
+                eprintln!("󰅖 Папка не существует: {}", path.display());
+                return ExitCode::from(EXIT_USAGE);
+            }
+            let build_type = match source.as_deref().map(|name| config.source(name)) {
+                Some(Some(source)) => Some(source.side),
+                Some(None) => {
+                    eprintln!("󰅖 Неизвестный источник: см. `stm source list`");
+                    return ExitCode::from(EXIT_USAGE);
+                }
+                None => None,
+            };
+
+            let mut instance = Instance::from_mods_path(&path.join(&mods_dir));
+            instance.name = name;
+            instance.path = path.display().to_string();
+            instance.mods_dir = mods_dir;
+            instance.source = source;
+            instance.build_type = build_type;
+            instance.game_version = game_version;
+            instance.loader = loader;
+            config.add_instance(instance)
+        }
+        InstanceCommand::Edit { name, rename, source, game_version, loader } => {
+            let mut instance = match config.instance(&name) {
+                Some(instance) => instance.clone(),
+                None => {
+                    eprintln!("󰅖 Неизвестный экземпляр '{}': см. `stm instance list`", name);
+                    return ExitCode::from(EXIT_USAGE);
+                }
+            };
+            if let Some(rename) = rename {
+                instance.name = rename;
+            }
+            match source.as_deref() {
+                Some("") => {
+                    instance.source = None;
+                    instance.build_type = None;
+                }
+                Some(source_name) => match config.source(source_name) {
+                    Some(source) => {
+                        instance.source = Some(source.name.clone());
+                        instance.build_type = Some(source.side);
+                    }
+                    None => {
+                        eprintln!("󰅖 Неизвестный источник '{}': см. `stm source list`", source_name);
+                        return ExitCode::from(EXIT_USAGE);
+                    }
+                },
+                None => {}
+            }
+            if game_version.is_some() {
+                instance.game_version = game_version;
+            }
+            if loader.is_some() {
+                instance.loader = loader;
+            }
+            config.update_instance(&name, instance)
+        }
+        InstanceCommand::Remove { name } => config.remove_instance(&name),
+    };
+
+    match result {
+        Ok(()) => {
+            println!("󰄬 Экземпляры сохранены");
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("󰅖 {}", e);
+            ExitCode::from(EXIT_FAILURE)
+        }
+    }
+}
+
+/// Определение папки Minecraft и папки модов из аргументов и конфига
+fn resolve_paths(paths: &PathArgs, config: &Config) -> Result<(PathBuf, PathBuf), ExitCode> {
+    if let Some(name) = &paths.instance {
+        return match config.instance(name) {
+            Some(instance) => Ok((PathBuf::from(&instance.path), instance.mods_path())),
+            None => {
+                eprintln!("󰅖 Неизвестный экземпляр '{}': см. `stm instance list`", name);
+                Err(ExitCode::from(EXIT_USAGE))
+            }
+        };
+    }
+
+    let minecraft_path = match paths.path.clone().or_else(|| config.get_default_path()) {
+        Some(path) => path,
+        None => {
+            eprintln!("󰅖 Не указана папка Minecraft: используйте --path или `stm config set-default`");
+            return Err(ExitCode::from(EXIT_USAGE));
+        }
+    };
+
+    if !minecraft_path.is_dir() {
+        eprintln!("󰅖 Папка не существует: {}", minecraft_path.display());
+        return Err(ExitCode::from(EXIT_USAGE));
+    }
+
+    let mods_path = paths
+        .mods_dir
+        .clone()
+        .unwrap_or_else(|| mods::default_mods_dir(&minecraft_path));
+
+    Ok((minecraft_path, mods_path))
+}
@@ -1,54 +1,651 @@
+use std::collections::BTreeMap;
 use std::fs;
+use std::io::Write;
 use std::path::{Path, PathBuf};
-use dirs;
+use chrono::Local;
 use serde::{Deserialize, Serialize};
 
+use crate::backup;
+use crate::curseforge;
+use crate::download;
+use crate::git_ops::BuildType;
+
+/// Репозитории сборок StoryTime, которые добавляются в новый конфиг
+const STORYTIME_CLIENT_URL: &str = "https://github.com/Frog1-cell/StoryTime-ServerKlient-Mods.git";
+const STORYTIME_SERVER_URL: &str = "https://github.com/Frog1-cell/StoryTime-ServerBuild-Mods.git";
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Config {
+    /// Версия схемы конфига, см. `MIGRATIONS`
+    #[serde(default)]
+    pub schema_version: u32,
     pub default_minecraft_path: Option<String>,
+    /// Сохранённые экземпляры игры
+    #[serde(default)]
+    pub instances: Vec<Instance>,
+    /// Каналы обновлений сборки по имени
+    #[serde(default = "default_channels")]
+    pub channels: BTreeMap<String, ChannelSpec>,
+    /// Сколько снимков папки модов хранить для отката
+    #[serde(default = "default_backup_limit")]
+    pub backup_limit: usize,
+    /// Сколько файлов скачивать одновременно
+    #[serde(default = "default_download_concurrency")]
+    pub download_concurrency: usize,
+    /// Доступ к API CurseForge для импорта сборок
+    #[serde(default)]
+    pub curseforge: CurseForgeSettings,
+    /// Источники сборок, из которых ставятся моды
+    #[serde(default = "default_sources")]
+    pub sources: Vec<PackSource>,
+}
+
+/// Сохранённый экземпляр игры: папка, сборка, которой он следует, и её версия
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Instance {
+    pub name: String,
+    /// Папка экземпляра (папка игры или сервера)
+    pub path: String,
+    /// Папка модов относительно папки экземпляра
+    #[serde(default = "default_mods_subdir")]
+    pub mods_dir: String,
+    /// Имя источника сборки
+    pub source: Option<String>,
+    pub build_type: Option<BuildType>,
+    pub game_version: Option<String>,
+    pub loader: Option<String>,
+    /// Время последней установки сборки (RFC 3339)
+    pub last_synced: Option<String>,
+    /// Закреплённый тег, ветка или коммит сборки
+    pub git_ref: Option<String>,
+    /// Канал обновлений (по умолчанию `stable`)
+    pub channel: Option<String>,
+}
+
+impl Instance {
+    /// Новый экземпляр для папки модов: имя берётся из папки экземпляра
+    /// (для `.minecraft` лаунчеров — из папки над ней)
+    pub fn from_mods_path(mods_path: &Path) -> Self {
+        let path = mods_path.parent().unwrap_or(mods_path);
+        let named = match path.file_name().and_then(|n| n.to_str()) {
+            Some(".minecraft" | "minecraft") => path.parent().unwrap_or(path),
+            _ => path,
+        };
+
+        Instance {
+            name: named
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "minecraft".to_string()),
+            path: path.display().to_string(),
+            mods_dir: mods_path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(default_mods_subdir),
+            source: None,
+            build_type: None,
+            game_version: None,
+            loader: None,
+            last_synced: None,
+            git_ref: None,
+            channel: None,
+        }
+    }
+
+    /// Полный путь к папке модов
+    pub fn mods_path(&self) -> PathBuf {
+        Path::new(&self.path).join(&self.mods_dir)
+    }
+}
+
+/// Куда ведёт канал обновлений: ветка или шаблон тегов.
+/// Пустой канал следует ветке по умолчанию.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct ChannelSpec {
+    pub branch: Option<String>,
+    /// Шаблон тегов, например `v*-beta*`; берётся самый свежий подходящий тег
+    pub tag_pattern: Option<String>,
+}
+
+/// Источник сборки: откуда и для какой стороны брать моды
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PackSource {
+    /// Имя, по которому источник выбирается в меню и в `--source`
+    pub name: String,
+    /// Репозиторий, `pack.toml` или `.mrpack`; можно `file://` для локальной проверки
+    pub url: String,
+    #[serde(rename = "type", default)]
+    pub kind: SourceKind,
+    /// Ветка git, если не задан канал или закреплённая версия
+    pub branch: Option<String>,
+    #[serde(default = "default_side")]
+    pub side: BuildType,
+}
+
+/// Формат источника сборки
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    /// Git-репозиторий с .jar или сборкой packwiz
+    #[default]
+    Git,
+    /// `pack.toml` сборки packwiz по ссылке
+    Manifest,
+    /// Архив .mrpack по ссылке или из файла
+    Mrpack,
+}
+
+impl SourceKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            SourceKind::Git => "git",
+            SourceKind::Manifest => "manifest",
+            SourceKind::Mrpack => "mrpack",
+        }
+    }
+}
+
+/// Настройки API CurseForge
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CurseForgeSettings {
+    /// Адрес API; для проверки можно указать локальную подмену
+    #[serde(default = "default_curseforge_api_url")]
+    pub api_url: String,
+    /// Ключ API (заголовок `x-api-key`)
+    pub api_key: Option<String>,
+}
+
+impl Default for CurseForgeSettings {
+    fn default() -> Self {
+        CurseForgeSettings {
+            api_url: default_curseforge_api_url(),
+            api_key: None,
+        }
+    }
+}
+
+pub const DEFAULT_CHANNEL: &str = "stable";
+
+fn default_backup_limit() -> usize {
+    backup::DEFAULT_BACKUP_LIMIT
+}
+
+fn default_download_concurrency() -> usize {
+    download::DEFAULT_CONCURRENCY
+}
+
+fn default_curseforge_api_url() -> String {
+    curseforge::DEFAULT_API_URL.to_string()
+}
+
+fn default_mods_subdir() -> String {
+    "mods".to_string()
+}
+
+fn default_side() -> BuildType {
+    BuildType::Client
+}
+
+fn default_sources() -> Vec<PackSource> {
+    vec![
+        PackSource {
+            name: "StoryTime Client".to_string(),
+            url: STORYTIME_CLIENT_URL.to_string(),
+            kind: SourceKind::Git,
+            branch: None,
+            side: BuildType::Client,
+        },
+        PackSource {
+            name: "StoryTime Server".to_string(),
+            url: STORYTIME_SERVER_URL.to_string(),
+            kind: SourceKind::Git,
+            branch: None,
+            side: BuildType::Server,
+        },
+    ]
+}
+
+fn default_channels() -> BTreeMap<String, ChannelSpec> {
+    BTreeMap::from([
+        (DEFAULT_CHANNEL.to_string(), ChannelSpec::default()),
+        (
+            "beta".to_string(),
+            ChannelSpec {
+                branch: Some("beta".to_string()),
+                tag_pattern: None,
+            },
+        ),
+    ])
+}
+
+/// Текущая версия схемы конфига
+const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Миграции схемы по порядку: `MIGRATIONS[n]` переводит конфиг из версии `n` в `n + 1`.
+/// Конфиг без `schema_version` считается версией 0.
+const MIGRATIONS: &[Migration] = &[migrate_instances_to_list];
+
+/// Шаг миграции: меняет таблицу конфига на месте
+type Migration = fn(&mut toml::Table) -> Result<(), String>;
+
+/// 0 → 1: в первых версиях был только `default_minecraft_path`, затем настройки
+/// экземпляров хранились таблицей по пути к папке модов; теперь это список
+/// именованных экземпляров, в который попадает и папка по умолчанию
+fn migrate_instances_to_list(table: &mut toml::Table) -> Result<(), String> {
+    let old = match table.remove("instances") {
+        Some(toml::Value::Table(old)) => old,
+        Some(other) => {
+            table.insert("instances".to_string(), other);
+            return Ok(());
+        }
+        None => toml::Table::new(),
+    };
+
+    let mut instances: Vec<Instance> = Vec::new();
+    let mut add = |mods_path: &Path, settings: Option<&toml::Value>| {
+        let mut instance = Instance::from_mods_path(mods_path);
+        if instances.iter().any(|i| i.path == instance.path) {
+            return;
+        }
+        instance.name = unique_name(&instance.name, |name| instances.iter().any(|i| i.name == name));
+        let setting = |key| settings.and_then(|s| s.get(key)).and_then(|v| v.as_str()).map(String::from);
+        instance.git_ref = setting("git_ref");
+        instance.channel = setting("channel");
+        instances.push(instance);
+    };
+    for (mods_path, settings) in &old {
+        add(Path::new(mods_path), Some(settings));
+    }
+    if let Some(path) = table.get("default_minecraft_path").and_then(|v| v.as_str()) {
+        add(&Path::new(path).join("mods"), None);
+    }
+
+    let instances = instances
+        .into_iter()
+        .map(|instance| toml::Value::try_from(instance).map_err(|e| e.to_string()))
+        .collect::<Result<_, _>>()?;
+    table.insert("instances".to_string(), toml::Value::Array(instances));
+    Ok(())
+}
+
+/// Имя, не занятое другим экземпляром: при совпадении добавляется номер
+fn unique_name(base: &str, is_taken: impl Fn(&str) -> bool) -> String {
+    let mut name = base.to_string();
+    let mut n = 2;
+    while is_taken(&name) {
+        name = format!("{} ({})", base, n);
+        n += 1;
+    }
+    name
+}
+
+/// Почему конфиг не загрузился
+#[derive(Debug)]
+enum ParseError {
+    /// Конфиг неразборчив. Указывается строка (для синтаксических ошибок) или ключ.
+    Invalid(String),
+    /// Конфиг создан более новой версией stm: его нельзя ни читать, ни перезаписывать
+    Newer(u32),
+}
+
+/// Разбор конфига с миграцией со старых версий схемы.
+/// Возвращает конфиг и признак того, что он был мигрирован.
+fn parse(content: &str) -> Result<(Config, bool), ParseError> {
+    let mut table: toml::Table = toml::from_str(content).map_err(|e| ParseError::Invalid(e.to_string()))?;
+
+    let version = match table.get("schema_version") {
+        None => 0,
+        Some(value) => value
+            .as_integer()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| ParseError::Invalid("ожидалось целое число\nin `schema_version`".to_string()))?,
+    };
+    if version > SCHEMA_VERSION {
+        return Err(ParseError::Newer(version));
+    }
+
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(&mut table).map_err(|e| ParseError::Invalid(format!("ошибка миграции: {}", e)))?;
+    }
+    table.insert("schema_version".to_string(), toml::Value::Integer(SCHEMA_VERSION.into()));
+
+    let config: Config = table
+        .try_into()
+        .map_err(|e: toml::de::Error| ParseError::Invalid(e.to_string()))?;
+    Ok((config, version < SCHEMA_VERSION))
+}
+
+/// Путь к файлу конфига
+fn config_path() -> PathBuf {
+    dirs::config_dir()
+        .unwrap()
+        .join("storytime-launcher")
+        .join("config.toml")
+}
+
+/// Неразборчивый конфиг откладывается в сторону, чтобы настройки
+/// пользователя не пропали при следующем сохранении
+fn set_aside_broken(config_file: &Path) -> std::io::Result<PathBuf> {
+    let stamp = Local::now().format("%Y%m%d-%H%M%S").to_string();
+    let name = unique_name(&format!("config.toml.broken-{}", stamp), |name| {
+        config_file.with_file_name(name).exists()
+    });
+    let backup = config_file.with_file_name(name);
+    fs::rename(config_file, &backup)?;
+    Ok(backup)
+}
+
+/// Атомарная запись: во временный файл рядом, затем переименование,
+/// чтобы сбой посреди записи не оставил обрезанный конфиг
+fn write_atomic(path: &Path, content: &str) -> std::io::Result<()> {
+    let tmp = path.with_extension("toml.tmp");
+    {
+        let mut file = fs::File::create(&tmp)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)
 }
 
 impl Config {
-    pub fn load() -> Self {
-        let config_dir = dirs::config_dir().unwrap().join("storytime-launcher");
-        let config_file = config_dir.join("config.toml");
-        
+    /// Загрузка конфига. Старый конфиг мигрируется и сразу пересохраняется;
+    /// неразборчивый откладывается в сторону с сообщением об ошибке,
+    /// и используются настройки по умолчанию. Конфиг более новой версии stm
+    /// не трогается: это ошибка, с которой работать нельзя.
+    /// Сообщения пишутся в stderr, чтобы не мешать выводу команд.
+    pub fn load() -> Result<Self, String> {
+        let config_file = config_path();
+
         if config_file.exists() {
-            match fs::read_to_string(&config_file) {
-                Ok(content) => match toml::from_str(&content) {
-                    Ok(config) => return config,
-                    Err(_) => println!("󰅖 Ошибка чтения конфига, создаю новый"),
-                },
-                Err(_) => println!("󰅖 Ошибка чтения конфига, создаю новый"),
+            let parsed = fs::read_to_string(&config_file)
+                .map_err(|e| ParseError::Invalid(e.to_string()))
+                .and_then(|content| parse(&content));
+
+            match parsed {
+                Ok((config, migrated)) => {
+                    if migrated {
+                        if let Err(e) = config.save() {
+                            eprintln!("󰅖 Ошибка сохранения обновлённого конфига: {}", e);
+                        }
+                    }
+                    return Ok(config);
+                }
+                Err(ParseError::Newer(version)) => {
+                    return Err(format!(
+                        "Конфиг {} создан более новой версией stm (схема {}, поддерживается {}). Обновите stm",
+                        config_file.display(),
+                        version,
+                        SCHEMA_VERSION
+                    ));
+                }
+                Err(ParseError::Invalid(e)) => {
+                    eprintln!("󰅖 Ошибка в конфиге {}:\n{}", config_file.display(), e);
+                    match set_aside_broken(&config_file) {
+                        Ok(backup) => eprintln!("󰆓 Старый конфиг сохранён: {}", backup.display()),
+                        Err(e) => eprintln!("󰅖 Не удалось сохранить копию конфига: {}", e),
+                    }
+                    eprintln!("󰝚 Использую настройки по умолчанию");
+                }
             }
         }
-        
-        Config {
+
+        Ok(Config {
+            schema_version: SCHEMA_VERSION,
             default_minecraft_path: None,
-        }
+            instances: Vec::new(),
+            channels: default_channels(),
+            backup_limit: default_backup_limit(),
+            download_concurrency: default_download_concurrency(),
+            curseforge: CurseForgeSettings::default(),
+            sources: default_sources(),
+        })
     }
-    
+
     pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let config_dir = dirs::config_dir().unwrap().join("storytime-launcher");
-        fs::create_dir_all(&config_dir)?;
-        
-        let config_file = config_dir.join("config.toml");
+        let config_file = config_path();
+        if let Some(config_dir) = config_file.parent() {
+            fs::create_dir_all(config_dir)?;
+        }
+
         let toml = toml::to_string(&self)?;
-        fs::write(config_file, toml)?;
-        
+        write_atomic(&config_file, &toml)?;
+
         Ok(())
     }
     
     pub fn get_default_path(&self) -> Option<PathBuf> {
         self.default_minecraft_path
             .as_ref()
-            .map(|p| PathBuf::from(p))
+            .map(PathBuf::from)
     }
     
-    pub fn set_default_path(&mut self, path: &Path) {
+    pub fn set_default_path(&mut self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
         self.default_minecraft_path = Some(path.display().to_string());
-        if let Err(e) = self.save() {
-            eprintln!("󰅖 Ошибка сохранения конфига: {}", e);
+        self.save()
+    }
+
+    /// Экземпляр по имени
+    pub fn instance(&self, name: &str) -> Option<&Instance> {
+        self.instances.iter().find(|i| i.name == name)
+    }
+
+    /// Экземпляр, которому принадлежит папка модов
+    pub fn instance_for(&self, mods_path: &Path) -> Option<&Instance> {
+        self.instances.iter().find(|i| i.mods_path() == mods_path)
+    }
+
+    /// Экземпляр для папки модов; если его нет — сохраняется новый
+    /// с именем папки (при совпадении имён добавляется номер)
+    fn instance_entry(&mut self, mods_path: &Path) -> &mut Instance {
+        if let Some(index) = self.instances.iter().position(|i| i.mods_path() == mods_path) {
+            return &mut self.instances[index];
+        }
+
+        let mut instance = Instance::from_mods_path(mods_path);
+        instance.name = unique_name(&instance.name, |name| self.instance(name).is_some());
+        self.instances.push(instance);
+        self.instances.last_mut().unwrap()
+    }
+
+    /// Добавление экземпляра; имена и папки модов не повторяются
+    pub fn add_instance(&mut self, instance: Instance) -> Result<(), Box<dyn std::error::Error>> {
+        if self.instance(&instance.name).is_some() {
+            return Err(format!("Экземпляр '{}' уже есть", instance.name).into());
+        }
+        if let Some(existing) = self.instance_for(&instance.mods_path()) {
+            return Err(format!("Эта папка модов уже сохранена как '{}'", existing.name).into());
+        }
+        self.instances.push(instance);
+        self.save()
+    }
+
+    /// Замена экземпляра `name` (в том числе с новым именем)
+    pub fn update_instance(&mut self, name: &str, instance: Instance) -> Result<(), Box<dyn std::error::Error>> {
+        if instance.name != name && self.instance(&instance.name).is_some() {
+            return Err(format!("Экземпляр '{}' уже есть", instance.name).into());
+        }
+        let existing = self.instances
+            .iter_mut()
+            .find(|i| i.name == name)
+            .ok_or_else(|| format!("Экземпляр '{}' не найден", name))?;
+        *existing = instance;
+        self.save()
+    }
+
+    /// Удаление экземпляра из конфига (файлы не трогаются)
+    pub fn remove_instance(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let count = self.instances.len();
+        self.instances.retain(|i| i.name != name);
+        if self.instances.len() == count {
+            return Err(format!("Экземпляр '{}' не найден", name).into());
+        }
+        self.save()
+    }
+
+    /// Запись успешной установки: экземпляр запоминает источник, сторону и время
+    pub fn record_sync(&mut self, mods_path: &Path, source: &PackSource) -> Result<(), Box<dyn std::error::Error>> {
+        let instance = self.instance_entry(mods_path);
+        instance.source = Some(source.name.clone());
+        instance.build_type = Some(source.side);
+        instance.last_synced = Some(Local::now().to_rfc3339());
+        self.save()
+    }
+
+    /// Запись версии игры и лоадера экземпляра (известных, например, из сборки)
+    pub fn set_game_info(
+        &mut self,
+        mods_path: &Path,
+        game_version: &str,
+        loader: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let instance = self.instance_entry(mods_path);
+        instance.game_version = Some(game_version.to_string());
+        if let Some(loader) = loader {
+            instance.loader = Some(loader.to_string());
+        }
+        self.save()
+    }
+
+    /// Закреплённая версия сборки для папки модов
+    pub fn get_git_ref(&self, mods_path: &Path) -> Option<String> {
+        self.instance_for(mods_path).and_then(|i| i.git_ref.clone())
+    }
+
+    /// Закрепление версии сборки для папки модов (`None` — следовать ветке по умолчанию)
+    pub fn set_git_ref(
+        &mut self,
+        mods_path: &Path,
+        git_ref: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.instance_entry(mods_path).git_ref = git_ref.map(|r| r.to_string());
+        self.save()
+    }
+
+    /// Канал обновлений экземпляра
+    pub fn get_channel(&self, mods_path: &Path) -> String {
+        self.instance_for(mods_path)
+            .and_then(|i| i.channel.clone())
+            .unwrap_or_else(|| DEFAULT_CHANNEL.to_string())
+    }
+
+    /// Настройки канала экземпляра
+    pub fn channel_spec(&self, mods_path: &Path) -> Option<ChannelSpec> {
+        self.channels.get(&self.get_channel(mods_path)).cloned()
+    }
+
+    /// Переключение канала экземпляра. Закреплённая версия при этом снимается,
+    /// иначе канал не имел бы эффекта.
+    pub fn set_channel(&mut self, mods_path: &Path, channel: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let instance = self.instance_entry(mods_path);
+        instance.channel = Some(channel.to_string());
+        instance.git_ref = None;
+        self.save()
+    }
+
+    /// Источник сборки по имени
+    pub fn source(&self, name: &str) -> Option<&PackSource> {
+        self.sources.iter().find(|s| s.name == name)
+    }
+
+    /// Первый источник для стороны — он используется, если источник не указан
+    pub fn default_source(&self, side: BuildType) -> Option<&PackSource> {
+        self.sources.iter().find(|s| s.side == side)
+    }
+
+    /// Добавление источника; имена источников не повторяются
+    pub fn add_source(&mut self, source: PackSource) -> Result<(), Box<dyn std::error::Error>> {
+        if self.source(&source.name).is_some() {
+            return Err(format!("Источник '{}' уже есть", source.name).into());
+        }
+        self.sources.push(source);
+        self.save()
+    }
+
+    /// Замена источника `name` (в том числе с новым именем)
+    pub fn update_source(&mut self, name: &str, source: PackSource) -> Result<(), Box<dyn std::error::Error>> {
+        if source.name != name && self.source(&source.name).is_some() {
+            return Err(format!("Источник '{}' уже есть", source.name).into());
+        }
+        let existing = self.sources
+            .iter_mut()
+            .find(|s| s.name == name)
+            .ok_or_else(|| format!("Источник '{}' не найден", name))?;
+        *existing = source;
+        self.save()
+    }
+
+    /// Удаление источника по имени
+    pub fn remove_source(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let count = self.sources.len();
+        self.sources.retain(|s| s.name != name);
+        if self.sources.len() == count {
+            return Err(format!("Источник '{}' не найден", name).into());
         }
+        self.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn baseline_default_path_becomes_instance() {
+        let (config, migrated) = parse("default_minecraft_path = \"/games/.minecraft\"\n").unwrap();
+
+        assert!(migrated);
+        assert_eq!(config.schema_version, SCHEMA_VERSION);
+        assert_eq!(config.default_minecraft_path.as_deref(), Some("/games/.minecraft"));
+        assert_eq!(config.instances.len(), 1);
+        assert_eq!(config.instances[0].name, "games");
+        assert_eq!(config.instances[0].path, "/games/.minecraft");
+        assert_eq!(config.instances[0].mods_dir, "mods");
+    }
+
+    #[test]
+    fn instance_table_becomes_list() {
+        let content = r#"
+            default_minecraft_path = "/a/.minecraft"
+
+            [instances."/a/.minecraft/mods"]
+            channel = "beta"
+
+            [instances."/b/a/mods"]
+            git_ref = "v1.0"
+        "#;
+        let (config, migrated) = parse(content).unwrap();
+
+        assert!(migrated);
+        let instances: Vec<_> = config
+            .instances
+            .iter()
+            .map(|i| (i.name.as_str(), i.path.as_str(), i.channel.as_deref(), i.git_ref.as_deref()))
+            .collect();
+        // Папка по умолчанию уже есть в таблице и не дублируется
+        assert_eq!(
+            instances,
+            [
+                ("a", "/a/.minecraft", Some("beta"), None),
+                ("a (2)", "/b/a", None, Some("v1.0")),
+            ]
+        );
+    }
+
+    #[test]
+    fn current_config_is_not_migrated() {
+        let content = format!("schema_version = {}\ninstances = []\n", SCHEMA_VERSION);
+        let (config, migrated) = parse(&content).unwrap();
+
+        assert!(!migrated);
+        assert!(config.instances.is_empty());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn newer_and_broken_configs_are_rejected() {
+        let newer = format!("schema_version = {}\n", SCHEMA_VERSION + 1);
+        assert!(matches!(parse(&newer), Err(ParseError::Newer(v)) if v == SCHEMA_VERSION + 1));
+
+        assert!(matches!(parse("schema_version = \"1\"\n"), Err(ParseError::Invalid(_))));
+        assert!(matches!(parse("instances = [\n"), Err(ParseError::Invalid(_))));
+    }
+}
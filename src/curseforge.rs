@@ -0,0 +1,435 @@
+use indicatif::MultiProgress;
+use inquire::Text;
+use serde::Deserialize;
+use serde_json::Value;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use zip::ZipArchive;
+
+use crate::backup;
+use crate::config::CurseForgeSettings;
+use crate::download::{self, Download, DownloadQueue, FileHashes};
+use crate::manifest::{Manifest, Source};
+use crate::mrpack;
+use crate::ui;
+
+/// Адрес API CurseForge по умолчанию
+pub const DEFAULT_API_URL: &str = "https://api.curseforge.com";
+
+/// Файл с описанием сборки внутри экспорта CurseForge
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Код алгоритма SHA-1 в ответах API CurseForge
+const HASH_ALGO_SHA1: u64 = 1;
+
+/// `manifest.json` экспорта CurseForge
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PackManifest {
+    minecraft: MinecraftInfo,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    version: String,
+    files: Vec<PackFile>,
+    /// Папка с файлами, которые копируются как есть
+    #[serde(default = "default_overrides")]
+    overrides: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MinecraftInfo {
+    version: String,
+    #[serde(default)]
+    mod_loaders: Vec<ModLoader>,
+}
+
+#[derive(Deserialize)]
+struct ModLoader {
+    /// Например `forge-47.2.0` или `fabric-0.15.11`
+    id: String,
+    #[serde(default)]
+    primary: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PackFile {
+    #[serde(rename = "projectID")]
+    project_id: u64,
+    #[serde(rename = "fileID")]
+    file_id: u64,
+}
+
+fn default_overrides() -> String {
+    "overrides".to_string()
+}
+
+/// Файл, который не удалось получить: (id проекта, id файла, причина)
+pub type Unresolved = (u64, u64, String);
+
+/// Итог импорта
+pub struct ImportReport {
+    pub name: String,
+    pub version: String,
+    pub game_version: String,
+    /// Лоадер и его версия, например (`forge`, `47.2.0`)
+    pub loader: Option<(String, String)>,
+    pub downloaded: u32,
+    pub overrides: u32,
+    pub unresolved: Vec<Unresolved>,
+}
+
+impl ImportReport {
+    /// Записан ли в папку игры хоть один файл, даже если часть не получена
+    pub fn wrote_files(&self) -> bool {
+        self.downloaded > 0 || self.overrides > 0
+    }
+}
+
+/// Импорт экспорта CurseForge (.zip) в папку игры, в которой лежит `mods_dir`.
+/// Каждый projectID/fileID ищется через API CurseForge; файлы, для которых
+/// нет ссылки на скачивание, попадают в `unresolved`.
+pub fn import(
+    zip_path: &Path,
+    mods_dir: &Path,
+    settings: &CurseForgeSettings,
+    concurrency: usize,
+    backup_limit: usize,
+) -> Result<ImportReport, String> {
+    let file = File::open(zip_path).map_err(|e| format!("Ошибка открытия архива: {}", e))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| format!("Архив повреждён: {}", e))?;
+
+    let pack: PackManifest = {
+        let entry = archive
+            .by_name(MANIFEST_FILE)
+            .map_err(|_| format!("В архиве нет {}", MANIFEST_FILE))?;
+        serde_json::from_reader(entry).map_err(|e| format!("Ошибка чтения {}: {}", MANIFEST_FILE, e))?
+    };
+
+    let game_dir = mods_dir.parent().unwrap_or(mods_dir).to_path_buf();
+    let loader = pack
+        .minecraft
+        .mod_loaders
+        .iter()
+        .find(|l| l.primary)
+        .or_else(|| pack.minecraft.mod_loaders.first())
+        .and_then(|l| l.id.split_once('-'))
+        .map(|(name, version)| (name.to_string(), version.to_string()));
+
+    // Ссылки на скачивание через API
+    let mut unresolved = Vec::new();
+    let mut resolved = Vec::new();
+    let ids: Vec<(u64, u64)> = pack.files.iter().map(|f| (f.project_id, f.file_id)).collect();
+    for (file, info) in pack.files.iter().zip(resolve_files(&ids, settings, concurrency)) {
+        match info {
+            Ok(info) => resolved.push((file, info)),
+            Err(e) => unresolved.push((file.project_id, file.file_id, e)),
+        }
+    }
+
+    if mods_dir.exists() {
+        backup::snapshot(mods_dir, backup_limit)
+            .map_err(|e| format!("Не удалось сохранить снимок, импорт отменён: {}", e))?;
+    }
+
+    let mut queue = DownloadQueue::new(concurrency);
+    for (_, info) in &resolved {
+        let destination = game_dir.join(target_dir(&info.file_name)).join(&info.file_name);
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Ошибка создания {}: {}", parent.display(), e))?;
+        }
+        queue.push(Download {
+            url: info.download_url.clone(),
+            destination,
+            hashes: FileHashes {
+                sha1: info.sha1.clone(),
+                ..FileHashes::default()
+            },
+        });
+    }
+
+    let mut manifest = Manifest::load(mods_dir);
+    let mut downloaded = 0;
+    for ((file, _), result) in resolved.iter().zip(queue.run(&MultiProgress::new())) {
+        match result {
+            Ok(destination) => {
+                downloaded += 1;
+                let source = Source::CurseForge { project_id: file.project_id };
+                mrpack::record_mod(&mut manifest, mods_dir, &destination, &source, &file.file_id.to_string());
+            }
+            Err(e) => unresolved.push((file.project_id, file.file_id, e)),
+        }
+    }
+
+    let overrides = mrpack::extract_overrides(&mut archive, &pack.overrides, &game_dir, |_| {})?;
+
+    manifest
+        .save(mods_dir)
+        .map_err(|e| format!("Ошибка сохранения манифеста: {}", e))?;
+
+    Ok(ImportReport {
+        name: pack.name,
+        version: pack.version,
+        game_version: pack.minecraft.version,
+        loader,
+        downloaded,
+        overrides,
+        unresolved,
+    })
+}
+
+/// Куда класть файл: .jar — в mods, остальное (ресурспаки) — в resourcepacks
+fn target_dir(file_name: &str) -> &'static str {
+    if file_name.ends_with(".jar") {
+        "mods"
+    } else {
+        "resourcepacks"
+    }
+}
+
+/// Файл на CurseForge
+pub struct FileInfo {
+    pub file_name: String,
+    pub download_url: String,
+    pub sha1: Option<String>,
+}
+
+/// Поиск файлов по парам (projectID, fileID) через API:
+/// `GET {api_url}/v1/mods/{projectID}/files/{fileID}`.
+/// Запросы идут параллельно; результаты — в том же порядке, что и `files`.
+pub fn resolve_files(
+    files: &[(u64, u64)],
+    settings: &CurseForgeSettings,
+    concurrency: usize,
+) -> Vec<Result<FileInfo, String>> {
+    let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+    let api_url = settings.api_url.trim_end_matches('/');
+    let mut results: Vec<Result<FileInfo, String>> =
+        files.iter().map(|_| Err("запрос не выполнялся".to_string())).collect();
+
+    download::runtime().block_on(async {
+        let mut tasks = JoinSet::new();
+
+        for (index, (project_id, file_id)) in files.iter().enumerate() {
+            let url = format!("{}/v1/mods/{}/files/{}", api_url, project_id, file_id);
+            let api_key = settings.api_key.clone();
+            let semaphore = semaphore.clone();
+
+            tasks.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                (index, fetch_file_info(&url, api_key.as_deref()).await)
+            });
+        }
+
+        while let Some(joined) = tasks.join_next().await {
+            if let Ok((index, result)) = joined {
+                results[index] = result;
+            }
+        }
+    });
+
+    results
+}
+
+async fn fetch_file_info(url: &str, api_key: Option<&str>) -> Result<FileInfo, String> {
+    let mut request = download::client().get(url);
+    if let Some(api_key) = api_key {
+        request = request.header("x-api-key", api_key);
+    }
+
+    let response: Value = request
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+    let data = &response["data"];
+
+    let file_name = data["fileName"]
+        .as_str()
+        .ok_or("в ответе API нет имени файла")?;
+    // Без ссылки — автор запретил скачивание сторонними программами
+    let download_url = data["downloadUrl"]
+        .as_str()
+        .ok_or("автор запретил скачивание вне CurseForge")?;
+    mrpack::safe_path(file_name)?;
+
+    let sha1 = data["hashes"].as_array().and_then(|hashes| {
+        hashes
+            .iter()
+            .find(|h| h["algo"].as_u64() == Some(HASH_ALGO_SHA1))
+            .and_then(|h| h["value"].as_str())
+            .map(|v| v.to_lowercase())
+    });
+
+    Ok(FileInfo {
+        file_name: file_name.to_string(),
+        download_url: download_url.to_string(),
+        sha1,
+    })
+}
+
+/// Вывод итога импорта. Возвращает false, если какие-то файлы не получены.
+pub fn print_import_report(result: Result<ImportReport, String>) -> bool {
+    let report = match result {
+        Ok(report) => report,
+        Err(e) => {
+            println!("󰅖 {}", e);
+            return false;
+        }
+    };
+
+    println!("󰄬 Сборка {} {} импортирована", report.name, report.version);
+    match &report.loader {
+        Some((loader, version)) => println!("   Minecraft {}, {} {}", report.game_version, loader, version),
+        None => println!("   Minecraft {}", report.game_version),
+    }
+    println!("   Скачано: {}, из overrides: {}", report.downloaded, report.overrides);
+
+    if !report.unresolved.is_empty() {
+        println!("\n󰀦 Не удалось получить файлов: {}. Скачайте их вручную:", report.unresolved.len());
+        for (project_id, file_id, reason) in &report.unresolved {
+            println!(
+                "  - https://www.curseforge.com/projects/{} (файл {}): {}",
+                project_id, file_id, reason
+            );
+        }
+    }
+    report.unresolved.is_empty()
+}
+
+/// Импорт экспорта CurseForge с вопросами пользователю
+pub fn import_pack(
+    folder: &ui::GameFolder,
+    settings: &CurseForgeSettings,
+    concurrency: usize,
+    backup_limit: usize,
+) {
+    let mods_path = match folder.mods_path() {
+        Some(path) => path,
+        None => return,
+    };
+
+    let zip_path = match Text::new("󰝚 Путь к .zip экспорта CurseForge:").prompt() {
+        Ok(path) if !path.trim().is_empty() => PathBuf::from(path.trim()),
+        _ => {
+            println!("󰅖 Отменено");
+            return;
+        }
+    };
+
+    println!("󰇚 Импортирую сборку...");
+    let result = import(&zip_path, &mods_path, settings, concurrency, backup_limit);
+    let wrote_files = result.as_ref().is_ok_and(ImportReport::wrote_files);
+    print_import_report(result);
+    if wrote_files {
+        ui::check_duplicates(&mods_path, backup_limit);
+    }
+
+    println!("󰝚 Нажмите Enter чтобы продолжить...");
+    let _ = std::io::stdin().read_line(&mut String::new());
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::test_server::{self, Reply};
+    use sha1::{Digest, Sha1};
+    use std::io::Write;
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    pub(crate) const JAR: &[u8] = b"not really a jar";
+
+    /// Локальная подмена API CurseForge: файл 1/10 можно скачать,
+    /// у файла 2/20 нет `downloadUrl`
+    pub(crate) fn serve_api() -> String {
+        let sha1 = format!("{:x}", Sha1::digest(JAR));
+        test_server::serve(move |base, path| match path {
+            "/v1/mods/1/files/10" => Reply::ok(
+                serde_json::json!({ "data": {
+                    "fileName": "a.jar",
+                    "downloadUrl": format!("{}/files/a.jar", base),
+                    "hashes": [{ "algo": HASH_ALGO_SHA1, "value": sha1 }],
+                }})
+                .to_string(),
+            ),
+            "/v1/mods/2/files/20" => Reply::ok(
+                serde_json::json!({ "data": { "fileName": "b.jar", "downloadUrl": null } }).to_string(),
+            ),
+            "/files/a.jar" => Reply::ok(JAR),
+            _ => Reply::not_found(),
+        })
+    }
+
+    /// Экспорт CurseForge с двумя модами и одним файлом в overrides
+    fn write_export(path: &Path) {
+        let manifest = serde_json::json!({
+            "minecraft": {
+                "version": "1.20.1",
+                "modLoaders": [{ "id": "forge-47.2.0", "primary": true }],
+            },
+            "name": "Test",
+            "version": "1.0",
+            "files": [
+                { "projectID": 1, "fileID": 10 },
+                { "projectID": 2, "fileID": 20 },
+            ],
+            "overrides": "overrides",
+        });
+
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        zip.start_file(MANIFEST_FILE, FileOptions::default()).unwrap();
+        zip.write_all(manifest.to_string().as_bytes()).unwrap();
+        zip.start_file("overrides/config/test.toml", FileOptions::default()).unwrap();
+        zip.write_all(b"enabled = true").unwrap();
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn import_resolves_files_and_extracts_overrides() {
+        let root = std::env::temp_dir().join(format!("stm-curseforge-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let zip_path = root.join("export.zip");
+        write_export(&zip_path);
+        let mods_dir = root.join("game").join("mods");
+
+        let settings = CurseForgeSettings {
+            api_url: serve_api(),
+            api_key: None,
+        };
+        let report = import(&zip_path, &mods_dir, &settings, 2, 0).unwrap();
+
+        assert_eq!(report.name, "Test");
+        assert_eq!(report.game_version, "1.20.1");
+        assert_eq!(report.loader, Some(("forge".to_string(), "47.2.0".to_string())));
+        assert_eq!(report.downloaded, 1);
+        assert_eq!(fs::read(mods_dir.join("a.jar")).unwrap(), JAR);
+
+        assert_eq!(report.unresolved.len(), 1);
+        let (project_id, file_id, reason) = &report.unresolved[0];
+        assert_eq!((*project_id, *file_id), (2, 20));
+        assert!(reason.contains("запретил"), "{}", reason);
+        assert!(!mods_dir.join("b.jar").exists());
+
+        assert_eq!(report.overrides, 1);
+        let config = fs::read_to_string(root.join("game/config/test.toml")).unwrap();
+        assert_eq!(config, "enabled = true");
+
+        let manifest = Manifest::load(&mods_dir);
+        assert_eq!(manifest.files.len(), 1);
+        assert_eq!(manifest.files[0].file_name, "a.jar");
+        assert_eq!(manifest.files[0].source, Source::CurseForge { project_id: 1 });
+        assert_eq!(manifest.files[0].version, "10");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,347 @@
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::runtime::Runtime;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::manifest;
+
+/// Сколько файлов качать одновременно по умолчанию
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Сколько раз пытаться скачать файл, если скачивание сорвалось или хеш не совпал
+const DOWNLOAD_ATTEMPTS: u32 = 3;
+
+/// Таймаут запросов к API (скачивание файлов им не ограничено)
+const API_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Сколько ждать ответа или следующей части файла, прежде чем считать, что сервер
+/// завис: общий таймаут оборвал бы большие файлы на медленном канале.
+/// В тестах короче, чтобы зависший сервер не тормозил их.
+const STALL_TIMEOUT: Duration = if cfg!(test) {
+    Duration::from_secs(1)
+} else {
+    Duration::from_secs(30)
+};
+
+/// Общий асинхронный рантайм для сетевых операций
+pub fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| Runtime::new().expect("Не удалось запустить асинхронный рантайм"))
+}
+
+/// Общий HTTP-клиент: соединения переиспользуются между запросами
+pub fn client() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .user_agent(concat!("stm/", env!("CARGO_PKG_VERSION")))
+            .connect_timeout(Duration::from_secs(10))
+            .build()
+            .expect("Не удалось создать HTTP-клиент")
+    })
+}
+
+/// GET-запрос к API с разбором JSON-ответа
+pub fn get_json(url: &str, query: &[(&str, &str)]) -> reqwest::Result<Value> {
+    runtime().block_on(async {
+        client()
+            .get(url)
+            .query(query)
+            .timeout(API_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    })
+}
+
+/// POST-запрос к API с JSON-телом и JSON-ответом
+pub fn post_json(url: &str, body: &Value) -> reqwest::Result<Value> {
+    runtime().block_on(async {
+        client()
+            .post(url)
+            .json(body)
+            .timeout(API_TIMEOUT)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    })
+}
+
+/// Опубликованные хеши файла (hex). Файл без хешей не проверяется.
+#[derive(Clone, Default)]
+pub struct FileHashes {
+    pub sha512: Option<String>,
+    pub sha256: Option<String>,
+    pub sha1: Option<String>,
+}
+
+impl FileHashes {
+    /// Нет ни одного хеша
+    pub fn is_empty(&self) -> bool {
+        self.sha512.is_none() && self.sha256.is_none() && self.sha1.is_none()
+    }
+
+    /// Сверка файла с самым надёжным из известных хешей: SHA-512, SHA-256, SHA-1
+    pub fn verify(&self, path: &Path) -> Result<(), String> {
+        let (actual, expected) = match (&self.sha512, &self.sha256, &self.sha1) {
+            (Some(sha512), _, _) => (manifest::sha512_file(path), sha512),
+            (None, Some(sha256), _) => (manifest::sha256_file(path), sha256),
+            (None, None, Some(sha1)) => (manifest::sha1_file(path), sha1),
+            (None, None, None) => return Ok(()),
+        };
+
+        match actual {
+            Ok(actual) if actual.eq_ignore_ascii_case(expected) => Ok(()),
+            Ok(_) => Err("хеш скачанного файла не совпадает".to_string()),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+/// Один файл в очереди скачивания
+pub struct Download {
+    pub url: String,
+    /// Куда положить файл после проверки
+    pub destination: PathBuf,
+    pub hashes: FileHashes,
+}
+
+/// Очередь скачивания: файлы качаются параллельно, не больше `concurrency` сразу,
+/// с общим отображением прогресса. Каждый файл пишется во временный `.part`,
+/// проверяется по хешу и только потом переносится на место.
+pub struct DownloadQueue {
+    downloads: Vec<Download>,
+    concurrency: usize,
+}
+
+impl DownloadQueue {
+    pub fn new(concurrency: usize) -> Self {
+        DownloadQueue {
+            downloads: Vec::new(),
+            concurrency: concurrency.max(1),
+        }
+    }
+
+    pub fn push(&mut self, download: Download) {
+        self.downloads.push(download);
+    }
+
+    /// Скачивание всей очереди. Результаты — в порядке добавления:
+    /// путь к скачанному файлу или текст ошибки.
+    pub fn run(self, multi_progress: &MultiProgress) -> Vec<Result<PathBuf, String>> {
+        let total = self.downloads.len();
+        if total == 0 {
+            return Vec::new();
+        }
+
+        let overall = multi_progress.add(ProgressBar::new(total as u64));
+        overall.set_style(
+            ProgressStyle::with_template("{spinner:.green} Скачано {pos}/{len} [{wide_bar:.cyan/blue}]")
+                .unwrap()
+                .progress_chars("#>-"),
+        );
+
+        let semaphore = Arc::new(Semaphore::new(self.concurrency));
+        let mut results: Vec<Result<PathBuf, String>> =
+            (0..total).map(|_| Err("скачивание не запускалось".to_string())).collect();
+
+        runtime().block_on(async {
+            let mut tasks = JoinSet::new();
+
+            for (index, download) in self.downloads.into_iter().enumerate() {
+                let semaphore = semaphore.clone();
+                let multi_progress = multi_progress.clone();
+                let overall = overall.clone();
+
+                tasks.spawn(async move {
+                    let _permit = semaphore.acquire_owned().await;
+                    let result = download_with_retries(&download, &multi_progress).await;
+                    overall.inc(1);
+                    (index, result.map(|_| download.destination))
+                });
+            }
+
+            while let Some(joined) = tasks.join_next().await {
+                if let Ok((index, result)) = joined {
+                    results[index] = result;
+                }
+            }
+        });
+
+        overall.finish_and_clear();
+        results
+    }
+}
+
+/// Скачивание одного файла с повторами при ошибке
+async fn download_with_retries(download: &Download, multi_progress: &MultiProgress) -> Result<(), String> {
+    let name = download
+        .destination
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let partial = download
+        .destination
+        .with_file_name(format!(".{}.part", name));
+
+    let pb = multi_progress.add(ProgressBar::new(0));
+    pb.set_style(
+        ProgressStyle::with_template("  {msg} [{wide_bar:.cyan/blue}] {bytes}/{total_bytes}")
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+    pb.set_message(name.clone());
+
+    let mut attempt = 1;
+    loop {
+        pb.set_position(0);
+        let result = fetch_to(&download.url, &partial, &pb)
+            .await
+            .and_then(|_| download.hashes.verify(&partial));
+
+        match result {
+            Ok(()) => {
+                // Переименование не трогает старый файл, на который может ссылаться снимок
+                let renamed = fs::rename(&partial, &download.destination).map_err(|e| e.to_string());
+                pb.finish_and_clear();
+                return renamed;
+            }
+            Err(e) => {
+                let _ = fs::remove_file(&partial);
+                if attempt == DOWNLOAD_ATTEMPTS {
+                    pb.finish_and_clear();
+                    return Err(e);
+                }
+                attempt += 1;
+                pb.set_message(format!("{} ({}, попытка {}/{})", name, e, attempt, DOWNLOAD_ATTEMPTS));
+            }
+        }
+    }
+}
+
+/// Потоковое скачивание по ссылке в файл. Если сервер молчит дольше
+/// `STALL_TIMEOUT`, скачивание обрывается с ошибкой.
+async fn fetch_to(url: &str, path: &Path, pb: &ProgressBar) -> Result<(), String> {
+    let stalled = |_| format!("сервер не отвечает {} с", STALL_TIMEOUT.as_secs());
+
+    let mut response = tokio::time::timeout(STALL_TIMEOUT, client().get(url).send())
+        .await
+        .map_err(stalled)?
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?;
+    pb.set_length(response.content_length().unwrap_or(0));
+
+    let mut file = tokio::fs::File::create(path).await.map_err(|e| e.to_string())?;
+    while let Some(chunk) = tokio::time::timeout(STALL_TIMEOUT, response.chunk())
+        .await
+        .map_err(stalled)?
+        .map_err(|e| e.to_string())?
+    {
+        file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+        pb.inc(chunk.len() as u64);
+    }
+
+    file.sync_all().await.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{self, Reply};
+    use sha2::{Digest, Sha256};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const CONTENT: &[u8] = b"mod content";
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("stm-download-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    /// Сервер, который на n-й запрос (с нуля) отвечает `reply(n)`.
+    /// Возвращает адрес файла и счётчик запросов.
+    fn serve_attempts(reply: impl Fn(usize) -> Reply + Send + Sync + 'static) -> (String, Arc<AtomicUsize>) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+        let base = test_server::serve(move |_, _| reply(counter.fetch_add(1, Ordering::SeqCst)));
+        (format!("{}/a.jar", base), requests)
+    }
+
+    fn download(url: String, root: &Path) -> Result<PathBuf, String> {
+        let mut queue = DownloadQueue::new(1);
+        queue.push(Download {
+            url,
+            destination: root.join("a.jar"),
+            hashes: FileHashes {
+                sha256: Some(format!("{:x}", Sha256::digest(CONTENT))),
+                ..FileHashes::default()
+            },
+        });
+        queue.run(&MultiProgress::new()).pop().unwrap()
+    }
+
+    fn stall() -> Reply {
+        Reply { stall: true, ..Reply::ok(&CONTENT[..4]) }
+    }
+
+    #[test]
+    fn hash_mismatch_is_retried() {
+        let root = temp_root("retry");
+        let (url, requests) = serve_attempts(|n| Reply::ok(if n == 0 { &b"broken"[..] } else { CONTENT }));
+
+        let path = download(url, &root).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), CONTENT);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        // Временный файл не остаётся
+        assert_eq!(fs::read_dir(&root).unwrap().count(), 1);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn persistent_failures_give_up_after_all_attempts() {
+        let root = temp_root("fail");
+
+        let (url, requests) = serve_attempts(|_| Reply::ok("broken"));
+        let error = download(url, &root).unwrap_err();
+        assert!(error.contains("хеш"), "{}", error);
+        assert_eq!(requests.load(Ordering::SeqCst), DOWNLOAD_ATTEMPTS as usize);
+
+        let (url, _) = serve_attempts(|_| Reply::not_found());
+        assert!(download(url, &root).unwrap_err().contains("404"));
+
+        assert_eq!(fs::read_dir(&root).unwrap().count(), 0);
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn stalled_server_is_retried_and_then_fails() {
+        let root = temp_root("stall");
+
+        let (url, requests) = serve_attempts(|n| if n == 0 { stall() } else { Reply::ok(CONTENT) });
+        download(url, &root).unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        fs::remove_file(root.join("a.jar")).unwrap();
+
+        let (url, requests) = serve_attempts(|_| stall());
+        let error = download(url, &root).unwrap_err();
+        assert!(error.contains("не отвечает"), "{}", error);
+        assert_eq!(requests.load(Ordering::SeqCst), DOWNLOAD_ATTEMPTS as usize);
+        assert_eq!(fs::read_dir(&root).unwrap().count(), 0);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,197 @@
+use clap::ValueEnum;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::inventory::{self, InstalledMod, Origin};
+use crate::manifest::Manifest;
+
+/// Какой jar оставить из нескольких с одним модом
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Keep {
+    /// Самую новую версию
+    Newest,
+    /// Файл из сборки, а если его нет — самую новую версию
+    Pack,
+}
+
+/// Несколько включённых jar с одним id мода: лоадер откажется запускать игру
+pub struct Duplicate {
+    pub id: String,
+    /// Не меньше двух jar
+    pub jars: Vec<InstalledMod>,
+}
+
+impl Duplicate {
+    /// Jar, который останется при выбранном правиле
+    pub fn keeper(&self, keep: Keep) -> &InstalledMod {
+        let from_pack: Vec<&InstalledMod> = self.jars.iter().filter(|m| is_pack_managed(m)).collect();
+        match keep {
+            Keep::Pack if !from_pack.is_empty() => newest(from_pack),
+            _ => newest(self.jars.iter().collect()),
+        }
+    }
+
+    /// Есть ли среди jar файл сборки
+    pub fn has_pack_jar(&self) -> bool {
+        self.jars.iter().any(is_pack_managed)
+    }
+}
+
+/// Моды, которые представлены в папке несколькими включёнными jar
+pub fn find(mods_path: &Path) -> io::Result<Vec<Duplicate>> {
+    let mut by_id: BTreeMap<String, Vec<InstalledMod>> = BTreeMap::new();
+    for installed in inventory::scan(mods_path)? {
+        // Отключённые jar лоадер не загружает
+        if !installed.enabled {
+            continue;
+        }
+        if let Some(id) = installed.id.clone() {
+            by_id.entry(id).or_default().push(installed);
+        }
+    }
+
+    Ok(by_id
+        .into_iter()
+        .filter(|(_, jars)| jars.len() > 1)
+        .map(|(id, jars)| Duplicate { id, jars })
+        .collect())
+}
+
+/// Удаление всех jar, кроме выбранных правилом, вместе с их записями в манифесте.
+/// Возвращает имена удалённых файлов.
+pub fn resolve(mods_path: &Path, duplicates: &[Duplicate], keep: Keep) -> io::Result<Vec<String>> {
+    let mut manifest = Manifest::load(mods_path);
+    let mut removed = Vec::new();
+
+    for duplicate in duplicates {
+        let keeper = duplicate.keeper(keep);
+        for jar in &duplicate.jars {
+            if jar.file_name == keeper.file_name {
+                continue;
+            }
+            fs::remove_file(mods_path.join(&jar.file_name))?;
+            manifest.remove(&jar.file_name);
+            removed.push(jar.file_name.clone());
+        }
+    }
+
+    if !removed.is_empty() {
+        manifest.save(mods_path)?;
+    }
+    Ok(removed)
+}
+
+/// Файл поставлен сборкой: репозиторием, .mrpack или экспортом CurseForge
+fn is_pack_managed(installed: &InstalledMod) -> bool {
+    matches!(installed.origin, Origin::Pack | Origin::Mrpack | Origin::CurseForge)
+}
+
+/// Самый новый jar: по версии из метаданных, а если её нет — по имени файла
+fn newest(jars: Vec<&InstalledMod>) -> &InstalledMod {
+    jars.into_iter()
+        .reduce(|best, jar| {
+            let order = match (&jar.version, &best.version) {
+                (Some(a), Some(b)) => compare_versions(a, b),
+                _ => Ordering::Equal,
+            }
+            .then_with(|| compare_versions(&jar.file_name, &best.file_name));
+            if order == Ordering::Greater { jar } else { best }
+        })
+        .expect("в дубликате всегда есть jar")
+}
+
+/// Сравнение версий вида `0.5.8`, `1.20.1-47.2.0`, `mc1.20.1-0.5.8`, `2.0-beta.3`.
+/// Числа сравниваются как числа, метаданные сборки после `+` не учитываются,
+/// а пре-релиз (`alpha`, `beta`, `rc`, `pre`) ниже выпуска с тем же номером.
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let tokens = |version: &str| -> Vec<String> {
+        let version = version.split('+').next().unwrap_or(version);
+        let mut tokens = Vec::new();
+        let mut current = String::new();
+        for c in version.chars() {
+            let boundary = match current.chars().last() {
+                Some(last) => !c.is_alphanumeric() || last.is_ascii_digit() != c.is_ascii_digit(),
+                None => false,
+            };
+            if boundary {
+                tokens.push(std::mem::take(&mut current));
+            }
+            if c.is_alphanumeric() {
+                current.push(c.to_ascii_lowercase());
+            }
+        }
+        if !current.is_empty() {
+            tokens.push(current);
+        }
+        tokens
+    };
+    let is_pre_release = |token: &str| {
+        ["alpha", "beta", "rc", "pre", "snapshot"].iter().any(|tag| token.starts_with(tag))
+    };
+
+    let (a, b) = (tokens(a), tokens(b));
+    for i in 0..a.len().max(b.len()) {
+        let order = match (a.get(i), b.get(i)) {
+            (Some(x), Some(y)) => match (x.parse::<u64>(), y.parse::<u64>()) {
+                (Ok(x), Ok(y)) => x.cmp(&y),
+                // Число новее пре-релиза: `1.0.1` > `1.0.beta`
+                (Ok(_), Err(_)) => Ordering::Greater,
+                (Err(_), Ok(_)) => Ordering::Less,
+                (Err(_), Err(_)) => x.cmp(y),
+            },
+            (Some(x), None) if is_pre_release(x) => Ordering::Less,
+            (Some(_), None) => Ordering::Greater,
+            (None, Some(y)) if is_pre_release(y) => Ordering::Greater,
+            (None, Some(_)) => Ordering::Less,
+            (None, None) => Ordering::Equal,
+        };
+        if order != Ordering::Equal {
+            return order;
+        }
+    }
+    Ordering::Equal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_compare_as_numbers() {
+        assert_eq!(compare_versions("0.5.8", "0.5.11"), Ordering::Less);
+        assert_eq!(compare_versions("0.5.11", "0.5.8"), Ordering::Greater);
+        assert_eq!(compare_versions("1.2", "1.2.0.1"), Ordering::Less);
+    }
+
+    #[test]
+    fn forge_style_versions() {
+        assert_eq!(compare_versions("1.20.1-47.2.0", "1.20.1-47.10.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.20.1-47.2.0", "1.20.1-47.1.3"), Ordering::Greater);
+        assert_eq!(compare_versions("1.20.1-47.2.0", "1.20.1-47.2.0"), Ordering::Equal);
+    }
+
+    #[test]
+    fn game_version_prefix() {
+        assert_eq!(compare_versions("mc1.20.1-0.5.8", "mc1.20.1-0.5.11"), Ordering::Less);
+        assert_eq!(compare_versions("mc1.20.1-0.5.8", "MC1.20.1-0.5.8"), Ordering::Equal);
+    }
+
+    #[test]
+    fn pre_release_is_older_than_release() {
+        assert_eq!(compare_versions("2.0-beta.3", "2.0"), Ordering::Less);
+        assert_eq!(compare_versions("2.0", "2.0-beta.3"), Ordering::Greater);
+        assert_eq!(compare_versions("2.0-beta.3", "2.0-beta.10"), Ordering::Less);
+        assert_eq!(compare_versions("2.0-beta.3", "2.0-rc.1"), Ordering::Less);
+        assert_eq!(compare_versions("2.0.1", "2.0-beta.3"), Ordering::Greater);
+    }
+
+    #[test]
+    fn build_metadata_is_ignored() {
+        assert_eq!(compare_versions("1.0.0+build.5", "1.0.0+build.7"), Ordering::Equal);
+        assert_eq!(compare_versions("1.0.0+1.20.1", "1.0.0"), Ordering::Equal);
+        assert_eq!(compare_versions("1.0.1+a", "1.0.0+z"), Ordering::Greater);
+    }
+}
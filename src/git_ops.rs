@@ -1,112 +1,473 @@
-use git2::Repository;
 use indicatif::{ProgressBar, ProgressStyle, MultiProgress};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
-use tokio::runtime::Runtime;
 
+use crate::backup;
+use crate::cache::{JarChange, PackRepo};
+use crate::config::{ChannelSpec, Config, CurseForgeSettings, PackSource, SourceKind};
+use crate::download;
+use crate::manifest::{Manifest, Source};
 use crate::mods;
+use crate::mrpack;
+use crate::packwiz;
+use crate::plan::InstallPlan;
 use crate::ui;
 use console::Term;
+use inquire::Confirm;
+use serde::{Deserialize, Serialize};
 
-const CLIENT_REPO_URL: &str = "https://github.com/Frog1-cell/StoryTime-ServerKlient-Mods.git";
-const SERVER_REPO_URL: &str = "https://github.com/Frog1-cell/StoryTime-ServerBuild-Mods.git";
-const TEMP_DIR: &str = ".storytime-mods-temp";
+/// Тип сборки модов
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum BuildType {
+    /// Клиентская сборка
+    Client,
+    /// Серверная сборка
+    Server,
+}
+
+/// Параметры установки сборки
+pub struct InstallOptions {
+    /// Откуда брать сборку и для какой стороны
+    pub source: PackSource,
+    /// Удалить все моды сборки перед установкой
+    pub clean_install: bool,
+    /// Закреплённый тег, ветка или коммит сборки
+    pub git_ref: Option<String>,
+    /// Канал обновлений; без закрепления и канала — ветка по умолчанию
+    pub channel: Option<ChannelSpec>,
+    /// Только показать, что изменится, не трогая папку модов
+    pub dry_run: bool,
+    /// Сколько снимков папки модов хранить
+    pub backup_limit: usize,
+    /// Сколько файлов скачивать одновременно (для сборок packwiz и .mrpack)
+    pub download_concurrency: usize,
+    /// Доступ к API CurseForge (для модов packwiz с CurseForge)
+    pub curseforge: CurseForgeSettings,
+    /// Клон git-источника, уже обновлённый в этом запуске;
+    /// без него клон обновляется при установке
+    pub repo: Option<PackRepo>,
+}
 
 /// Установка модов в выбранную папку Minecraft
-pub fn install(minecraft_path: &Path, clean_install: bool) {
+pub fn install(config: &mut Config, folder: &ui::GameFolder, clean_install: bool, dry_run: bool) {
     let term = Term::stdout();
     let _ = term.clear_screen();
     ui::print_banner();
     
-    // Выбор типа сборки (клиентская или серверная)
-    let repo_url = match ui::select_build_type() {
-        Some(choice) => {
-            if choice == "󰌌 Клиентская сборка" {
-                CLIENT_REPO_URL
-            } else {
-                SERVER_REPO_URL
-            }
-        }
+    // Выбор источника сборки (клиентская, серверная или добавленная пользователем)
+    let source = match ui::select_source(&config.sources) {
+        Some(source) => source,
         None => return,
     };
 
     // Выбор экземпляра/папки для установки модов
-    let mods_path: std::path::PathBuf = match ui::select_instance(minecraft_path) {
+    let mods_path: PathBuf = match folder.mods_path() {
         Some(path) => path,
         None => return,
     };
 
-    // Создание папки mods, если она не существует
-    if !mods_path.exists() {
-        if let Err(_) = fs::create_dir_all(&mods_path) {
-            return;
-        }
-    }
-
-    // Если clean_install=true, удаляем все .jar файлы из папки mods
-    if clean_install {
-        let spinner = create_docker_spinner("󰅖 Очищаю папку модов...");
-        match mods::clean_mods_dir(&mods_path) {
-            Ok(count) => {
-                spinner.finish_with_message(format!("󰄬 Удалено {} модов", count));
+    // Выбор версии сборки: последняя, закреплённая или один из релизов.
+    // Версии есть только у git-репозиториев
+    let mut repo = None;
+    let git_ref = if source.kind == SourceKind::Git {
+        let spinner = create_docker_spinner("󰇚 Получаю список релизов сборки...");
+        let fetched = PackRepo::fetch(&source.url).and_then(|repo| Ok((repo.tags()?, repo)));
+        let tags = match &fetched {
+            Ok((tags, _)) => {
+                spinner.finish_and_clear();
+                tags.clone()
             }
             Err(e) => {
                 spinner.finish_with_message(format!("󰅖 Ошибка: {}", e));
+                Vec::new()
             }
+        };
+        repo = fetched.ok().map(|(_, repo)| repo);
+
+        let pinned = config.get_git_ref(&mods_path);
+        match ui::select_git_ref(&tags, pinned.as_deref()) {
+            Some(git_ref) => git_ref,
+            None => return,
+        }
+    } else {
+        None
+    };
+
+    let options = InstallOptions {
+        source,
+        clean_install,
+        git_ref,
+        channel: config.channel_spec(&mods_path),
+        dry_run,
+        backup_limit: config.backup_limit,
+        download_concurrency: config.download_concurrency,
+        curseforge: config.curseforge.clone(),
+        repo,
+    };
+
+    if install_mods(&mods_path, &options, &ui::confirm_plan).is_ok() && !dry_run {
+        let saved = config
+            .set_git_ref(&mods_path, options.git_ref.as_deref())
+            .and_then(|()| config.record_sync(&mods_path, &options.source));
+        if let Err(e) = saved {
+            println!("󰅖 Ошибка сохранения конфига: {}", e);
         }
+        println!("󰄬 Установка завершена!");
+        ui::check_duplicates(&mods_path, config.backup_limit);
     }
+    println!("󰝚 Нажмите Enter чтобы продолжить...");
+    let _ = std::io::stdin().read_line(&mut String::new());
+}
 
-    // Путь для временного хранения репозитория
-    let repo_path = minecraft_path.join(TEMP_DIR);
+/// Установка модов без вопросов пользователю, кроме подтверждения плана:
+/// `confirm` получает план и решает, продолжать ли установку.
+/// Возвращает количество скопированных модов.
+pub fn install_mods(
+    mods_path: &Path,
+    options: &InstallOptions,
+    confirm: &dyn Fn(&InstallPlan) -> bool,
+) -> Result<u32, String> {
+    // Восстанавливаем папку модов после прерванной установки
+    if !options.dry_run {
+        report_recovery(mods_path)?;
+    }
 
-    // Очистка временной папки при повторной установке
-    if repo_path.exists() {
-        fs::remove_dir_all(&repo_path).ok();
+    // Создание папки mods, если она не существует
+    if !mods_path.exists() && !options.dry_run {
+        fs::create_dir_all(mods_path)
+            .map_err(|e| format!("Ошибка создания папки mods: {}", e))?;
     }
 
     // Создание многопоточного прогресс-бара
     let multi_progress = MultiProgress::new();
     
-    // Инициализация асинхронного рантайма
-    let rt = Runtime::new().unwrap();
-    
-    // Скачивание репозитория с прогрессом
-    let spinner1 = create_docker_spinner("󰇚 Подключаюсь к репозиторию...");
-    match rt.block_on(download_repo(repo_url, &repo_path)) {
-        Ok(_) => spinner1.finish_with_message("󰄬 Репозиторий скачан!"),
+    // Получение модов сборки из источника с прогрессом
+    let spinner1 = create_docker_spinner("󰇚 Подключаюсь к источнику сборки...");
+    let (repo_path, commit) = match fetch_source(options) {
+        Ok(fetched) => {
+            spinner1.finish_with_message("󰄬 Сборка обновлена!");
+            fetched
+        }
         Err(e) => {
             spinner1.finish_with_message(format!("󰅖 Ошибка: {}", e));
-            return;
+            return Err(e);
         }
+    };
+
+    let mut manifest = Manifest::load(mods_path);
+
+    // План установки: показываем пользователю до любых изменений
+    let plan = InstallPlan::build(&repo_path, mods_path, &manifest, options.clean_install)
+        .map_err(|e| format!("Ошибка сравнения модов: {}", e))?;
+
+    // Пробный запуск: только отчёт, папка модов не меняется
+    if options.dry_run {
+        ui::print_dry_run(&plan, mods_path);
+        return Ok(0);
+    }
+
+    if !confirm(&plan) {
+        return Err("Установка отменена".to_string());
+    }
+
+    if plan.has_changes() {
+        take_snapshot(mods_path, options.backup_limit)?;
     }
 
-    // Установка модов с прогрессом
+    // Установка модов с прогрессом: папка модов меняется целиком или не меняется вовсе
+    let source = Source::Pack { url: options.source.url.clone() };
     let spinner2 = create_docker_spinner("󰇚 Устанавливаю моды...");
-    match mods::install_mods_with_progress(&repo_path, &mods_path, &multi_progress) {
+    let installed = match mods::install_mods_with_progress(
+        mods_path,
+        &plan,
+        &multi_progress,
+        &mut manifest,
+        &source,
+        &commit,
+    ) {
         Ok(count) => {
             spinner2.finish_with_message(format!("󰄬 Установлено {} модов!", count));
+            count
         }
         Err(e) => {
             spinner2.finish_with_message(format!("󰅖 Ошибка: {}", e));
+            return Err(e.to_string());
         }
+    };
+
+    if let Err(e) = manifest.save(mods_path) {
+        println!("󰅖 Ошибка сохранения манифеста: {}", e);
     }
 
-    // Очистка временной папки
-    fs::remove_dir_all(&repo_path).ok();
-    
-    println!("󰄬 Установка завершена!");
+    Ok(installed)
+}
+
+/// Папка с .jar сборки и версия сборки для манифеста.
+/// Git-репозиторий синхронизируется в кеше (сборка packwiz в нём скачивается),
+/// `pack.toml` и .mrpack скачиваются по ссылке в кеш рядом с клонами.
+fn fetch_source(options: &InstallOptions) -> Result<(PathBuf, String), String> {
+    let source = &options.source;
+    match source.kind {
+        SourceKind::Git => {
+            let repo = match &options.repo {
+                Some(repo) => repo.clone(),
+                None => PackRepo::fetch(&source.url)?,
+            };
+            let git_ref = resolve_ref(options, &repo)?;
+            let (repo_path, commit) = download::runtime().block_on(download_repo(repo, git_ref))?;
+
+            // Сборка packwiz: моды скачиваются по описаниям из репозитория
+            if packwiz::is_packwiz(&repo_path) {
+                println!("󰇚 Скачиваю моды сборки packwiz...");
+                let mods = packwiz::resolve(
+                    &repo_path,
+                    source.side,
+                    &options.curseforge,
+                    options.download_concurrency,
+                )?;
+                return Ok((mods, commit));
+            }
+            Ok((repo_path, commit))
+        }
+        SourceKind::Manifest => packwiz::resolve_url(
+            &source.url,
+            source.side,
+            &options.curseforge,
+            options.download_concurrency,
+        ),
+        SourceKind::Mrpack => mrpack::resolve_mods(&source.url, source.side, options.download_concurrency),
+    }
+}
+
+/// Удаление всех модов сборки из папки модов; моды игрока остаются.
+/// Возвращает количество удалённых файлов.
+pub fn clean_pack_mods(
+    mods_path: &Path,
+    dry_run: bool,
+    backup_limit: usize,
+    confirm: &dyn Fn(&InstallPlan) -> bool,
+) -> Result<u32, String> {
+    if !dry_run {
+        report_recovery(mods_path)?;
+    }
+
+    let mut manifest = Manifest::load(mods_path);
+    let plan = InstallPlan::clean(mods_path, &manifest)
+        .map_err(|e| format!("Ошибка чтения папки модов: {}", e))?;
+
+    if dry_run {
+        ui::print_dry_run(&plan, mods_path);
+        return Ok(0);
+    }
+
+    if !confirm(&plan) {
+        return Err("Удаление отменено".to_string());
+    }
+
+    if plan.has_changes() {
+        take_snapshot(mods_path, backup_limit)?;
+    }
+
+    let count = mods::clean_mods_dir(mods_path, &plan, &mut manifest).map_err(|e| e.to_string())?;
+    manifest
+        .save(mods_path)
+        .map_err(|e| format!("Ошибка сохранения манифеста: {}", e))?;
+
+    Ok(count)
+}
+
+/// Восстановление после прерванной установки с сообщением пользователю
+fn report_recovery(mods_path: &Path) -> Result<(), String> {
+    match mods::recover_interrupted(mods_path) {
+        Ok(true) => {
+            println!("󰄬 Папка модов восстановлена после прерванной установки");
+            Ok(())
+        }
+        Ok(false) => Ok(()),
+        Err(e) => Err(format!("Ошибка восстановления папки модов: {}", e)),
+    }
+}
+
+/// Снимок папки модов перед изменениями с выводом прогресса
+fn take_snapshot(mods_path: &Path, backup_limit: usize) -> Result<(), String> {
+    let spinner = create_docker_spinner("󰆓 Сохраняю снимок папки модов...");
+    match backup::snapshot(mods_path, backup_limit) {
+        Ok(path) => {
+            spinner.finish_with_message(format!("󰄬 Снимок сохранён: {}", path.display()));
+            Ok(())
+        }
+        Err(e) => {
+            spinner.finish_with_message(format!("󰅖 Ошибка: {}", e));
+            Err(format!("Не удалось сохранить снимок: {}", e))
+        }
+    }
+}
+
+/// Удаление модов сборки из выбранной папки Minecraft
+pub fn clean(config: &Config, folder: &ui::GameFolder, dry_run: bool) {
+    let mods_path: PathBuf = match folder.mods_path() {
+        Some(path) => path,
+        None => return,
+    };
+
+    match clean_pack_mods(&mods_path, dry_run, config.backup_limit, &ui::confirm_plan) {
+        Ok(count) if !dry_run => println!("󰄬 Удалено {} модов", count),
+        Ok(_) => {}
+        Err(e) => println!("󰅖 {}", e),
+    }
     println!("󰝚 Нажмите Enter чтобы продолжить...");
     let _ = std::io::stdin().read_line(&mut String::new());
 }
 
-/// Асинхронное скачивание репозитория
-async fn download_repo(repo_url: &str, repo_path: &Path) -> Result<(), String> {
-    // Клонирование репозитория
-    Repository::clone(repo_url, repo_path)
-        .map_err(|e| format!("Ошибка клонирования: {}", e))?;
-    
-    Ok(())
+/// Откат папки модов к последнему снимку
+pub fn rollback(folder: &ui::GameFolder, backup_limit: usize) {
+    let mods_path: PathBuf = match folder.mods_path() {
+        Some(path) => path,
+        None => return,
+    };
+
+    match backup::list(&mods_path) {
+        Ok(backups) if !backups.is_empty() => {
+            let latest = &backups[0];
+            let name = latest.file_name().unwrap_or_default().to_string_lossy();
+
+            let confirmed = Confirm::new(&format!("󰝚 Восстановить снимок от {}?", name))
+                .with_default(true)
+                .prompt()
+                .unwrap_or(false);
+
+            if confirmed {
+                match backup::restore(&mods_path, latest, backup_limit) {
+                    Ok(count) => {
+                        println!("󰄬 Восстановлено {} файлов", count);
+                        println!("   Прежние моды сохранены в новый снимок");
+                    }
+                    Err(e) => println!("󰅖 Ошибка восстановления: {}", e),
+                }
+            }
+        }
+        Ok(_) => println!("󰅖 Снимков пока нет"),
+        Err(e) => println!("󰅖 Ошибка чтения снимков: {}", e),
+    }
+    println!("󰝚 Нажмите Enter чтобы продолжить...");
+    let _ = std::io::stdin().read_line(&mut String::new());
+}
+
+/// Ссылка, которую нужно установить: закреплённая версия важнее канала,
+/// канал — ветки источника. Теги канала берутся из уже обновлённого клона `repo`.
+pub fn resolve_ref(options: &InstallOptions, repo: &PackRepo) -> Result<Option<String>, String> {
+    if options.git_ref.is_some() {
+        return Ok(options.git_ref.clone());
+    }
+
+    match &options.channel {
+        Some(ChannelSpec { branch: Some(branch), .. }) => Ok(Some(branch.clone())),
+        Some(ChannelSpec { tag_pattern: Some(pattern), .. }) => {
+            repo.latest_tag_matching(pattern)?
+                .map(Some)
+                .ok_or_else(|| format!("Нет тегов по шаблону '{}'", pattern))
+        }
+        _ => Ok(options.source.branch.clone()),
+    }
+}
+
+/// Изменения модов сборки при установке с параметрами `options`
+/// относительно версии, записанной в манифесте экземпляра.
+/// Обновлённый клон запоминается в `options.repo`, и установка не обновляет его повторно.
+pub fn pending_changes(mods_path: &Path, options: &mut InstallOptions) -> Result<Vec<JarChange>, String> {
+    if options.source.kind != SourceKind::Git {
+        return Err("Каналы обновлений есть только у git-источников".to_string());
+    }
+
+    let repo_url = options.source.url.as_str();
+    let repo = PackRepo::fetch(repo_url)?;
+    let git_ref = resolve_ref(options, &repo)?;
+
+    let manifest = Manifest::load(mods_path);
+    let installed_commit = manifest
+        .pack_files()
+        .find(|f| f.source == Source::Pack { url: repo_url.to_string() })
+        .map(|f| f.version.clone());
+
+    let changes = repo.diff_jars(installed_commit.as_deref(), git_ref.as_deref())?;
+    options.repo = Some(repo);
+    Ok(changes)
+}
+
+/// Переключение канала обновлений экземпляра с показом изменений
+pub fn switch_channel(config: &mut Config, folder: &ui::GameFolder, dry_run: bool) {
+    let term = Term::stdout();
+    let _ = term.clear_screen();
+    ui::print_banner();
+
+    let source = match ui::select_source(&config.sources) {
+        Some(source) => source,
+        None => return,
+    };
+
+    let mods_path: PathBuf = match folder.mods_path() {
+        Some(path) => path,
+        None => return,
+    };
+
+    let names: Vec<String> = config.channels.keys().cloned().collect();
+    let current = config.get_channel(&mods_path);
+    let channel = match ui::select_channel(&names, &current) {
+        Some(channel) => channel,
+        None => return,
+    };
+
+    let mut options = InstallOptions {
+        source,
+        clean_install: false,
+        git_ref: None,
+        channel: config.channels.get(&channel).cloned(),
+        dry_run,
+        backup_limit: config.backup_limit,
+        download_concurrency: config.download_concurrency,
+        curseforge: config.curseforge.clone(),
+        repo: None,
+    };
+
+    let spinner = create_docker_spinner("󰇚 Сравниваю версии сборки...");
+    match pending_changes(&mods_path, &mut options) {
+        Ok(changes) => {
+            spinner.finish_and_clear();
+            ui::print_jar_changes(&changes);
+        }
+        Err(e) => {
+            spinner.finish_with_message(format!("󰅖 Ошибка: {}", e));
+            println!("󰝚 Нажмите Enter чтобы продолжить...");
+            let _ = std::io::stdin().read_line(&mut String::new());
+            return;
+        }
+    }
+
+    // План установки показывает и то, чего нет в разнице версий:
+    // устаревшие файлы сборки и моды Modrinth, которые будут заменены
+    if install_mods(&mods_path, &options, &ui::confirm_plan).is_ok() && !dry_run {
+        let saved = config
+            .set_channel(&mods_path, &channel)
+            .and_then(|()| config.record_sync(&mods_path, &options.source));
+        match saved {
+            Ok(()) => println!("󰄬 Канал переключён: {}", channel),
+            Err(e) => println!("󰅖 Ошибка сохранения конфига: {}", e),
+        }
+        ui::check_duplicates(&mods_path, config.backup_limit);
+    }
+    println!("󰝚 Нажмите Enter чтобы продолжить...");
+    let _ = std::io::stdin().read_line(&mut String::new());
+}
+
+/// Переключение клона репозитория в кеше на общем рантайме скачивания.
+/// git2 блокирующий, поэтому работает в отдельном потоке рантайма.
+/// Возвращает путь к клону и хеш выбранного коммита.
+async fn download_repo(repo: PackRepo, git_ref: Option<String>) -> Result<(PathBuf, String), String> {
+    tokio::task::spawn_blocking(move || repo.checkout(git_ref.as_deref()))
+        .await
+        .map_err(|e| e.to_string())?
 }
 
 /// Создание спиннера с анимацией как у Docker
@@ -120,4 +481,4 @@ fn create_docker_spinner(msg: &str) -> ProgressBar {
     pb.set_message(msg.to_string());
     pb.enable_steady_tick(Duration::from_millis(80));
     pb
-}
\ No newline at end of file
+}
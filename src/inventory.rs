@@ -0,0 +1,322 @@
+use clap::ValueEnum;
+use console::{measure_text_width, pad_str, truncate_str, Alignment};
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::manifest::{Manifest, Source};
+use crate::metadata;
+use crate::ui;
+
+/// Суффикс отключённого мода: лаунчеры не загружают `*.jar.disabled`
+const DISABLED_SUFFIX: &str = ".disabled";
+/// Самая длинная строка в колонке названия
+const MAX_NAME_WIDTH: usize = 40;
+
+/// Откуда мод попал в папку
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Origin {
+    /// Репозиторий сборки
+    Pack,
+    Modrinth,
+    /// Сборка .mrpack
+    Mrpack,
+    #[value(name = "curseforge")]
+    CurseForge,
+    /// Положен игроком, stm о нём не знает
+    Manual,
+}
+
+impl Origin {
+    pub fn label(self) -> &'static str {
+        match self {
+            Origin::Pack => "сборка",
+            Origin::Modrinth => "Modrinth",
+            Origin::Mrpack => ".mrpack",
+            Origin::CurseForge => "CurseForge",
+            Origin::Manual => "вручную",
+        }
+    }
+}
+
+impl From<&Source> for Origin {
+    fn from(source: &Source) -> Self {
+        match source {
+            Source::Pack { .. } => Origin::Pack,
+            Source::Modrinth { .. } => Origin::Modrinth,
+            Source::Mrpack { .. } => Origin::Mrpack,
+            Source::CurseForge { .. } => Origin::CurseForge,
+        }
+    }
+}
+
+/// Мод в папке модов
+#[derive(Serialize, Clone, Debug)]
+pub struct InstalledMod {
+    pub file_name: String,
+    /// id из метаданных jar; `None`, если метаданных нет
+    pub id: Option<String>,
+    /// Название из метаданных или имя файла
+    pub name: String,
+    pub version: Option<String>,
+    pub size: u64,
+    pub loader: Option<&'static str>,
+    pub origin: Origin,
+    pub enabled: bool,
+}
+
+/// Условия отбора модов; пустой фильтр пропускает всё
+#[derive(Default)]
+pub struct Filter {
+    /// Подстрока названия, id, имени файла или источника без учёта регистра
+    pub query: Option<String>,
+    pub origin: Option<Origin>,
+    pub loader: Option<String>,
+    /// `Some(true)` — только включённые, `Some(false)` — только отключённые
+    pub enabled: Option<bool>,
+}
+
+impl Filter {
+    pub fn matches(&self, installed: &InstalledMod) -> bool {
+        if let Some(query) = &self.query {
+            let query = query.to_lowercase();
+            let fields = [
+                Some(installed.name.as_str()),
+                installed.id.as_deref(),
+                Some(installed.file_name.as_str()),
+                Some(installed.origin.label()),
+            ];
+            if !fields.iter().flatten().any(|field| field.to_lowercase().contains(&query)) {
+                return false;
+            }
+        }
+        self.origin.is_none_or(|origin| installed.origin == origin)
+            && self.loader.as_deref().is_none_or(|loader| installed.loader == Some(loader))
+            && self.enabled.is_none_or(|enabled| installed.enabled == enabled)
+    }
+}
+
+/// Все моды папки, включённые и отключённые, по названию.
+/// Папки модов ещё нет — модов тоже нет.
+pub fn scan(mods_path: &Path) -> io::Result<Vec<InstalledMod>> {
+    let manifest = Manifest::load(mods_path);
+    let mut mods = Vec::new();
+
+    let entries = match fs::read_dir(mods_path) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(mods),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let (jar_name, enabled) = match file_name.strip_suffix(DISABLED_SUFFIX) {
+            Some(jar_name) => (jar_name.to_string(), false),
+            None => (file_name.clone(), true),
+        };
+        if !jar_name.ends_with(".jar") || !entry.file_type()?.is_file() {
+            continue;
+        }
+
+        // Отключённый мод записан в манифесте под именем без `.disabled`
+        let origin = manifest
+            .files
+            .iter()
+            .find(|f| f.file_name == jar_name)
+            .map_or(Origin::Manual, |f| Origin::from(&f.source));
+
+        let info = metadata::read_jar(&entry.path()).ok();
+        let primary = info.as_ref().and_then(|info| info.mods.first());
+
+        mods.push(InstalledMod {
+            id: primary.map(|m| m.id.clone()),
+            name: primary.map_or_else(|| jar_name.clone(), |m| m.name.clone()),
+            version: primary.and_then(|m| m.version.clone()),
+            size: entry.metadata()?.len(),
+            loader: primary.map(|m| m.loader),
+            origin,
+            enabled,
+            file_name,
+        });
+    }
+
+    mods.sort_by_key(|m| m.name.to_lowercase());
+    Ok(mods)
+}
+
+/// Таблица модов с итогом по количеству и размеру
+pub fn print_table(mods: &[InstalledMod]) {
+    let headers = ["Название", "Версия", "Размер", "Лоадер", "Источник", "Вкл"];
+    let rows: Vec<[String; 6]> = mods
+        .iter()
+        .map(|m| {
+            [
+                truncate_str(&m.name, MAX_NAME_WIDTH, "…").to_string(),
+                m.version.clone().unwrap_or_else(|| "—".to_string()),
+                ui::format_size(m.size),
+                m.loader.unwrap_or("—").to_string(),
+                m.origin.label().to_string(),
+                if m.enabled { "да" } else { "нет" }.to_string(),
+            ]
+        })
+        .collect();
+
+    let mut widths = headers.map(measure_text_width);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(measure_text_width(cell));
+        }
+    }
+
+    let print_row = |cells: [&str; 6]| {
+        let line: Vec<String> = cells
+            .iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(column, (cell, width))| {
+                // Размер выравниваем по правому краю
+                let align = if column == 2 { Alignment::Right } else { Alignment::Left };
+                pad_str(cell, width, align, None).to_string()
+            })
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    };
+
+    print_row(headers);
+    print_row(widths.map(|width| "─".repeat(width)).each_ref().map(String::as_str));
+    for row in &rows {
+        print_row(row.each_ref().map(String::as_str));
+    }
+
+    let total: u64 = mods.iter().map(|m| m.size).sum();
+    let disabled = mods.iter().filter(|m| !m.enabled).count();
+    println!(
+        "\n󰏗 Модов: {} (отключено: {}), всего {}",
+        mods.len(),
+        disabled,
+        ui::format_size(total)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::ManagedFile;
+    use std::io::Write;
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    fn temp_root(name: &str) -> std::path::PathBuf {
+        let root = std::env::temp_dir().join(format!("stm-inventory-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    /// jar с `fabric.mod.json`
+    fn write_fabric_jar(path: &Path, id: &str, name: &str) {
+        let mut zip = ZipWriter::new(fs::File::create(path).unwrap());
+        zip.start_file("fabric.mod.json", FileOptions::default()).unwrap();
+        let json = serde_json::json!({ "id": id, "name": name, "version": "1.0" });
+        zip.write_all(json.to_string().as_bytes()).unwrap();
+        zip.finish().unwrap();
+    }
+
+    fn installed(name: &str, origin: Origin, loader: Option<&'static str>, enabled: bool) -> InstalledMod {
+        InstalledMod {
+            file_name: format!("{}.jar", name.to_lowercase()),
+            id: loader.map(|_| name.to_lowercase()),
+            name: name.to_string(),
+            version: None,
+            size: 0,
+            loader,
+            origin,
+            enabled,
+        }
+    }
+
+    #[test]
+    fn scan_classifies_disabled_files_and_origins() {
+        let root = temp_root("scan");
+        let mods_dir = root.join("mods");
+        fs::create_dir_all(mods_dir.join("sub.jar")).unwrap();
+        write_fabric_jar(&mods_dir.join("sodium.jar"), "sodium", "Sodium");
+        fs::write(mods_dir.join("pack.jar.disabled"), "not a zip").unwrap();
+        fs::write(mods_dir.join("manual.jar"), "not a zip").unwrap();
+        fs::write(mods_dir.join("notes.txt"), "").unwrap();
+        fs::write(mods_dir.join("other.disabled"), "").unwrap();
+
+        let mut manifest = Manifest::default();
+        for (file_name, source) in [
+            ("sodium.jar", Source::Modrinth { project_id: "AANobbMI".to_string() }),
+            // Отключённый мод записан под именем без `.disabled`
+            ("pack.jar", Source::Pack { url: "https://example.com/pack.git".to_string() }),
+        ] {
+            manifest.record(ManagedFile {
+                file_name: file_name.to_string(),
+                source,
+                version: "1".to_string(),
+                sha256: String::new(),
+            });
+        }
+        manifest.save(&mods_dir).unwrap();
+
+        let mods = scan(&mods_dir).unwrap();
+        let summary: Vec<_> = mods
+            .iter()
+            .map(|m| (m.file_name.as_str(), m.name.as_str(), m.id.as_deref(), m.loader, m.origin, m.enabled))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("manual.jar", "manual.jar", None, None, Origin::Manual, true),
+                ("pack.jar.disabled", "pack.jar", None, None, Origin::Pack, false),
+                ("sodium.jar", "Sodium", Some("sodium"), Some("fabric"), Origin::Modrinth, true),
+            ]
+        );
+        assert_eq!(mods[2].version.as_deref(), Some("1.0"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn scan_of_missing_folder_is_empty() {
+        let root = temp_root("missing");
+        assert!(scan(&root.join("mods")).unwrap().is_empty());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn filter_combines_conditions() {
+        let sodium = installed("Sodium", Origin::Modrinth, Some("fabric"), true);
+        let manual = installed("Extra", Origin::Manual, None, false);
+
+        assert!(Filter::default().matches(&sodium));
+        assert!(Filter::default().matches(&manual));
+
+        let by_query = |query: &str| Filter { query: Some(query.to_string()), ..Filter::default() };
+        assert!(by_query("SOD").matches(&sodium));
+        assert!(by_query("sodium.jar").matches(&sodium));
+        // Подпись источника тоже ищется
+        assert!(by_query("вручную").matches(&manual));
+        assert!(!by_query("lithium").matches(&sodium));
+
+        let by_origin = Filter { origin: Some(Origin::Modrinth), ..Filter::default() };
+        assert!(by_origin.matches(&sodium) && !by_origin.matches(&manual));
+
+        let by_loader = Filter { loader: Some("fabric".to_string()), ..Filter::default() };
+        assert!(by_loader.matches(&sodium) && !by_loader.matches(&manual));
+
+        let disabled = Filter { enabled: Some(false), ..Filter::default() };
+        assert!(!disabled.matches(&sodium) && disabled.matches(&manual));
+
+        let combined = Filter {
+            query: Some("sodium".to_string()),
+            enabled: Some(false),
+            ..Filter::default()
+        };
+        assert!(!combined.matches(&sodium));
+    }
+}
@@ -0,0 +1,920 @@
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Экземпляр игры, найденный в лаунчере
+#[derive(Clone, Debug)]
+pub struct LauncherInstance {
+    /// Название лаунчера, например `Prism`
+    pub launcher: &'static str,
+    /// Имя экземпляра так, как его показывает лаунчер
+    pub name: String,
+    /// Папка экземпляра с файлами лаунчера (`instance.cfg`, `mmc-pack.json` и т. п.)
+    pub instance_dir: PathBuf,
+    /// Папка игры: в ней лежат `mods`, `saves`, `options.txt`
+    pub game_dir: PathBuf,
+}
+
+impl LauncherInstance {
+    /// Подпись для меню: «Prism › StoryTime S3»
+    pub fn label(&self) -> String {
+        format!("{} › {}", self.launcher, self.name)
+    }
+}
+
+/// Версия игры и лоадер экземпляра
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GameInfo {
+    pub game_version: String,
+    /// fabric, quilt, forge или neoforge; `None` — игра без лоадера
+    pub loader: Option<String>,
+    pub loader_version: Option<String>,
+}
+
+/// Лаунчер Minecraft: где он хранит данные и как в них найти экземпляры
+pub trait LauncherAdapter {
+    /// Название лаунчера для меню
+    fn name(&self) -> &'static str;
+
+    /// Возможные папки данных лаунчера (в том числе Flatpak)
+    fn data_roots(&self) -> Vec<PathBuf>;
+
+    /// Экземпляры в одной папке данных
+    fn instances_in(&self, root: &Path) -> Vec<LauncherInstance>;
+
+    /// Папки, внутри которых лежат экземпляры папки данных `root`;
+    /// экземпляры перебираются, только если искомая папка внутри одной из них
+    fn instance_roots(&self, root: &Path) -> Vec<PathBuf> {
+        vec![root.to_path_buf()]
+    }
+
+    /// Версия игры и лоадер экземпляра по файлам лаунчера
+    fn game_info(&self, _instance: &LauncherInstance) -> Option<GameInfo> {
+        None
+    }
+
+    /// Экземпляры во всех существующих папках данных
+    fn instances(&self) -> Vec<LauncherInstance> {
+        self.data_roots()
+            .iter()
+            .filter(|root| root.is_dir())
+            .flat_map(|root| self.instances_in(root))
+            .collect()
+    }
+}
+
+/// Все поддерживаемые лаунчеры
+pub fn adapters() -> Vec<Box<dyn LauncherAdapter>> {
+    vec![
+        Box::new(Official),
+        Box::new(MultiMcFamily {
+            name: "Prism",
+            dir_name: "PrismLauncher",
+            flatpak: Some("org.prismlauncher.PrismLauncher"),
+            config_file: "prismlauncher.cfg",
+        }),
+        Box::new(MultiMcFamily {
+            name: "PolyMC",
+            dir_name: "PolyMC",
+            flatpak: Some("org.polymc.PolyMC"),
+            config_file: "polymc.cfg",
+        }),
+        Box::new(MultiMcFamily {
+            name: "MultiMC",
+            dir_name: "multimc",
+            flatpak: None,
+            config_file: "multimc.cfg",
+        }),
+        Box::new(AtLauncher),
+        Box::new(GdLauncher),
+        Box::new(ModrinthApp),
+        Box::new(TLauncher),
+    ]
+}
+
+/// Экземпляры всех найденных лаунчеров
+pub fn discover() -> Vec<LauncherInstance> {
+    adapters().iter().flat_map(|adapter| adapter.instances()).collect()
+}
+
+/// Экземпляр лаунчера, которому принадлежит папка (папка игры или папка экземпляра)
+pub fn identify(path: &Path) -> Option<LauncherInstance> {
+    find_owner(adapters(), path).map(|(_, instance)| instance)
+}
+
+/// Лаунчер и его экземпляр для папки. Экземпляры перебираются только в тех
+/// папках данных, внутри которых лежит `path`, а не во всех сразу
+fn find_owner(
+    adapters: Vec<Box<dyn LauncherAdapter>>,
+    path: &Path,
+) -> Option<(Box<dyn LauncherAdapter>, LauncherInstance)> {
+    for adapter in adapters {
+        let found = adapter
+            .data_roots()
+            .iter()
+            .filter(|root| root.is_dir())
+            .filter(|root| adapter.instance_roots(root).iter().any(|dir| path.starts_with(dir)))
+            .find_map(|root| {
+                adapter
+                    .instances_in(root)
+                    .into_iter()
+                    .find(|instance| instance.game_dir == path || instance.instance_dir == path)
+            });
+        if let Some(instance) = found {
+            return Some((adapter, instance));
+        }
+    }
+    None
+}
+
+/// Версия игры и лоадер для папки игры: сначала по экземпляру известного лаунчера,
+/// затем по файлам лаунчеров рядом с папкой (`mmc-pack.json`, `profile.json`, `versions/`)
+pub fn detect_game(game_dir: &Path) -> Option<GameInfo> {
+    if let Some((adapter, instance)) = find_owner(adapters(), game_dir) {
+        if let Some(info) = adapter.game_info(&instance) {
+            return Some(info);
+        }
+    }
+
+    game_dir
+        .parent()
+        .and_then(|instance_dir| from_mmc_pack(&instance_dir.join("mmc-pack.json")))
+        .or_else(|| from_modrinth_profile(&game_dir.join("profile.json")))
+        .or_else(|| from_official(game_dir, game_dir))
+}
+
+/// Официальный лаунчер: `.minecraft` и профили с отдельной папкой игры
+struct Official;
+
+impl LauncherAdapter for Official {
+    fn name(&self) -> &'static str {
+        "Официальный"
+    }
+
+    fn data_roots(&self) -> Vec<PathBuf> {
+        let mut roots = Vec::new();
+        if let Some(home) = dirs::home_dir() {
+            roots.push(home.join(".minecraft"));
+        }
+        if let Some(data) = dirs::data_dir() {
+            // Windows: %APPDATA%\.minecraft, macOS: ~/Library/Application Support/minecraft
+            roots.push(data.join(".minecraft"));
+            roots.push(data.join("minecraft"));
+        }
+        dedup(roots)
+    }
+
+    fn instances_in(&self, root: &Path) -> Vec<LauncherInstance> {
+        let mut instances = vec![LauncherInstance {
+            launcher: self.name(),
+            name: "Minecraft".to_string(),
+            instance_dir: root.to_path_buf(),
+            game_dir: root.to_path_buf(),
+        }];
+
+        // Профили с собственной папкой игры — отдельные экземпляры
+        let profiles = read_json(&root.join("launcher_profiles.json"));
+        if let Some(profiles) = profiles.as_ref().and_then(|p| p["profiles"].as_object()) {
+            for profile in profiles.values() {
+                let game_dir = match profile["gameDir"].as_str() {
+                    Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+                    _ => continue,
+                };
+                if instances.iter().any(|i| i.game_dir == game_dir) {
+                    continue;
+                }
+                instances.push(LauncherInstance {
+                    launcher: self.name(),
+                    name: profile["name"].as_str().unwrap_or("Профиль").to_string(),
+                    instance_dir: root.to_path_buf(),
+                    game_dir,
+                });
+            }
+        }
+
+        instances
+    }
+
+    fn instance_roots(&self, root: &Path) -> Vec<PathBuf> {
+        // Профиль может хранить игру где угодно, а читается всего один файл
+        self.instances_in(root).into_iter().map(|instance| instance.game_dir).collect()
+    }
+
+    fn game_info(&self, instance: &LauncherInstance) -> Option<GameInfo> {
+        from_official(&instance.instance_dir, &instance.game_dir)
+    }
+}
+
+/// Prism Launcher, PolyMC и MultiMC: общий формат `instances/<папка>/instance.cfg`
+struct MultiMcFamily {
+    name: &'static str,
+    /// Папка данных в `~/.local/share`
+    dir_name: &'static str,
+    /// Идентификатор Flatpak-приложения
+    flatpak: Option<&'static str>,
+    /// Настройки лаунчера, в которых может быть переопределена папка экземпляров
+    config_file: &'static str,
+}
+
+impl LauncherAdapter for MultiMcFamily {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn data_roots(&self) -> Vec<PathBuf> {
+        let mut roots = Vec::new();
+        if let Some(data) = dirs::data_dir() {
+            roots.push(data.join(self.dir_name));
+        }
+        if let (Some(home), Some(flatpak)) = (dirs::home_dir(), self.flatpak) {
+            roots.push(home.join(".var/app").join(flatpak).join("data").join(self.dir_name));
+        }
+        dedup(roots)
+    }
+
+    fn instances_in(&self, root: &Path) -> Vec<LauncherInstance> {
+        subdirs(&self.instances_dir(root))
+            .into_iter()
+            .filter(|dir| dir.join("instance.cfg").is_file())
+            .map(|dir| {
+                let name = read_cfg(&dir.join("instance.cfg"), "name")
+                    .unwrap_or_else(|| folder_name(&dir));
+                // Старые экземпляры хранят игру в `minecraft`, новые — в `.minecraft`
+                let game_dir = if dir.join("minecraft").is_dir() && !dir.join(".minecraft").is_dir() {
+                    dir.join("minecraft")
+                } else {
+                    dir.join(".minecraft")
+                };
+                LauncherInstance {
+                    launcher: self.name,
+                    name,
+                    instance_dir: dir,
+                    game_dir,
+                }
+            })
+            .collect()
+    }
+
+    fn instance_roots(&self, root: &Path) -> Vec<PathBuf> {
+        vec![self.instances_dir(root)]
+    }
+
+    fn game_info(&self, instance: &LauncherInstance) -> Option<GameInfo> {
+        from_mmc_pack(&instance.instance_dir.join("mmc-pack.json"))
+    }
+}
+
+impl MultiMcFamily {
+    /// Папка экземпляров: `instances` или своя из настроек лаунчера (в том числе абсолютная)
+    fn instances_dir(&self, root: &Path) -> PathBuf {
+        read_cfg(&root.join(self.config_file), "InstanceDir")
+            .map(|dir| root.join(dir))
+            .unwrap_or_else(|| root.join("instances"))
+    }
+}
+
+/// ATLauncher: `instances/<папка>/instance.json`, игра прямо в папке экземпляра
+struct AtLauncher;
+
+impl LauncherAdapter for AtLauncher {
+    fn name(&self) -> &'static str {
+        "ATLauncher"
+    }
+
+    fn data_roots(&self) -> Vec<PathBuf> {
+        let mut roots = Vec::new();
+        if let Some(data) = dirs::data_dir() {
+            roots.push(data.join("ATLauncher"));
+        }
+        if let Some(home) = dirs::home_dir() {
+            roots.push(home.join(".var/app/com.atlauncher.ATLauncher/data/ATLauncher"));
+        }
+        dedup(roots)
+    }
+
+    fn instances_in(&self, root: &Path) -> Vec<LauncherInstance> {
+        subdirs(&root.join("instances"))
+            .into_iter()
+            .filter(|dir| dir.join("instance.json").is_file())
+            .map(|dir| {
+                let name = read_json(&dir.join("instance.json"))
+                    .and_then(|json| json["launcher"]["name"].as_str().map(String::from))
+                    .unwrap_or_else(|| folder_name(&dir));
+                LauncherInstance {
+                    launcher: self.name(),
+                    name,
+                    instance_dir: dir.clone(),
+                    game_dir: dir,
+                }
+            })
+            .collect()
+    }
+}
+
+/// GDLauncher: старый (`gdlauncher_next`, игра в папке экземпляра)
+/// и новый (`gdlauncher_carbon`, игра в `instance/`)
+struct GdLauncher;
+
+impl LauncherAdapter for GdLauncher {
+    fn name(&self) -> &'static str {
+        "GDLauncher"
+    }
+
+    fn data_roots(&self) -> Vec<PathBuf> {
+        let mut roots = Vec::new();
+        if let Some(data) = dirs::data_dir() {
+            roots.push(data.join("gdlauncher_next"));
+            roots.push(data.join("gdlauncher_carbon").join("data"));
+        }
+        roots
+    }
+
+    fn instances_in(&self, root: &Path) -> Vec<LauncherInstance> {
+        subdirs(&root.join("instances"))
+            .into_iter()
+            .filter_map(|dir| {
+                if dir.join("instance.json").is_file() {
+                    let name = read_json(&dir.join("instance.json"))
+                        .and_then(|json| json["name"].as_str().map(String::from))
+                        .unwrap_or_else(|| folder_name(&dir));
+                    Some(LauncherInstance {
+                        launcher: self.name(),
+                        name,
+                        game_dir: dir.join("instance"),
+                        instance_dir: dir,
+                    })
+                } else if dir.join("config.json").is_file() {
+                    // В старом GDLauncher имя экземпляра — имя папки
+                    Some(LauncherInstance {
+                        launcher: self.name(),
+                        name: folder_name(&dir),
+                        instance_dir: dir.clone(),
+                        game_dir: dir,
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+/// Modrinth App: `profiles/<папка>`, игра прямо в папке профиля
+struct ModrinthApp;
+
+impl LauncherAdapter for ModrinthApp {
+    fn name(&self) -> &'static str {
+        "Modrinth App"
+    }
+
+    fn data_roots(&self) -> Vec<PathBuf> {
+        let mut roots = Vec::new();
+        if let Some(data) = dirs::data_dir() {
+            roots.push(data.join("ModrinthApp"));
+            roots.push(data.join("com.modrinth.theseus"));
+        }
+        if let Some(home) = dirs::home_dir() {
+            roots.push(home.join(".var/app/com.modrinth.ModrinthApp/data/ModrinthApp"));
+        }
+        dedup(roots)
+    }
+
+    fn instances_in(&self, root: &Path) -> Vec<LauncherInstance> {
+        subdirs(&root.join("profiles"))
+            .into_iter()
+            .map(|dir| {
+                // Старые версии хранили имя в profile.json, новые — в базе,
+                // а папка профиля называется так же, как профиль
+                let name = read_json(&dir.join("profile.json"))
+                    .and_then(|json| json["metadata"]["name"].as_str().map(String::from))
+                    .unwrap_or_else(|| folder_name(&dir));
+                LauncherInstance {
+                    launcher: self.name(),
+                    name,
+                    instance_dir: dir.clone(),
+                    game_dir: dir,
+                }
+            })
+            .collect()
+    }
+
+    fn game_info(&self, instance: &LauncherInstance) -> Option<GameInfo> {
+        from_modrinth_profile(&instance.instance_dir.join("profile.json"))
+    }
+}
+
+/// TLauncher: сборки лежат в `versions/<имя>` общей папки `.minecraft`
+/// и используют эту папку как папку игры
+struct TLauncher;
+
+impl LauncherAdapter for TLauncher {
+    fn name(&self) -> &'static str {
+        "TLauncher"
+    }
+
+    fn data_roots(&self) -> Vec<PathBuf> {
+        let mut roots = Vec::new();
+        if let Some(home) = dirs::home_dir() {
+            roots.push(home.join(".minecraft"));
+            roots.push(home.join(".tlauncher"));
+        }
+        if let Some(data) = dirs::data_dir() {
+            roots.push(data.join(".minecraft"));
+        }
+        dedup(roots)
+    }
+
+    fn instances_in(&self, root: &Path) -> Vec<LauncherInstance> {
+        // Без файлов TLauncher это папка официального лаунчера
+        let is_tlauncher = root.join("TlauncherProfiles.json").is_file()
+            || root.join("tlauncher_profiles.json").is_file()
+            || folder_name(root) == ".tlauncher";
+        if !is_tlauncher {
+            return Vec::new();
+        }
+
+        subdirs(&root.join("versions"))
+            .into_iter()
+            .filter(|dir| dir.join("mods").is_dir() || dir.join("TLauncherAdditional.json").is_file())
+            .map(|dir| LauncherInstance {
+                launcher: self.name(),
+                name: folder_name(&dir),
+                instance_dir: dir.clone(),
+                game_dir: dir,
+            })
+            .collect()
+    }
+
+    fn game_info(&self, instance: &LauncherInstance) -> Option<GameInfo> {
+        // Папка сборки — это папка версии: `versions/<имя>/<имя>.json`
+        let versions_dir = instance.instance_dir.parent()?;
+        from_version_json(versions_dir, &folder_name(&instance.instance_dir))
+    }
+}
+
+/// `mmc-pack.json` Prism/MultiMC: компоненты экземпляра с версиями
+fn from_mmc_pack(path: &Path) -> Option<GameInfo> {
+    let pack = read_json(path)?;
+    let components = pack["components"].as_array()?;
+    let version_of = |uid: &str| {
+        components
+            .iter()
+            .find(|c| c["uid"] == uid)
+            .and_then(|c| c["version"].as_str().or_else(|| c["cachedVersion"].as_str()))
+            .map(String::from)
+    };
+
+    let game_version = version_of("net.minecraft")?;
+    let loader = [
+        ("net.fabricmc.fabric-loader", "fabric"),
+        ("org.quiltmc.quilt-loader", "quilt"),
+        ("net.neoforged", "neoforge"),
+        ("net.minecraftforge", "forge"),
+    ]
+    .into_iter()
+    .find_map(|(uid, loader)| version_of(uid).map(|version| (loader, version)));
+
+    Some(GameInfo {
+        game_version,
+        loader: loader.as_ref().map(|(loader, _)| loader.to_string()),
+        loader_version: loader.map(|(_, version)| version),
+    })
+}
+
+/// `profile.json` Modrinth App: `metadata.game_version` и `metadata.loader`
+fn from_modrinth_profile(path: &Path) -> Option<GameInfo> {
+    let profile = read_json(path)?;
+    let metadata = &profile["metadata"];
+    let game_version = metadata["game_version"].as_str()?.to_string();
+    let loader = metadata["loader"]
+        .as_str()
+        .filter(|loader| *loader != "vanilla")
+        .map(String::from);
+    let loader_version = metadata["loader_version"]["id"].as_str().map(String::from);
+
+    Some(GameInfo { game_version, loader, loader_version })
+}
+
+/// Официальный лаунчер: версия последнего запущенного профиля с этой папкой игры
+/// из `launcher_profiles.json`, её описание ищется в `versions/`
+fn from_official(root: &Path, game_dir: &Path) -> Option<GameInfo> {
+    let profiles = read_json(&root.join("launcher_profiles.json"))?;
+    let version_id = profiles["profiles"]
+        .as_object()?
+        .values()
+        .filter(|profile| match profile["gameDir"].as_str() {
+            Some(dir) if !dir.is_empty() => Path::new(dir) == game_dir,
+            _ => root == game_dir,
+        })
+        .max_by_key(|profile| profile["lastUsed"].as_str().unwrap_or("").to_string())
+        .and_then(|profile| profile["lastVersionId"].as_str())?
+        .to_string();
+
+    from_version_json(&root.join("versions"), &version_id)
+}
+
+/// `versions/<id>/<id>.json`: модифицированная версия наследует ванильную
+/// (`inheritsFrom`), лоадер виден по библиотекам
+fn from_version_json(versions_dir: &Path, id: &str) -> Option<GameInfo> {
+    let json = read_json(&versions_dir.join(id).join(format!("{}.json", id)))?;
+    let game_version = json["inheritsFrom"]
+        .as_str()
+        .or_else(|| json["id"].as_str())?
+        .to_string();
+
+    let libraries = json["libraries"].as_array().cloned().unwrap_or_default();
+    let loader = libraries.iter().filter_map(|lib| lib["name"].as_str()).find_map(|name| {
+        let (group_artifact, version) = name.rsplit_once(':')?;
+        let (loader, with_game_version) = match group_artifact {
+            "net.fabricmc:fabric-loader" => ("fabric", false),
+            "org.quiltmc:quilt-loader" => ("quilt", false),
+            "net.neoforged:neoforge" => ("neoforge", false),
+            "net.neoforged:forge" => ("neoforge", true),
+            "net.minecraftforge:forge" | "net.minecraftforge:fmlloader" => ("forge", true),
+            _ => return None,
+        };
+        // Версии Forge записаны как `1.20.1-47.2.0`, а у Quilt дефис отделяет
+        // пре-релиз (`0.19.0-beta.18`), его отрезать нельзя
+        let version = match version.split_once('-') {
+            Some((_, v)) if with_game_version => v,
+            _ => version,
+        };
+        Some((loader, version.to_string()))
+    });
+
+    Some(GameInfo {
+        game_version,
+        loader: loader.as_ref().map(|(loader, _)| loader.to_string()),
+        loader_version: loader.map(|(_, version)| version),
+    })
+}
+
+/// Подпапки, отсортированные по имени
+fn subdirs(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.is_dir() && !folder_name(p).starts_with('.'))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    dirs.sort();
+    dirs
+}
+
+/// Значение `key=value` из файла настроек в формате INI (instance.cfg, prismlauncher.cfg)
+fn read_cfg(path: &Path, key: &str) -> Option<String> {
+    fs::read_to_string(path).ok()?.lines().find_map(|line| {
+        let (k, v) = line.split_once('=')?;
+        (k.trim() == key && !v.trim().is_empty()).then(|| v.trim().to_string())
+    })
+}
+
+fn read_json(path: &Path) -> Option<Value> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn folder_name(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().to_string()
+}
+
+/// Без повторов: на разных системах `home` и `data_dir` могут совпадать
+fn dedup(mut paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut seen = Vec::new();
+    paths.retain(|p| {
+        let fresh = !seen.contains(p);
+        seen.push(p.clone());
+        fresh
+    });
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("stm-launchers-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn prism() -> MultiMcFamily {
+        MultiMcFamily {
+            name: "Prism",
+            dir_name: "PrismLauncher",
+            flatpak: None,
+            config_file: "prismlauncher.cfg",
+        }
+    }
+
+    /// Лаунчер с одной папкой данных: настоящие лежат в домашней папке
+    struct InRoot<A> {
+        root: PathBuf,
+        adapter: A,
+    }
+
+    impl<A: LauncherAdapter> LauncherAdapter for InRoot<A> {
+        fn name(&self) -> &'static str {
+            self.adapter.name()
+        }
+
+        fn data_roots(&self) -> Vec<PathBuf> {
+            vec![self.root.clone()]
+        }
+
+        fn instances_in(&self, root: &Path) -> Vec<LauncherInstance> {
+            self.adapter.instances_in(root)
+        }
+
+        fn instance_roots(&self, root: &Path) -> Vec<PathBuf> {
+            self.adapter.instance_roots(root)
+        }
+
+        fn game_info(&self, instance: &LauncherInstance) -> Option<GameInfo> {
+            self.adapter.game_info(instance)
+        }
+    }
+
+    #[test]
+    fn multimc_instances_from_instance_cfg() {
+        let root = temp_root("prism");
+        let instances = root.join("instances");
+        write(&instances.join("s3/instance.cfg"), "[General]\nname=StoryTime S3\n");
+        fs::create_dir_all(instances.join("s3/.minecraft")).unwrap();
+        // Старый экземпляр MultiMC без имени: игра в `minecraft`
+        write(&instances.join("old/instance.cfg"), "InstanceType=OneSix\n");
+        fs::create_dir_all(instances.join("old/minecraft")).unwrap();
+        // Не экземпляр и скрытая папка
+        fs::create_dir_all(instances.join("_LAUNCHER_TEMP")).unwrap();
+        write(&instances.join(".tmp/instance.cfg"), "name=Скрытый\n");
+
+        let found = prism().instances_in(&root);
+        let names: Vec<&str> = found.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, ["old", "StoryTime S3"]);
+        assert_eq!(found[0].game_dir, instances.join("old/minecraft"));
+        assert_eq!(found[1].game_dir, instances.join("s3/.minecraft"));
+        assert_eq!(found[1].label(), "Prism › StoryTime S3");
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn multimc_custom_instance_dir() {
+        let root = temp_root("prism-custom");
+        let elsewhere = temp_root("prism-elsewhere");
+        write(
+            &root.join("prismlauncher.cfg"),
+            &format!("[General]\nInstanceDir={}\n", elsewhere.display()),
+        );
+        write(&elsewhere.join("pack/instance.cfg"), "name=Pack\n");
+
+        let adapter = prism();
+        assert_eq!(adapter.instance_roots(&root), vec![elsewhere.clone()]);
+        let found = adapter.instances_in(&root);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].game_dir, elsewhere.join("pack/.minecraft"));
+
+        let adapters: Vec<Box<dyn LauncherAdapter>> = vec![Box::new(InRoot { root: root.clone(), adapter })];
+        let (_, owner) = find_owner(adapters, &elsewhere.join("pack/.minecraft")).unwrap();
+        assert_eq!(owner.name, "Pack");
+
+        fs::remove_dir_all(&root).unwrap();
+        fs::remove_dir_all(&elsewhere).unwrap();
+    }
+
+    #[test]
+    fn owner_is_searched_only_in_matching_roots() {
+        let root = temp_root("owner");
+        write(&root.join("instances/a/instance.cfg"), "name=A\n");
+        let adapters = || -> Vec<Box<dyn LauncherAdapter>> {
+            vec![
+                Box::new(InRoot { root: root.join("missing"), adapter: AtLauncher }),
+                Box::new(InRoot { root: root.clone(), adapter: prism() }),
+            ]
+        };
+
+        let (adapter, instance) = find_owner(adapters(), &root.join("instances/a")).unwrap();
+        assert_eq!(adapter.name(), "Prism");
+        assert_eq!(instance.name, "A");
+        assert!(find_owner(adapters(), &root.join("instances/b")).is_none());
+        assert!(find_owner(adapters(), Path::new("/somewhere/else")).is_none());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn atlauncher_instances() {
+        let root = temp_root("atlauncher");
+        write(&root.join("instances/Pack1/instance.json"), r#"{ "launcher": { "name": "Pack One" } }"#);
+        write(&root.join("instances/Pack2/instance.json"), "{}");
+        fs::create_dir_all(root.join("instances/NotAnInstance")).unwrap();
+
+        let found = AtLauncher.instances_in(&root);
+        let names: Vec<&str> = found.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names, ["Pack One", "Pack2"]);
+        assert_eq!(found[0].game_dir, root.join("instances/Pack1"));
+        assert_eq!(found[0].instance_dir, found[0].game_dir);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn gdlauncher_instances() {
+        let root = temp_root("gdlauncher");
+        // Новый GDLauncher: игра в `instance/`
+        write(&root.join("instances/carbon/instance.json"), r#"{ "name": "Carbon Pack" }"#);
+        // Старый: `config.json`, имя — имя папки
+        write(&root.join("instances/Next Pack/config.json"), "{}");
+        fs::create_dir_all(root.join("instances/empty")).unwrap();
+
+        let found = GdLauncher.instances_in(&root);
+        assert_eq!(found.len(), 2);
+        let next = found.iter().find(|i| i.name == "Next Pack").unwrap();
+        assert_eq!(next.game_dir, root.join("instances/Next Pack"));
+        let carbon = found.iter().find(|i| i.name == "Carbon Pack").unwrap();
+        assert_eq!(carbon.game_dir, root.join("instances/carbon/instance"));
+        assert_eq!(carbon.instance_dir, root.join("instances/carbon"));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    fn info(game_version: &str, loader: Option<&str>, loader_version: Option<&str>) -> GameInfo {
+        GameInfo {
+            game_version: game_version.to_string(),
+            loader: loader.map(String::from),
+            loader_version: loader_version.map(String::from),
+        }
+    }
+
+    #[test]
+    fn mmc_pack_loaders() {
+        let root = temp_root("mmc-pack");
+        let cases = [
+            ("net.fabricmc.fabric-loader", "0.15.11", "fabric"),
+            ("org.quiltmc.quilt-loader", "0.19.0-beta.18", "quilt"),
+            ("net.minecraftforge", "47.2.0", "forge"),
+            ("net.neoforged", "20.4.80", "neoforge"),
+        ];
+        for (uid, version, loader) in cases {
+            let path = root.join(format!("{}.json", loader));
+            write(
+                &path,
+                &format!(
+                    r#"{{ "components": [
+                        {{ "uid": "org.lwjgl3", "version": "3.3.1" }},
+                        {{ "uid": "net.minecraft", "version": "1.20.1" }},
+                        {{ "uid": "{}", "cachedVersion": "{}" }}
+                    ] }}"#,
+                    uid, version
+                ),
+            );
+            assert_eq!(from_mmc_pack(&path), Some(info("1.20.1", Some(loader), Some(version))));
+        }
+
+        let vanilla = root.join("vanilla.json");
+        write(&vanilla, r#"{ "components": [{ "uid": "net.minecraft", "version": "1.21" }] }"#);
+        assert_eq!(from_mmc_pack(&vanilla), Some(info("1.21", None, None)));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn modrinth_app_profile() {
+        let root = temp_root("modrinth-profile");
+        let path = root.join("profile.json");
+        write(
+            &path,
+            r#"{ "metadata": { "name": "Pack", "game_version": "1.20.4", "loader": "neoforge",
+                 "loader_version": { "id": "20.4.80" } } }"#,
+        );
+        assert_eq!(from_modrinth_profile(&path), Some(info("1.20.4", Some("neoforge"), Some("20.4.80"))));
+
+        write(&path, r#"{ "metadata": { "game_version": "1.20.1", "loader": "vanilla" } }"#);
+        assert_eq!(from_modrinth_profile(&path), Some(info("1.20.1", None, None)));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// `versions/<id>/<id>.json` с одной библиотекой лоадера
+    fn write_version(versions_dir: &Path, id: &str, library: &str) {
+        write(
+            &versions_dir.join(id).join(format!("{}.json", id)),
+            &format!(
+                r#"{{ "id": "{}", "inheritsFrom": "1.20.1", "libraries": [
+                    {{ "name": "org.ow2.asm:asm:9.6" }},
+                    {{ "name": "{}" }}
+                ] }}"#,
+                id, library
+            ),
+        );
+    }
+
+    #[test]
+    fn version_json_loaders() {
+        let root = temp_root("versions");
+        let cases = [
+            ("net.fabricmc:fabric-loader:0.15.11", "fabric", "0.15.11"),
+            ("org.quiltmc:quilt-loader:0.19.0-beta.18", "quilt", "0.19.0-beta.18"),
+            ("net.minecraftforge:forge:1.20.1-47.2.0", "forge", "47.2.0"),
+            ("net.minecraftforge:fmlloader:1.20.1-47.2.0", "forge", "47.2.0"),
+            ("net.neoforged:forge:1.20.1-47.1.106", "neoforge", "47.1.106"),
+            ("net.neoforged:neoforge:20.4.80", "neoforge", "20.4.80"),
+        ];
+        for (index, (library, loader, version)) in cases.into_iter().enumerate() {
+            let id = format!("modded-{}", index);
+            write_version(&root, &id, library);
+            assert_eq!(from_version_json(&root, &id), Some(info("1.20.1", Some(loader), Some(version))), "{}", library);
+        }
+
+        // Ванильная версия без `inheritsFrom`
+        write(&root.join("1.21/1.21.json"), r#"{ "id": "1.21", "libraries": [] }"#);
+        assert_eq!(from_version_json(&root, "1.21"), Some(info("1.21", None, None)));
+        assert_eq!(from_version_json(&root, "missing"), None);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn official_profiles_pick_last_used_version() {
+        let root = temp_root("official");
+        let separate = root.join("separate");
+        write(
+            &root.join("launcher_profiles.json"),
+            &format!(
+                r#"{{ "profiles": {{
+                    "old": {{ "lastUsed": "2024-01-01T00:00:00Z", "lastVersionId": "1.21" }},
+                    "new": {{ "lastUsed": "2024-06-01T00:00:00Z", "lastVersionId": "fabric" }},
+                    "own": {{ "lastUsed": "2024-07-01T00:00:00Z", "lastVersionId": "forge",
+                              "gameDir": "{}" }}
+                }} }}"#,
+                separate.display()
+            ),
+        );
+        write(&root.join("versions/1.21/1.21.json"), r#"{ "id": "1.21" }"#);
+        write_version(&root.join("versions"), "fabric", "net.fabricmc:fabric-loader:0.15.11");
+        write_version(&root.join("versions"), "forge", "net.minecraftforge:forge:1.20.1-47.2.0");
+
+        // Профиль с отдельной папкой игры не относится к общей
+        assert_eq!(from_official(&root, &root), Some(info("1.20.1", Some("fabric"), Some("0.15.11"))));
+        assert_eq!(from_official(&root, &separate), Some(info("1.20.1", Some("forge"), Some("47.2.0"))));
+
+        let instances = Official.instances_in(&root);
+        assert_eq!(instances.len(), 2);
+        assert_eq!(instances[1].game_dir, separate);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn detect_game_falls_back_to_files_next_to_the_folder() {
+        // Папка `versions/` рядом с папкой игры без известного лаунчера
+        let root = temp_root("detect-official");
+        write(&root.join("launcher_profiles.json"), r#"{ "profiles": { "p": { "lastVersionId": "q" } } }"#);
+        write_version(&root.join("versions"), "q", "org.quiltmc:quilt-loader:0.19.0-beta.18");
+        assert_eq!(detect_game(&root), Some(info("1.20.1", Some("quilt"), Some("0.19.0-beta.18"))));
+
+        // `mmc-pack.json` в папке экземпляра над папкой игры
+        let instance = temp_root("detect-mmc");
+        write(
+            &instance.join("mmc-pack.json"),
+            r#"{ "components": [{ "uid": "net.minecraft", "version": "1.20.1" },
+                                 { "uid": "net.fabricmc.fabric-loader", "version": "0.15.11" }] }"#,
+        );
+        assert_eq!(
+            detect_game(&instance.join(".minecraft")),
+            Some(info("1.20.1", Some("fabric"), Some("0.15.11")))
+        );
+
+        // `profile.json` Modrinth App в самой папке игры
+        let profile = temp_root("detect-modrinth");
+        write(&profile.join("profile.json"), r#"{ "metadata": { "game_version": "1.19.2", "loader": "forge" } }"#);
+        assert_eq!(detect_game(&profile), Some(info("1.19.2", Some("forge"), None)));
+
+        for dir in [root, instance, profile] {
+            fs::remove_dir_all(dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn tlauncher_version_folder() {
+        let root = temp_root("tlauncher");
+        write(&root.join("TlauncherProfiles.json"), "{}");
+        write_version(&root.join("versions"), "Forge 1.20.1", "net.minecraftforge:forge:1.20.1-47.2.0");
+        fs::create_dir_all(root.join("versions/Forge 1.20.1/mods")).unwrap();
+        fs::create_dir_all(root.join("versions/1.21")).unwrap();
+
+        let found = TLauncher.instances_in(&root);
+        assert_eq!(found.len(), 1);
+        assert_eq!(TLauncher.game_info(&found[0]), Some(info("1.20.1", Some("forge"), Some("47.2.0"))));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -4,25 +4,64 @@ mod git_ops;
 mod mods;
 mod modrinth;
 mod config;
+mod cli;
+mod manifest;
+mod cache;
+mod plan;
+mod backup;
+mod download;
+mod mrpack;
+mod curseforge;
+mod packwiz;
+mod launchers;
+mod inventory;
+mod duplicates;
+mod metadata;
+#[cfg(test)]
+mod test_server;
 
+use clap::Parser;
 use console::Term;
+use std::process::ExitCode;
 
 /// Точка входа в приложение
-fn main() {
+fn main() -> ExitCode {
+    let cli = cli::Cli::parse();
+
+    // Загружаем конфигурацию
+    let mut config = match config::Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("󰅖 {}", e);
+            return ExitCode::from(cli::EXIT_FAILURE);
+        }
+    };
+
+    // Подкоманда — выполняем без интерактивного меню
+    if let Some(command) = cli.command {
+        return cli::run(command, &mut config);
+    }
+
+    run_menu(&mut config);
+    ExitCode::SUCCESS
+}
+
+/// Интерактивное меню
+fn run_menu(config: &mut config::Config) {
     let term = Term::stdout();
     
     // Выводим баннер при запуске
     ui::print_banner();
     
-    // Загружаем конфигурацию
-    let mut config = config::Config::load();
+    // Пробный запуск: установка и удаление только показывают изменения
+    let mut dry_run = false;
     
     // Основной цикл программы
     loop {
         let _ = term.clear_screen();
         ui::print_banner();
         
-        let choice = ui::main_menu();
+        let choice = ui::main_menu(dry_run);
         
         match choice.as_deref() {
             Some("󰆽 Установить моды") => {
@@ -30,13 +69,10 @@ fn main() {
                 ui::print_banner();
                 
                 // Установка модов
-                let path = match config.get_default_path() {
-                    Some(default_path) => ui::ask_minecraft_folder_with_default(Some(&default_path)),
-                    None => ui::ask_minecraft_folder(),
-                };
+                let path = ui::ask_game_folder(config);
                 
                 if let Some(path) = path {
-                    git_ops::install(&path, false);
+                    git_ops::install(config, &path, false, dry_run);
                 }
             }
 
@@ -45,13 +81,46 @@ fn main() {
                 ui::print_banner();
                 
                 // Переустановка модов (удалить все и установить заново)
-                let path = match config.get_default_path() {
-                    Some(default_path) => ui::ask_minecraft_folder_with_default(Some(&default_path)),
-                    None => ui::ask_minecraft_folder(),
-                };
+                let path = ui::ask_game_folder(config);
+                
+                if let Some(path) = path {
+                    git_ops::install(config, &path, true, dry_run);
+                }
+            }
+
+            Some("󰆴 Удалить моды сборки") => {
+                let _ = term.clear_screen();
+                ui::print_banner();
+                
+                // Удаление модов сборки (моды игрока остаются)
+                let path = ui::ask_game_folder(config);
                 
                 if let Some(path) = path {
-                    git_ops::install(&path, true);
+                    git_ops::clean(config, &path, dry_run);
+                }
+            }
+
+            Some("󰕌 Откатить моды к снимку") => {
+                let _ = term.clear_screen();
+                ui::print_banner();
+                
+                // Восстановление последнего снимка папки модов
+                let path = ui::ask_game_folder(config);
+                
+                if let Some(path) = path {
+                    git_ops::rollback(&path, config.backup_limit);
+                }
+            }
+
+            Some("󰋗 Мои моды") => {
+                let _ = term.clear_screen();
+                ui::print_banner();
+                
+                // Таблица модов экземпляра
+                let path = ui::ask_game_folder(config);
+                
+                if let Some(path) = path {
+                    ui::show_mods(&path);
                 }
             }
 
@@ -60,24 +129,103 @@ fn main() {
                 ui::print_banner();
                 
                 // Загрузка модов с Modrinth
-                let path = match config.get_default_path() {
-                    Some(default_path) => ui::ask_minecraft_folder_with_default(Some(&default_path)),
-                    None => ui::ask_minecraft_folder(),
-                };
+                let path = ui::ask_game_folder(config);
+                
+                if let Some(path) = path {
+                    modrinth::download_mods(&path, config.backup_limit, config.download_concurrency);
+                }
+            }
+
+            Some("󰚰 Проверить обновления модов") => {
+                let _ = term.clear_screen();
+                ui::print_banner();
+                
+                // Проверка обновлений модов на Modrinth
+                let path = ui::ask_game_folder(config);
+                
+                if let Some(path) = path {
+                    modrinth::check_updates(&path, config.backup_limit, config.download_concurrency);
+                }
+            }
+
+            Some("󰏗 Установить сборку .mrpack") => {
+                let _ = term.clear_screen();
+                ui::print_banner();
+                
+                // Установка сборки Modrinth из файла, по ссылке или из поиска
+                let path = ui::ask_game_folder(config);
+                
+                if let Some(path) = path {
+                    mrpack::install_pack(&path, config.download_concurrency, config.backup_limit);
+                }
+            }
+
+            Some("󰏗 Экспортировать в .mrpack") => {
+                let _ = term.clear_screen();
+                ui::print_banner();
+                
+                // Экспорт экземпляра в сборку Modrinth
+                let path = ui::ask_game_folder(config);
                 
                 if let Some(path) = path {
-                    modrinth::download_mods(&path);
+                    mrpack::export_pack(&path);
                 }
             }
 
+            Some("󰏗 Импортировать сборку CurseForge") => {
+                let _ = term.clear_screen();
+                ui::print_banner();
+                
+                // Импорт экспорта CurseForge
+                let path = ui::ask_game_folder(config);
+                
+                if let Some(path) = path {
+                    curseforge::import_pack(
+                        &path,
+                        &config.curseforge,
+                        config.download_concurrency,
+                        config.backup_limit,
+                    );
+                }
+            }
+
+            Some("󰘬 Сменить канал обновлений") => {
+                let _ = term.clear_screen();
+                ui::print_banner();
+                
+                // Переключение канала обновлений сборки
+                let path = ui::ask_game_folder(config);
+                
+                if let Some(path) = path {
+                    git_ops::switch_channel(config, &path, dry_run);
+                }
+            }
+
+            Some("󰒓 Источники сборок") => {
+                // Добавление, изменение и удаление источников сборок
+                ui::manage_sources(config);
+            }
+
+            Some("󰆍 Экземпляры") => {
+                // Сохранённые экземпляры: папки, сборки, версии игры
+                ui::manage_instances(config);
+            }
+
+            Some(toggle) if toggle.starts_with("󰙨 Пробный запуск") => {
+                // Переключение пробного запуска
+                dry_run = !dry_run;
+            }
+
             Some("󰒓 Установить папку по умолчанию") => {
                 let _ = term.clear_screen();
                 ui::print_banner();
                 
                 // Установка папки по умолчанию
                 if let Some(path) = ui::ask_minecraft_folder() {
-                    config.set_default_path(&path);
-                    println!("󰄬 Папка по умолчанию установлена: {}", path.display());
+                    match config.set_default_path(&path) {
+                        Ok(()) => println!("󰄬 Папка по умолчанию установлена: {}", path.display()),
+                        Err(e) => println!("󰅖 Ошибка сохранения конфига: {}", e),
+                    }
                     println!("󰝚 Нажмите Enter чтобы продолжить...");
                     let _ = std::io::stdin().read_line(&mut String::new());
                 }
@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Служебная папка stm внутри экземпляра
+const STM_DIR: &str = ".stm";
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Откуда stm взял файл
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Source {
+    /// Git-репозиторий сборки
+    Pack { url: String },
+    /// Проект на Modrinth
+    Modrinth { project_id: String },
+    /// Сборка в формате Modrinth (.mrpack)
+    Mrpack { name: String },
+    /// Проект на CurseForge
+    CurseForge { project_id: u64 },
+}
+
+/// Файл, установленный stm
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ManagedFile {
+    /// Имя файла в папке mods
+    pub file_name: String,
+    pub source: Source,
+    /// Коммит сборки, id версии на Modrinth, версия .mrpack или id файла CurseForge
+    pub version: String,
+    pub sha256: String,
+}
+
+/// Манифест файлов, которыми управляет stm в одном экземпляре.
+/// Всё, чего нет в манифесте, считается модами игрока и не трогается.
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct Manifest {
+    pub files: Vec<ManagedFile>,
+}
+
+impl Manifest {
+    /// Загрузка манифеста для папки модов. Если манифеста нет — пустой.
+    pub fn load(mods_dir: &Path) -> Self {
+        let path = manifest_path(mods_dir);
+
+        match fs::read_to_string(&path) {
+            Ok(content) => match serde_json::from_str(&content) {
+                Ok(manifest) => manifest,
+                Err(e) => {
+                    eprintln!("󰅖 Ошибка чтения манифеста {}: {}", path.display(), e);
+                    Manifest::default()
+                }
+            },
+            Err(_) => Manifest::default(),
+        }
+    }
+
+    pub fn save(&self, mods_dir: &Path) -> io::Result<()> {
+        let dir = stm_dir(mods_dir);
+        fs::create_dir_all(&dir)?;
+
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(dir.join(MANIFEST_FILE), json)
+    }
+
+    /// Запись файла в манифест (заменяет старую запись с тем же именем)
+    pub fn record(&mut self, file: ManagedFile) {
+        self.remove(&file.file_name);
+        self.files.push(file);
+    }
+
+    pub fn remove(&mut self, file_name: &str) {
+        self.files.retain(|f| f.file_name != file_name);
+    }
+
+    /// Файлы, установленные из репозитория сборки
+    pub fn pack_files(&self) -> impl Iterator<Item = &ManagedFile> {
+        self.files
+            .iter()
+            .filter(|f| matches!(f.source, Source::Pack { .. }))
+    }
+}
+
+/// Служебная папка `.stm` экземпляра, которому принадлежит папка модов
+pub fn stm_dir(mods_dir: &Path) -> PathBuf {
+    mods_dir.parent().unwrap_or(mods_dir).join(STM_DIR)
+}
+
+/// Путь к манифесту экземпляра
+pub fn manifest_path(mods_dir: &Path) -> PathBuf {
+    stm_dir(mods_dir).join(MANIFEST_FILE)
+}
+
+/// SHA-256 файла в виде hex-строки
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    hash_file::<Sha256>(path)
+}
+
+/// SHA-512 файла в виде hex-строки (так файлы ищутся на Modrinth)
+pub fn sha512_file(path: &Path) -> io::Result<String> {
+    hash_file::<Sha512>(path)
+}
+
+/// SHA-1 файла в виде hex-строки (старые файлы на Modrinth публикуются только с ним)
+pub fn sha1_file(path: &Path) -> io::Result<String> {
+    hash_file::<Sha1>(path)
+}
+
+fn hash_file<D: Digest + io::Write>(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = D::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
@@ -0,0 +1,703 @@
+use serde_json::Value;
+use std::fs::File;
+use std::io::{Cursor, Read, Seek};
+use std::path::Path;
+use zip::ZipArchive;
+
+/// Описание мода Fabric
+const FABRIC_FILE: &str = "fabric.mod.json";
+/// Описание мода Quilt
+const QUILT_FILE: &str = "quilt.mod.json";
+/// Описание модов NeoForge 20.5+
+const NEOFORGE_FILE: &str = "META-INF/neoforge.mods.toml";
+/// Описание модов Forge и старого NeoForge
+const FORGE_FILE: &str = "META-INF/mods.toml";
+/// Описание модов Forge до 1.13
+const MCMOD_FILE: &str = "mcmod.info";
+/// Список вложенных jar у Forge и NeoForge
+const JARJAR_FILE: &str = "META-INF/jarjar/metadata.json";
+/// Версия в `mods.toml`, которую Forge берёт из манифеста jar
+const JAR_VERSION_PLACEHOLDER: &str = "${file.jarVersion}";
+/// Глубина вложенности jar-in-jar, дальше которой не заглядываем
+const MAX_NESTING: usize = 4;
+
+/// На какой стороне нужен мод
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Side {
+    Both,
+    Client,
+    Server,
+}
+
+impl Side {
+    pub fn label(self) -> &'static str {
+        match self {
+            Side::Both => "обе стороны",
+            Side::Client => "клиент",
+            Side::Server => "сервер",
+        }
+    }
+}
+
+/// Вид зависимости
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DependencyKind {
+    Required,
+    Optional,
+    /// Мод несовместим с зависимостью
+    Incompatible,
+}
+
+/// Зависимость, объявленная модом
+#[derive(Clone, Debug)]
+pub struct Dependency {
+    pub id: String,
+    /// Диапазон версий в формате лоадера, например `>=0.15` или `[47,)`
+    pub versions: Option<String>,
+    pub kind: DependencyKind,
+}
+
+/// Мод, описанный в метаданных jar
+#[derive(Clone, Debug)]
+pub struct ModMetadata {
+    pub id: String,
+    /// Название для людей; если не указано — id
+    pub name: String,
+    /// `None`, если версия не указана или не подставлена при сборке jar
+    pub version: Option<String>,
+    pub authors: Vec<String>,
+    pub side: Side,
+    /// fabric, quilt, forge или neoforge
+    pub loader: &'static str,
+    pub dependencies: Vec<Dependency>,
+}
+
+/// Содержимое jar: описанные в нём моды и вложенные jar
+#[derive(Clone, Debug, Default)]
+pub struct JarInfo {
+    /// Моды самого jar; в `mods.toml` их может быть несколько
+    pub mods: Vec<ModMetadata>,
+    /// Jar-in-jar: путь внутри архива и его содержимое
+    pub nested: Vec<(String, JarInfo)>,
+}
+
+/// Метаданные модов из jar. Jar без известных файлов описания — не ошибка,
+/// в нём просто нет модов.
+pub fn read_jar(path: &Path) -> Result<JarInfo, String> {
+    let file = File::open(path).map_err(|e| format!("Ошибка открытия {}: {}", path.display(), e))?;
+    let mut archive =
+        ZipArchive::new(file).map_err(|e| format!("{} не является jar: {}", path.display(), e))?;
+    Ok(read_archive(&mut archive, 0))
+}
+
+fn read_archive<R: Read + Seek>(archive: &mut ZipArchive<R>, depth: usize) -> JarInfo {
+    let (mods, nested_paths) = if let Some(json) = read_json(archive, QUILT_FILE) {
+        parse_quilt(&json)
+    } else if let Some(json) = read_json(archive, FABRIC_FILE) {
+        parse_fabric(&json)
+    } else if let Some(table) = read_toml(archive, NEOFORGE_FILE) {
+        (parse_mods_toml(&table, "neoforge", archive), jarjar_paths(archive))
+    } else if let Some(table) = read_toml(archive, FORGE_FILE) {
+        (parse_mods_toml(&table, "forge", archive), jarjar_paths(archive))
+    } else if let Some(json) = read_json(archive, MCMOD_FILE) {
+        (parse_mcmod(&json), Vec::new())
+    } else {
+        (Vec::new(), Vec::new())
+    };
+
+    let mut nested = Vec::new();
+    if depth < MAX_NESTING {
+        for nested_path in nested_paths {
+            let Some(bytes) = read_entry(archive, &nested_path) else {
+                continue;
+            };
+            if let Ok(mut inner) = ZipArchive::new(Cursor::new(bytes)) {
+                nested.push((nested_path, read_archive(&mut inner, depth + 1)));
+            }
+        }
+    }
+
+    JarInfo { mods, nested }
+}
+
+/// `fabric.mod.json`: моды и пути вложенных jar
+fn parse_fabric(json: &Value) -> (Vec<ModMetadata>, Vec<String>) {
+    let Some(id) = json["id"].as_str() else {
+        return (Vec::new(), Vec::new());
+    };
+
+    let mut dependencies = Vec::new();
+    for (key, kind) in [
+        ("depends", DependencyKind::Required),
+        ("recommends", DependencyKind::Optional),
+        ("suggests", DependencyKind::Optional),
+        ("breaks", DependencyKind::Incompatible),
+        ("conflicts", DependencyKind::Incompatible),
+    ] {
+        let Some(map) = json[key].as_object() else {
+            continue;
+        };
+        for (dep_id, versions) in map {
+            dependencies.push(Dependency {
+                id: dep_id.clone(),
+                versions: version_range(versions),
+                kind,
+            });
+        }
+    }
+
+    let metadata = ModMetadata {
+        id: id.to_string(),
+        name: json["name"].as_str().unwrap_or(id).to_string(),
+        version: non_empty(json["version"].as_str()),
+        authors: people(&json["authors"]),
+        side: match json["environment"].as_str() {
+            Some("client") => Side::Client,
+            Some("server") => Side::Server,
+            _ => Side::Both,
+        },
+        loader: "fabric",
+        dependencies,
+    };
+
+    let nested = json["jars"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|jar| jar["file"].as_str().map(String::from))
+        .collect();
+    (vec![metadata], nested)
+}
+
+/// `quilt.mod.json`: моды и пути вложенных jar
+fn parse_quilt(json: &Value) -> (Vec<ModMetadata>, Vec<String>) {
+    let loader = &json["quilt_loader"];
+    let Some(id) = loader["id"].as_str() else {
+        return (Vec::new(), Vec::new());
+    };
+
+    let mut dependencies = Vec::new();
+    for (key, kind) in [("depends", DependencyKind::Required), ("breaks", DependencyKind::Incompatible)] {
+        for dependency in loader[key].as_array().into_iter().flatten() {
+            // Зависимость — строка с id или объект с id, versions и optional
+            let (dep_id, versions, optional) = match dependency {
+                Value::String(dep_id) => (dep_id.clone(), None, false),
+                Value::Object(_) => match dependency["id"].as_str() {
+                    Some(dep_id) => (
+                        dep_id.to_string(),
+                        version_range(&dependency["versions"]),
+                        dependency["optional"].as_bool().unwrap_or(false),
+                    ),
+                    None => continue,
+                },
+                _ => continue,
+            };
+            dependencies.push(Dependency {
+                // Quilt пишет зависимости в виде `group:id`
+                id: dep_id.rsplit(':').next().unwrap_or(&dep_id).to_string(),
+                versions,
+                kind: if optional && kind == DependencyKind::Required {
+                    DependencyKind::Optional
+                } else {
+                    kind
+                },
+            });
+        }
+    }
+
+    let metadata = &loader["metadata"];
+    // Авторы — ключи `contributors` (значения — роли)
+    let authors = metadata["contributors"]
+        .as_object()
+        .map(|contributors| contributors.keys().cloned().collect())
+        .unwrap_or_default();
+
+    let mod_metadata = ModMetadata {
+        id: id.to_string(),
+        name: metadata["name"].as_str().unwrap_or(id).to_string(),
+        version: non_empty(loader["version"].as_str()),
+        authors,
+        side: match json["minecraft"]["environment"].as_str() {
+            Some("client") => Side::Client,
+            Some("dedicated_server") => Side::Server,
+            _ => Side::Both,
+        },
+        loader: "quilt",
+        dependencies,
+    };
+
+    let nested = loader["jars"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|jar| jar.as_str().map(String::from))
+        .collect();
+    (vec![mod_metadata], nested)
+}
+
+/// `mods.toml` / `neoforge.mods.toml`: все `[[mods]]` с их `[[dependencies.<id>]]`
+fn parse_mods_toml<R: Read + Seek>(
+    table: &toml::Table,
+    loader: &'static str,
+    archive: &mut ZipArchive<R>,
+) -> Vec<ModMetadata> {
+    let mods = table.get("mods").and_then(|mods| mods.as_array()).cloned().unwrap_or_default();
+    let dependency_table = table.get("dependencies").and_then(|deps| deps.as_table());
+
+    // Версия `${file.jarVersion}` подставляется из манифеста jar
+    let jar_version = manifest_attribute(archive, "Implementation-Version");
+
+    mods.iter()
+        .filter_map(|entry| {
+            let id = entry.get("modId")?.as_str()?;
+            let str_field = |key: &str| entry.get(key).and_then(|v| v.as_str()).map(String::from);
+
+            // Без `Implementation-Version` подставить нечего: версия неизвестна
+            let version = str_field("version")
+                .and_then(|version| match &jar_version {
+                    _ if !version.contains(JAR_VERSION_PLACEHOLDER) => Some(version),
+                    Some(jar_version) => Some(version.replace(JAR_VERSION_PLACEHOLDER, jar_version)),
+                    None => None,
+                })
+                .filter(|version| !version.is_empty());
+
+            let dependencies: Vec<(Dependency, Option<String>)> = dependency_table
+                .and_then(|deps| deps.get(id))
+                .and_then(|deps| deps.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(forge_dependency)
+                .collect();
+
+            // Своей стороны у мода нет: клиентские моды объявляют зависимость
+            // от Minecraft или лоадера только на клиенте
+            let side = dependencies
+                .iter()
+                .find(|(dep, _)| matches!(dep.id.as_str(), "minecraft" | "forge" | "neoforge"))
+                .and_then(|(_, side)| match side.as_deref() {
+                    Some("CLIENT") => Some(Side::Client),
+                    Some("SERVER") => Some(Side::Server),
+                    _ => None,
+                })
+                .unwrap_or(Side::Both);
+
+            // Старый NeoForge использует `mods.toml` и зависит от `neoforge`
+            let loader = if dependencies.iter().any(|(dep, _)| dep.id == "neoforge") {
+                "neoforge"
+            } else {
+                loader
+            };
+
+            Some(ModMetadata {
+                id: id.to_string(),
+                name: str_field("displayName").unwrap_or_else(|| id.to_string()),
+                version,
+                authors: str_field("authors")
+                    .or_else(|| table.get("authors").and_then(|v| v.as_str()).map(String::from))
+                    .map(|authors| split_authors(&authors))
+                    .unwrap_or_default(),
+                side,
+                loader,
+                dependencies: dependencies.into_iter().map(|(dep, _)| dep).collect(),
+            })
+        })
+        .collect()
+}
+
+/// `[[dependencies.<id>]]`: зависимость и сторона, на которой она нужна
+fn forge_dependency(entry: &toml::Value) -> Option<(Dependency, Option<String>)> {
+    let id = entry.get("modId")?.as_str()?.to_string();
+    // Forge пишет `mandatory`, NeoForge — `type`
+    let kind = match entry.get("type").and_then(|v| v.as_str()) {
+        Some(kind) => match kind.to_ascii_lowercase().as_str() {
+            "required" => DependencyKind::Required,
+            "incompatible" | "discouraged" => DependencyKind::Incompatible,
+            _ => DependencyKind::Optional,
+        },
+        None => match entry.get("mandatory").and_then(|v| v.as_bool()) {
+            Some(false) => DependencyKind::Optional,
+            _ => DependencyKind::Required,
+        },
+    };
+    let versions = entry
+        .get("versionRange")
+        .and_then(|v| v.as_str())
+        .filter(|range| !range.is_empty())
+        .map(String::from);
+    let side = entry.get("side").and_then(|v| v.as_str()).map(String::from);
+    Some((Dependency { id, versions, kind }, side))
+}
+
+/// `mcmod.info`: массив модов или `{ "modList": [...] }`
+fn parse_mcmod(json: &Value) -> Vec<ModMetadata> {
+    let list = json
+        .as_array()
+        .or_else(|| json["modList"].as_array())
+        .cloned()
+        .unwrap_or_default();
+
+    list.iter()
+        .filter_map(|entry| {
+            let id = entry["modid"].as_str()?;
+            let authors = if entry["authorList"].is_array() {
+                people(&entry["authorList"])
+            } else {
+                people(&entry["authors"])
+            };
+            let dependencies = entry["requiredMods"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|dep| dep.as_str())
+                .map(|dep| {
+                    // Формат `id@[1.0,)`
+                    let (dep_id, versions) = match dep.split_once('@') {
+                        Some((dep_id, versions)) => (dep_id, Some(versions.to_string())),
+                        None => (dep, None),
+                    };
+                    Dependency { id: dep_id.to_string(), versions, kind: DependencyKind::Required }
+                })
+                .collect();
+
+            Some(ModMetadata {
+                id: id.to_string(),
+                name: entry["name"].as_str().unwrap_or(id).to_string(),
+                version: non_empty(entry["version"].as_str()),
+                authors,
+                side: Side::Both,
+                loader: "forge",
+                dependencies,
+            })
+        })
+        .collect()
+}
+
+/// Пути вложенных jar из `META-INF/jarjar/metadata.json`
+fn jarjar_paths<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Vec<String> {
+    read_json(archive, JARJAR_FILE)
+        .and_then(|json| json["jars"].as_array().cloned())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|jar| jar["path"].as_str().map(String::from))
+        .collect()
+}
+
+/// Диапазон версий: строка или массив строк (любой из них)
+fn version_range(value: &Value) -> Option<String> {
+    match value {
+        Value::String(range) if range != "*" => Some(range.clone()),
+        Value::Array(ranges) => {
+            let ranges: Vec<&str> = ranges.iter().filter_map(|r| r.as_str()).collect();
+            (!ranges.is_empty()).then(|| ranges.join(" || "))
+        }
+        _ => None,
+    }
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value.filter(|value| !value.is_empty()).map(String::from)
+}
+
+/// Список людей: строки или объекты с `name`
+fn people(value: &Value) -> Vec<String> {
+    value
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|person| person.as_str().or_else(|| person["name"].as_str()))
+        .map(String::from)
+        .collect()
+}
+
+/// `"A, B and C"` → `["A", "B", "C"]`
+fn split_authors(authors: &str) -> Vec<String> {
+    authors
+        .split([',', '&'])
+        .flat_map(|part| part.split(" and "))
+        .map(str::trim)
+        .filter(|author| !author.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Атрибут из `META-INF/MANIFEST.MF`
+fn manifest_attribute<R: Read + Seek>(archive: &mut ZipArchive<R>, key: &str) -> Option<String> {
+    let manifest = read_text(archive, "META-INF/MANIFEST.MF")?;
+    manifest.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        (name.trim() == key).then(|| value.trim().to_string())
+    })
+}
+
+fn read_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Option<Vec<u8>> {
+    let mut entry = archive.by_name(name).ok()?;
+    let mut bytes = Vec::new();
+    entry.read_to_end(&mut bytes).ok()?;
+    Some(bytes)
+}
+
+fn read_text<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Option<String> {
+    let bytes = read_entry(archive, name)?;
+    // Некоторые моды сохраняют описание с BOM
+    let text = String::from_utf8_lossy(&bytes);
+    Some(text.trim_start_matches('\u{feff}').to_string())
+}
+
+fn read_json<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Option<Value> {
+    let text = read_text(archive, name)?;
+    serde_json::from_str(&text).ok().or_else(|| {
+        // Fabric терпит переводы строк внутри строк JSON, serde_json — нет
+        let relaxed: String = text
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect();
+        serde_json::from_str(&relaxed).ok()
+    })
+}
+
+fn read_toml<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str) -> Option<toml::Table> {
+    read_text(archive, name)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    /// Jar в памяти из пар (путь, содержимое)
+    fn jar(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in entries {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    }
+
+    fn read(bytes: Vec<u8>) -> JarInfo {
+        read_archive(&mut ZipArchive::new(Cursor::new(bytes)).unwrap(), 0)
+    }
+
+    fn dependency<'a>(metadata: &'a ModMetadata, id: &str) -> &'a Dependency {
+        metadata.dependencies.iter().find(|d| d.id == id).unwrap()
+    }
+
+    #[test]
+    fn fabric_mod_with_nested_jar() {
+        let inner = jar(&[(FABRIC_FILE, br#"{ "id": "inner", "version": "0.1" }"#)]);
+        let info = read(jar(&[
+            (
+                FABRIC_FILE,
+                br#"{
+                    "id": "sodium",
+                    "name": "Sodium",
+                    "version": "0.5.8",
+                    "environment": "client",
+                    "authors": ["JellySquid", { "name": "IMS" }],
+                    "depends": { "fabricloader": ">=0.15", "minecraft": ["1.20.1", "1.20.2"] },
+                    "suggests": { "iris": "*" },
+                    "breaks": { "optifine": "*" },
+                    "jars": [{ "file": "META-INF/jars/inner.jar" }]
+                }"#,
+            ),
+            ("META-INF/jars/inner.jar", &inner),
+        ]));
+
+        let sodium = &info.mods[0];
+        assert_eq!(sodium.id, "sodium");
+        assert_eq!(sodium.name, "Sodium");
+        assert_eq!(sodium.version.as_deref(), Some("0.5.8"));
+        assert_eq!(sodium.side, Side::Client);
+        assert_eq!(sodium.loader, "fabric");
+        assert_eq!(sodium.authors, ["JellySquid", "IMS"]);
+
+        let loader = dependency(sodium, "fabricloader");
+        assert_eq!((loader.kind, loader.versions.as_deref()), (DependencyKind::Required, Some(">=0.15")));
+        assert_eq!(dependency(sodium, "minecraft").versions.as_deref(), Some("1.20.1 || 1.20.2"));
+        let iris = dependency(sodium, "iris");
+        assert_eq!((iris.kind, iris.versions.as_deref()), (DependencyKind::Optional, None));
+        assert_eq!(dependency(sodium, "optifine").kind, DependencyKind::Incompatible);
+
+        assert_eq!(info.nested.len(), 1);
+        assert_eq!(info.nested[0].0, "META-INF/jars/inner.jar");
+        assert_eq!(info.nested[0].1.mods[0].id, "inner");
+    }
+
+    #[test]
+    fn quilt_mod() {
+        let inner = jar(&[(QUILT_FILE, br#"{ "quilt_loader": { "id": "lib", "version": "2.0" } }"#)]);
+        let info = read(jar(&[
+            (
+                QUILT_FILE,
+                br#"{
+                    "quilt_loader": {
+                        "id": "example",
+                        "version": "1.2.0",
+                        "metadata": { "name": "Example", "contributors": { "Alice": "Owner" } },
+                        "depends": [
+                            "quilt_loader",
+                            { "id": "org.quiltmc:qsl", "versions": ">=6.0", "optional": true }
+                        ],
+                        "breaks": [{ "id": "other" }],
+                        "jars": ["META-INF/jars/lib.jar"]
+                    },
+                    "minecraft": { "environment": "dedicated_server" }
+                }"#,
+            ),
+            ("META-INF/jars/lib.jar", &inner),
+        ]));
+
+        let example = &info.mods[0];
+        assert_eq!(example.id, "example");
+        assert_eq!(example.name, "Example");
+        assert_eq!(example.version.as_deref(), Some("1.2.0"));
+        assert_eq!(example.side, Side::Server);
+        assert_eq!(example.loader, "quilt");
+        assert_eq!(example.authors, ["Alice"]);
+
+        assert_eq!(dependency(example, "quilt_loader").kind, DependencyKind::Required);
+        let qsl = dependency(example, "qsl");
+        assert_eq!((qsl.kind, qsl.versions.as_deref()), (DependencyKind::Optional, Some(">=6.0")));
+        assert_eq!(dependency(example, "other").kind, DependencyKind::Incompatible);
+
+        assert_eq!(info.nested[0].1.mods[0].id, "lib");
+    }
+
+    const FORGE_TOML: &[u8] = br#"
+        authors = "A, B and C"
+
+        [[mods]]
+        modId = "create"
+        displayName = "Create"
+        version = "${file.jarVersion}"
+
+        [[mods]]
+        modId = "ponder"
+        version = "1.0"
+
+        [[dependencies.create]]
+        modId = "forge"
+        mandatory = true
+        versionRange = "[47,)"
+        side = "BOTH"
+
+        [[dependencies.create]]
+        modId = "jei"
+        mandatory = false
+        versionRange = ""
+
+        [[dependencies.ponder]]
+        modId = "minecraft"
+        mandatory = true
+        side = "CLIENT"
+    "#;
+
+    #[test]
+    fn forge_mods_toml_with_jar_version_and_jarjar() {
+        let inner = jar(&[(MCMOD_FILE, br#"[{ "modid": "flywheel", "version": "0.6" }]"#)]);
+        let info = read(jar(&[
+            (FORGE_FILE, FORGE_TOML),
+            ("META-INF/MANIFEST.MF", b"Manifest-Version: 1.0\r\nImplementation-Version: 0.5.1.f\r\n"),
+            (JARJAR_FILE, br#"{ "jars": [{ "path": "META-INF/jarjar/flywheel.jar" }] }"#),
+            ("META-INF/jarjar/flywheel.jar", &inner),
+        ]));
+
+        assert_eq!(info.mods.len(), 2);
+        let create = &info.mods[0];
+        assert_eq!(create.id, "create");
+        assert_eq!(create.name, "Create");
+        assert_eq!(create.version.as_deref(), Some("0.5.1.f"));
+        assert_eq!(create.loader, "forge");
+        assert_eq!(create.side, Side::Both);
+        assert_eq!(create.authors, ["A", "B", "C"]);
+        let forge = dependency(create, "forge");
+        assert_eq!((forge.kind, forge.versions.as_deref()), (DependencyKind::Required, Some("[47,)")));
+        let jei = dependency(create, "jei");
+        assert_eq!((jei.kind, jei.versions.as_deref()), (DependencyKind::Optional, None));
+
+        let ponder = &info.mods[1];
+        assert_eq!(ponder.name, "ponder");
+        assert_eq!(ponder.side, Side::Client);
+
+        assert_eq!(info.nested[0].0, "META-INF/jarjar/flywheel.jar");
+        assert_eq!(info.nested[0].1.mods[0].id, "flywheel");
+    }
+
+    #[test]
+    fn unresolved_jar_version_is_unknown() {
+        let info = read(jar(&[(FORGE_FILE, FORGE_TOML)]));
+        assert_eq!(info.mods[0].version, None);
+        assert_eq!(info.mods[1].version.as_deref(), Some("1.0"));
+    }
+
+    #[test]
+    fn neoforge_mods_toml() {
+        let info = read(jar(&[(
+            NEOFORGE_FILE,
+            br#"
+            [[mods]]
+            modId = "example"
+            version = "3.1"
+
+            [[dependencies.example]]
+            modId = "neoforge"
+            type = "required"
+            versionRange = "[20.5,)"
+            side = "SERVER"
+
+            [[dependencies.example]]
+            modId = "optifine"
+            type = "incompatible"
+            "#,
+        )]));
+
+        let example = &info.mods[0];
+        assert_eq!(example.loader, "neoforge");
+        assert_eq!(example.side, Side::Server);
+        assert_eq!(dependency(example, "neoforge").kind, DependencyKind::Required);
+        assert_eq!(dependency(example, "optifine").kind, DependencyKind::Incompatible);
+    }
+
+    #[test]
+    fn old_neoforge_uses_mods_toml() {
+        let info = read(jar(&[(
+            FORGE_FILE,
+            br#"
+            [[mods]]
+            modId = "example"
+            version = "1.0"
+
+            [[dependencies.example]]
+            modId = "neoforge"
+            mandatory = true
+            "#,
+        )]));
+        assert_eq!(info.mods[0].loader, "neoforge");
+    }
+
+    #[test]
+    fn mcmod_info_mod_list() {
+        let info = read(jar(&[(
+            MCMOD_FILE,
+            "\u{feff}{ \"modList\": [{ \"modid\": \"journeymap\", \"name\": \"JourneyMap\", \"version\": \"5.7\", \"authorList\": [\"techbrew\"], \"requiredMods\": [\"forge@[14.23,)\", \"baubles\"] }] }"
+                .as_bytes(),
+        )]));
+
+        let journeymap = &info.mods[0];
+        assert_eq!(journeymap.id, "journeymap");
+        assert_eq!(journeymap.version.as_deref(), Some("5.7"));
+        assert_eq!(journeymap.loader, "forge");
+        assert_eq!(journeymap.authors, ["techbrew"]);
+        assert_eq!(dependency(journeymap, "forge").versions.as_deref(), Some("[14.23,)"));
+        assert_eq!(dependency(journeymap, "baubles").versions, None);
+    }
+
+    #[test]
+    fn jar_without_metadata_has_no_mods() {
+        let info = read(jar(&[("assets/readme.txt", b"hello")]));
+        assert!(info.mods.is_empty());
+        assert!(info.nested.is_empty());
+    }
+}
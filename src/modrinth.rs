@@ -1,17 +1,70 @@
-use reqwest::blocking::Client;
-use serde_json::Value;
-use std::fs::{self, File};
-use std::io::{Write, Read};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs;
 use std::path::Path;
-use std::time::Duration;
-use indicatif::{ProgressBar, ProgressStyle};
-use inquire::{Select, Text};
+use indicatif::MultiProgress;
+use inquire::{Confirm, MultiSelect, Select, Text};
 use console::Term;
 
+use crate::backup;
+use crate::download::{self, Download, DownloadQueue, FileHashes};
+use crate::launchers::{self, GameInfo};
+use crate::manifest::{self, ManagedFile, Manifest, Source};
+use crate::ui;
+
 const MODRINTH_API: &str = "https://api.modrinth.com/v2";
 
+/// Результат поиска: (название, описание, id проекта)
+type SearchHit = (String, String, String);
+
+/// Версия мода на Modrinth
+#[derive(Clone)]
+struct ModVersion {
+    /// id версии
+    id: String,
+    /// id проекта, которому принадлежит версия
+    project_id: String,
+    name: String,
+    filename: String,
+    url: String,
+    /// Опубликованные хеши файла
+    hashes: FileHashes,
+    dependencies: Vec<Dependency>,
+    /// Время публикации (Unix-время), по нему версии сортируются от новых к старым
+    published: i64,
+}
+
+
+/// Тип зависимости версии мода
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DependencyKind {
+    Required,
+    Optional,
+    Incompatible,
+    /// Уже встроена в jar мода
+    Embedded,
+}
+
+/// Зависимость версии мода: проект и, возможно, конкретная версия
+#[derive(Clone)]
+struct Dependency {
+    project_id: Option<String>,
+    version_id: Option<String>,
+    kind: DependencyKind,
+}
+
+/// Что нужно установить вместе с модом
+struct Resolution {
+    /// Мод и его обязательные зависимости, которых ещё нет в папке модов
+    to_install: Vec<ModVersion>,
+    /// Необязательные зависимости, которые можно установить по желанию: id проекта
+    optional: Vec<String>,
+    /// Установленные моды, с которыми устанавливаемые несовместимы: (id проекта, файл)
+    conflicts: Vec<(String, String)>,
+}
+
 /// Основная функция загрузки модов с Modrinth
-pub fn download_mods(minecraft_path: &Path) {
+pub fn download_mods(folder: &ui::GameFolder, backup_limit: usize, concurrency: usize) {
     let term = Term::stdout();
     let _ = term.clear_screen();
     
@@ -37,7 +90,7 @@ pub fn download_mods(minecraft_path: &Path) {
     // Ищем моды
     println!("󰇚 Ищем моды по запросу '{}'...", query);
     
-    let mods = match search_mods(&query) {
+    let mods = match search_projects(&query, "mod") {
         Ok(mods) if !mods.is_empty() => mods,
         Ok(_) => {
             println!("󰅖 Моды не найдены");
@@ -70,25 +123,10 @@ pub fn download_mods(minecraft_path: &Path) {
         .find(|(title, desc, _)| format!("{} - {}", title, desc) == selection)
         .unwrap();
     
-    // Запрашиваем версию Minecraft
-    let version = match Text::new("󰝚 Введите версию Minecraft (например: 1.20.1):")
-        .with_default("1.20.1")
-        .prompt()
-    {
-        Ok(v) => v,
-        Err(_) => {
-            println!("󰅖 Отменено");
-            return;
-        }
-    };
-    
-    // Запрашиваем лоадер
-    let loader_options = vec!["fabric", "forge", "quilt", "neoforge"];
-    let loader = match Select::new("󰝚 Выберите лоадер:", loader_options)
-        .prompt()
-    {
-        Ok(loader) => loader,
-        Err(_) => {
+    // Версия Minecraft и лоадер: из файлов лаунчера или у пользователя
+    let (version, loader) = match game_version_and_loader(folder.game_dir()) {
+        Some(answer) => answer,
+        None => {
             println!("󰅖 Отменено");
             return;
         }
@@ -110,7 +148,7 @@ pub fn download_mods(minecraft_path: &Path) {
     
     // Выбираем версию для загрузки
     let version_options: Vec<String> = versions.iter()
-        .map(|(name, filename, _)| format!("{} ({})", name, filename))
+        .map(|v| format!("{} ({})", v.name, v.filename))
         .collect();
     
     let selected_version = match Select::new("󰝚 Выберите версию для загрузки:", version_options)
@@ -123,46 +161,540 @@ pub fn download_mods(minecraft_path: &Path) {
         }
     };
     
-    // Находим URL для скачивания
-    let (_, _, download_url) = versions.iter()
-        .find(|(name, filename, _)| format!("{} ({})", name, filename) == selected_version)
+    // Находим выбранную версию
+    let mod_version = versions.iter()
+        .find(|v| format!("{} ({})", v.name, v.filename) == selected_version)
         .unwrap();
     
     // Определяем папку для загрузки
-    let mods_path = minecraft_path.join("mods");
+    let mods_path = folder.default_mods_path();
     if !mods_path.exists() {
         if let Err(e) = fs::create_dir_all(&mods_path) {
             println!("󰅖 Ошибка создания папки mods: {}", e);
             return;
         }
     }
+
+    // Собираем зависимости до скачивания
+    println!("󰇚 Проверяю зависимости...");
+    let to_install = match resolve_interactive(&mods_path, mod_version.clone(), &version, loader) {
+        Ok(Some(to_install)) => to_install,
+        Ok(None) => {
+            println!("󰅖 Отменено");
+            return;
+        }
+        Err(e) => {
+            println!("󰅖 Ошибка проверки зависимостей: {}", e);
+            println!("󰝚 Нажмите Enter чтобы продолжить...");
+            let _ = std::io::stdin().read_line(&mut String::new());
+            return;
+        }
+    };
+
+    // Скачиваем мод и зависимости
+    println!("󰇚 Скачиваю моды...");
+    let versions: Vec<&ModVersion> = to_install.iter().collect();
+    for (mod_version, result) in to_install.iter().zip(download_versions(&mods_path, &versions, concurrency)) {
+        match result {
+            Ok(filename) => println!("󰄬 Успешно скачан: {}", filename),
+            Err(e) => println!("󰅖 Ошибка скачивания {}: {}", mod_version.filename, e),
+        }
+    }
+    ui::check_duplicates(&mods_path, backup_limit);
+
+    println!("󰝚 Нажмите Enter чтобы продолжить...");
+    let _ = std::io::stdin().read_line(&mut String::new());
+}
+
+/// Разрешение зависимостей с вопросами пользователю: показывает, что будет
+/// установлено, предупреждает о несовместимых модах и предлагает необязательные
+/// зависимости. Возвращает версии для скачивания или `None`, если пользователь отказался.
+fn resolve_interactive(
+    mods_path: &Path,
+    root: ModVersion,
+    minecraft_version: &str,
+    loader: &str,
+) -> Result<Option<Vec<ModVersion>>, Box<dyn std::error::Error>> {
+    let installed = installed_projects(mods_path)?;
+    let mut resolution = resolve_dependencies(root, minecraft_version, loader, &installed)?;
+
+    let mut ids: Vec<String> = resolution.optional.clone();
+    ids.extend(resolution.conflicts.iter().map(|(id, _)| id.clone()));
+    ids.extend(resolution.to_install.iter().skip(1).map(|v| v.project_id.clone()));
+    let titles = project_titles(&ids);
+    let title = |id: &String| titles.get(id).cloned().unwrap_or_else(|| id.clone());
+
+    if resolution.to_install.len() > 1 {
+        println!("\n󰏗 Обязательные зависимости:");
+        for dependency in resolution.to_install.iter().skip(1) {
+            println!("  + {} ({})", title(&dependency.project_id), dependency.filename);
+        }
+    }
+
+    if !resolution.conflicts.is_empty() {
+        println!("\n󰀦 Несовместимые моды уже установлены:");
+        for (project_id, file_name) in &resolution.conflicts {
+            println!("  ! {} ({})", title(project_id), file_name);
+        }
+        let proceed = Confirm::new("Всё равно установить?")
+            .with_default(false)
+            .prompt()
+            .unwrap_or(false);
+        if !proceed {
+            return Ok(None);
+        }
+    }
+
+    if !resolution.optional.is_empty() {
+        let options: Vec<String> = resolution.optional.iter().map(title).collect();
+        let chosen = MultiSelect::new("󰝚 Необязательные зависимости:", options.clone())
+            .with_help_message("Пробел — выбрать, Enter — продолжить")
+            .prompt()
+            .unwrap_or_default();
+
+        let mut installed = installed;
+        for version in &resolution.to_install {
+            installed.insert(version.project_id.clone(), version.filename.clone());
+        }
+
+        for (project_id, option) in resolution.optional.iter().zip(&options) {
+            if !chosen.contains(option) || installed.contains_key(project_id) {
+                continue;
+            }
+            let version = pick_dependency_version(project_id, None, minecraft_version, loader)?;
+            let extra = resolve_dependencies(version, minecraft_version, loader, &installed)?;
+            for version in extra.to_install {
+                println!("  + {} ({})", title(&version.project_id), version.filename);
+                installed.insert(version.project_id.clone(), version.filename.clone());
+                resolution.to_install.push(version);
+            }
+        }
+    }
+
+    Ok(Some(resolution.to_install))
+}
+
+/// Файл сборки (.mrpack) на Modrinth
+pub struct ModpackFile {
+    pub url: String,
+    pub hashes: FileHashes,
+}
+
+/// Поиск сборки на Modrinth и выбор её версии
+pub fn choose_modpack() -> Option<ModpackFile> {
+    let query = match Text::new("󰝚 Введите название сборки для поиска:").prompt() {
+        Ok(query) if !query.trim().is_empty() => query.trim().to_string(),
+        _ => return None,
+    };
+
+    println!("󰇚 Ищем сборки по запросу '{}'...", query);
+    let packs = match search_projects(&query, "modpack") {
+        Ok(packs) if !packs.is_empty() => packs,
+        Ok(_) => {
+            println!("󰅖 Сборки не найдены");
+            return None;
+        }
+        Err(e) => {
+            println!("󰅖 Ошибка поиска: {}", e);
+            return None;
+        }
+    };
+
+    let options: Vec<String> = packs.iter()
+        .map(|(title, desc, _)| format!("{} - {}", title, desc))
+        .collect();
+    let selected = Select::new("󰝚 Выберите сборку:", options)
+        .with_page_size(10)
+        .raw_prompt()
+        .ok()?;
+    let (_, _, project_id) = &packs[selected.index];
+
+    println!("󰇚 Получаю информацию о версиях...");
+    let versions = match get_all_versions(project_id) {
+        Ok(versions) if !versions.is_empty() => versions,
+        Ok(_) => {
+            println!("󰅖 У сборки нет версий");
+            return None;
+        }
+        Err(e) => {
+            println!("󰅖 Ошибка: {}", e);
+            return None;
+        }
+    };
+
+    let options: Vec<String> = versions.iter()
+        .map(|v| format!("{} ({})", v.name, v.filename))
+        .collect();
+    let selected = Select::new("󰝚 Выберите версию сборки:", options)
+        .raw_prompt()
+        .ok()?;
+    let version = &versions[selected.index];
+
+    Some(ModpackFile {
+        url: version.url.clone(),
+        hashes: version.hashes.clone(),
+    })
+}
+
+/// Лоадеры, для которых ищутся моды
+pub const LOADERS: [&str; 4] = ["fabric", "forge", "quilt", "neoforge"];
+
+/// Версия Minecraft и лоадер папки игры. Берутся из файлов лаунчера,
+/// а спрашиваются только те, что определить не удалось.
+pub fn game_version_and_loader(game_dir: &Path) -> Option<(String, &'static str)> {
+    let detected = launchers::detect_game(game_dir);
+    let known_loader = detected
+        .as_ref()
+        .and_then(|info| info.loader.as_deref())
+        .and_then(|loader| LOADERS.iter().copied().find(|known| *known == loader));
+
+    if let (Some(info), Some(loader)) = (&detected, known_loader) {
+        println!("󰄬 Minecraft {}, {} (из файлов лаунчера)", info.game_version, loader);
+        return Some((info.game_version.clone(), loader));
+    }
+    ask_game_version_and_loader(detected.as_ref())
+}
+
+/// Запрос версии Minecraft и лоадера; найденная версия игры подставляется по умолчанию
+fn ask_game_version_and_loader(detected: Option<&GameInfo>) -> Option<(String, &'static str)> {
+    let default = detected.map_or("1.20.1", |info| info.game_version.as_str());
+    let version = Text::new("󰝚 Введите версию Minecraft (например: 1.20.1):")
+        .with_default(default)
+        .prompt()
+        .ok()?;
     
-    // Скачиваем мод
-    println!("󰇚 Скачиваю мод...");
-    match download_file(download_url, &mods_path) {
-        Ok(filename) => {
-            println!("󰄬 Успешно скачан: {}", filename);
+    let loader = Select::new("󰝚 Выберите лоадер:", LOADERS.to_vec())
+        .prompt()
+        .ok()?;
+
+    Some((version, loader))
+}
+
+/// Доступное обновление мода из папки модов
+pub struct ModUpdate {
+    /// Текущий файл в папке модов
+    pub file_name: String,
+    /// Название установленной версии
+    pub current_version: String,
+    new_version: ModVersion,
+    pub changelog: String,
+}
+
+impl ModUpdate {
+    /// Название новой версии
+    pub fn new_version_name(&self) -> &str {
+        &self.new_version.name
+    }
+}
+
+/// Проверка обновлений модов на Modrinth с выбором, что обновить
+pub fn check_updates(folder: &ui::GameFolder, backup_limit: usize, concurrency: usize) {
+    let term = Term::stdout();
+    let _ = term.clear_screen();
+    
+    println!("󰚰 Проверка обновлений модов");
+    println!("=============================\n");
+    
+    let (version, loader) = match game_version_and_loader(folder.game_dir()) {
+        Some(answer) => answer,
+        None => {
+            println!("󰅖 Отменено");
+            return;
+        }
+    };
+
+    let mods_path = folder.default_mods_path();
+    println!("󰇚 Ищу обновления...");
+    let updates = match find_updates(&mods_path, &version, loader) {
+        Ok(updates) if !updates.is_empty() => updates,
+        Ok(_) => {
+            println!("󰄬 Все моды с Modrinth в актуальном состоянии");
             println!("󰝚 Нажмите Enter чтобы продолжить...");
             let _ = std::io::stdin().read_line(&mut String::new());
+            return;
         }
         Err(e) => {
-            println!("󰅖 Ошибка скачивания: {}", e);
+            println!("󰅖 Ошибка проверки обновлений: {}", e);
+            println!("󰝚 Нажмите Enter чтобы продолжить...");
+            let _ = std::io::stdin().read_line(&mut String::new());
+            return;
+        }
+    };
+
+    // Показываем список изменений каждого обновления
+    for update in &updates {
+        println!("󰏗 {}: {} → {}", update.file_name, update.current_version, update.new_version.name);
+        for line in update.changelog.lines().filter(|l| !l.trim().is_empty()).take(8) {
+            println!("    {}", line);
+        }
+        println!();
+    }
+
+    let options: Vec<String> = updates.iter()
+        .map(|u| format!("{}: {} → {}", u.file_name, u.current_version, u.new_version.name))
+        .collect();
+    let all: Vec<usize> = (0..options.len()).collect();
+
+    let selected = match MultiSelect::new("󰝚 Выберите моды для обновления:", options)
+        .with_default(&all)
+        .with_page_size(15)
+        .raw_prompt()
+    {
+        Ok(selected) => selected,
+        Err(_) => {
+            println!("󰅖 Отменено");
+            return;
+        }
+    };
+
+    let selected: Vec<&ModUpdate> = selected.iter().map(|option| &updates[option.index]).collect();
+    if !selected.is_empty() {
+        if let Err(e) = backup::snapshot(&mods_path, backup_limit) {
+            println!("󰅖 Не удалось сохранить снимок, обновление отменено: {}", e);
             println!("󰝚 Нажмите Enter чтобы продолжить...");
             let _ = std::io::stdin().read_line(&mut String::new());
+            return;
+        }
+    }
+
+    for (update, result) in selected.iter().zip(apply_updates(&mods_path, &selected, concurrency)) {
+        match result {
+            Ok(filename) => println!("󰄬 Обновлён: {}", filename),
+            Err(e) => println!("󰅖 Ошибка обновления {}: {}", update.file_name, e),
         }
     }
+    if !selected.is_empty() {
+        ui::check_duplicates(&mods_path, backup_limit);
+    }
+    println!("󰝚 Нажмите Enter чтобы продолжить...");
+    let _ = std::io::stdin().read_line(&mut String::new());
 }
 
-/// Поиск модов на Modrinth
-fn search_mods(query: &str) -> Result<Vec<(String, String, String)>, Box<dyn std::error::Error>> {
-    let client = Client::new();
-    let url = format!("{}/search?query={}&limit=20", MODRINTH_API, query);
-    
-    let response = client.get(&url)
-        .timeout(Duration::from_secs(10))
-        .send()?;
-    
-    let json: Value = response.json()?;
+/// Поиск обновлений для модов из папки модов по SHA-512 их файлов.
+/// Моды сборки пропускаются: их обновляет сама сборка.
+pub fn find_updates(
+    mods_path: &Path,
+    minecraft_version: &str,
+    loader: &str,
+) -> Result<Vec<ModUpdate>, Box<dyn std::error::Error>> {
+    let hashes = hash_mods_dir(mods_path, true)?;
+    if hashes.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let hash_list: Vec<&String> = hashes.keys().collect();
+
+    // Какие версии установлены сейчас
+    let current = lookup_versions(hashes.keys())?;
+
+    // Самые свежие версии для нашей версии Minecraft и лоадера
+    let latest = download::post_json(
+        &format!("{}/version_files/update", MODRINTH_API),
+        &json!({
+            "hashes": hash_list,
+            "algorithm": "sha512",
+            "loaders": [loader],
+            "game_versions": [minecraft_version],
+        }),
+    )?;
+
+    let mut updates = Vec::new();
+    if let Value::Object(latest) = latest {
+        for (hash, version) in latest {
+            let installed = &current[&hash];
+            if installed["id"] == version["id"] {
+                continue;
+            }
+
+            let new_version = match parse_version(&version) {
+                Some(new_version) => new_version,
+                None => continue,
+            };
+
+            updates.push(ModUpdate {
+                file_name: hashes[&hash].clone(),
+                current_version: installed["name"].as_str().unwrap_or("?").to_string(),
+                new_version,
+                changelog: version["changelog"].as_str().unwrap_or("").to_string(),
+            });
+        }
+    }
+
+    updates.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    Ok(updates)
+}
+
+/// SHA-512 .jar файлов папки модов: хеш -> имя файла.
+/// При `skip_pack` моды сборки пропускаются.
+fn hash_mods_dir(mods_path: &Path, skip_pack: bool) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    let manifest = Manifest::load(mods_path);
+    let pack_files: Vec<&str> = manifest.pack_files().map(|f| f.file_name.as_str()).collect();
+
+    let mut hashes = HashMap::new();
+    if !mods_path.exists() {
+        return Ok(hashes);
+    }
+
+    for entry in fs::read_dir(mods_path)? {
+        let path = entry?.path();
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+
+        if !path.is_file() || !file_name.ends_with(".jar") {
+            continue;
+        }
+        if skip_pack && pack_files.contains(&file_name.as_str()) {
+            continue;
+        }
+
+        hashes.insert(manifest::sha512_file(&path)?, file_name);
+    }
+
+    Ok(hashes)
+}
+
+/// Поиск версий на Modrinth по SHA-512 файлов: хеш -> версия
+fn lookup_versions<'a>(
+    hashes: impl IntoIterator<Item = &'a String>,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    let hash_list: Vec<&String> = hashes.into_iter().collect();
+
+    let url = format!("{}/version_files", MODRINTH_API);
+    Ok(download::post_json(&url, &json!({ "hashes": hash_list, "algorithm": "sha512" }))?)
+}
+
+/// Ссылки на скачивание файлов с Modrinth по их SHA-512: хеш -> ссылка.
+/// Файлов, которых на Modrinth нет, в ответе нет.
+pub fn download_urls(sha512s: &[String]) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    let mut urls = HashMap::new();
+    if sha512s.is_empty() {
+        return Ok(urls);
+    }
+
+    if let Value::Object(versions) = lookup_versions(sha512s)? {
+        for (hash, version) in versions {
+            let url = version["files"].as_array()
+                .and_then(|files| {
+                    files.iter().find(|f| f["hashes"]["sha512"].as_str() == Some(hash.as_str()))
+                })
+                .and_then(|file| file["url"].as_str());
+
+            if let Some(url) = url {
+                urls.insert(hash, url.to_string());
+            }
+        }
+    }
+
+    Ok(urls)
+}
+
+/// Установка обновлений: новые версии скачиваются параллельно,
+/// старые файлы удаляются. Результаты — в том же порядке: имя нового файла или ошибка.
+pub fn apply_updates(
+    mods_path: &Path,
+    updates: &[&ModUpdate],
+    concurrency: usize,
+) -> Vec<Result<String, String>> {
+    let versions: Vec<&ModVersion> = updates.iter().map(|u| &u.new_version).collect();
+    let results = download_versions(mods_path, &versions, concurrency);
+
+    let mut manifest = Manifest::load(mods_path);
+    let results = updates.iter()
+        .zip(results)
+        .map(|(update, result)| {
+            let filename = result?;
+            if filename != update.file_name {
+                let old = mods_path.join(&update.file_name);
+                if old.exists() {
+                    fs::remove_file(&old).map_err(|e| e.to_string())?;
+                }
+                manifest.remove(&update.file_name);
+            }
+            Ok(filename)
+        })
+        .collect();
+
+    if let Err(e) = manifest.save(mods_path) {
+        println!("󰀦 Не удалось сохранить манифест: {}", e);
+    }
+    results
+}
+
+/// Установка самой свежей совместимой версии мода без вопросов пользователю.
+/// Обязательные зависимости ставятся вместе с модом; если мод несовместим
+/// с уже установленными, ничего не скачивается.
+/// `project` — id или slug проекта. Возвращает имена скачанных файлов.
+pub fn add_project(
+    mods_path: &Path,
+    project: &str,
+    minecraft_version: &str,
+    loader: &str,
+    concurrency: usize,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mod_version = latest_compatible_version(project, minecraft_version, loader)?
+        .ok_or_else(|| format!("Нет версий для {} с лоадером {}", minecraft_version, loader))?;
+
+    fs::create_dir_all(mods_path)?;
+    let installed = installed_projects(mods_path)?;
+    let resolution = resolve_dependencies(mod_version, minecraft_version, loader, &installed)?;
+
+    if !resolution.conflicts.is_empty() {
+        let files: Vec<&str> = resolution.conflicts.iter().map(|(_, f)| f.as_str()).collect();
+        return Err(format!("Мод несовместим с установленными: {}", files.join(", ")).into());
+    }
+
+    let versions: Vec<&ModVersion> = resolution.to_install.iter().collect();
+    download_versions(mods_path, &versions, concurrency)
+        .into_iter()
+        .collect::<Result<Vec<String>, String>>()
+        .map_err(|e| e.into())
+}
+
+/// Параллельное скачивание версий модов через общую очередь
+/// и запись скачанных в манифест экземпляра.
+/// Результаты — в том же порядке: имя файла или текст ошибки.
+fn download_versions(
+    mods_path: &Path,
+    versions: &[&ModVersion],
+    concurrency: usize,
+) -> Vec<Result<String, String>> {
+    let mut queue = DownloadQueue::new(concurrency);
+    for version in versions {
+        queue.push(Download {
+            url: version.url.clone(),
+            destination: mods_path.join(&version.filename),
+            hashes: version.hashes.clone(),
+        });
+    }
+
+    let results = queue.run(&MultiProgress::new());
+
+    let mut manifest = Manifest::load(mods_path);
+    let results: Vec<Result<String, String>> = versions.iter()
+        .zip(results)
+        .map(|(version, result)| {
+            let path = result?;
+            let sha256 = manifest::sha256_file(&path).map_err(|e| e.to_string())?;
+            manifest.record(ManagedFile {
+                file_name: version.filename.clone(),
+                source: Source::Modrinth { project_id: version.project_id.clone() },
+                version: version.id.clone(),
+                sha256,
+            });
+            Ok(version.filename.clone())
+        })
+        .collect();
+
+    if let Err(e) = manifest.save(mods_path) {
+        println!("󰀦 Не удалось сохранить манифест: {}", e);
+    }
+    results
+}
+
+/// Поиск проектов на Modrinth нужного типа (`mod`, `modpack`)
+fn search_projects(query: &str, project_type: &str) -> Result<Vec<SearchHit>, Box<dyn std::error::Error>> {
+    let url = format!("{}/search", MODRINTH_API);
+    let facets = json!([[format!("project_type:{}", project_type)]]).to_string();
+    let json = download::get_json(&url, &[("query", query), ("limit", "20"), ("facets", &facets)])?;
     
     let mut results = Vec::new();
     if let Value::Array(hits) = &json["hits"] {
@@ -184,15 +716,9 @@ fn get_mod_versions(
     project_id: &str, 
     minecraft_version: &str,
     loader: &str
-) -> Result<Vec<(String, String, String)>, Box<dyn std::error::Error>> {
-    let client = Client::new();
+) -> Result<Vec<ModVersion>, Box<dyn std::error::Error>> {
     let url = format!("{}/project/{}/version", MODRINTH_API, project_id);
-    
-    let response = client.get(&url)
-        .timeout(Duration::from_secs(10))
-        .send()?;
-    
-    let versions: Value = response.json()?;
+    let versions = download::get_json(&url, &[])?;
     
     let mut compatible_versions = Vec::new();
     if let Value::Array(versions_array) = versions {
@@ -206,60 +732,223 @@ fn get_mod_versions(
                 let has_loader = l.iter().any(|l| l.as_str() == Some(loader));
                 
                 if has_correct_version && has_loader {
-                    let name = version["name"].as_str().unwrap_or("Без названия").to_string();
-                    
-                    // Получаем массив файлов
-                    if let Some(files) = version["files"].as_array() {
-                        if let Some(file) = files.first() {
-                            let filename = file["filename"].as_str().unwrap_or("mod.jar").to_string();
-                            let url = file["url"].as_str().unwrap_or("").to_string();
-                            
-                            compatible_versions.push((name, filename, url));
-                        }
+                    if let Some(mod_version) = parse_version(&version) {
+                        compatible_versions.push(mod_version);
                     }
                 }
             }
         }
     }
     
-    // Сортируем по имени (сначала свежие версии)
-    compatible_versions.sort_by(|a, b| b.0.cmp(&a.0));
+    // Сначала свежие версии: по дате публикации, а не по названию
+    // (в названиях «0.5.8» при сравнении строк оказывается выше «0.5.11»)
+    compatible_versions.sort_by_key(|v| std::cmp::Reverse(v.published));
     
     Ok(compatible_versions)
 }
 
-/// Скачивание файла с отображением прогресса
-fn download_file(url: &str, destination: &Path) -> Result<String, Box<dyn std::error::Error>> {
-    let client = Client::new();
-    let mut response = client.get(url)
-        .timeout(Duration::from_secs(30))
-        .send()?;
-    
-    let total_size = response.content_length().unwrap_or(0);
-    let filename = url.split('/').last().unwrap_or("mod.jar");
-    let filepath = destination.join(filename);
-    
-    let pb = ProgressBar::new(total_size);
-    pb.set_style(ProgressStyle::default_bar()
-        .template("{spinner:.green} [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({eta})")
-        .unwrap()
-        .progress_chars("#>-"));
-    
-    let mut file = File::create(&filepath)?;
-    let mut buffer = [0; 8192]; // 8KB buffer
-    let mut downloaded: u64 = 0;
-    
-    loop {
-        let bytes_read = response.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break;
-        }
-        
-        file.write_all(&buffer[..bytes_read])?;
-        downloaded += bytes_read as u64;
-        pb.set_position(downloaded);
+/// Самая свежая по дате публикации версия мода для версии Minecraft и лоадера
+fn latest_compatible_version(
+    project_id: &str,
+    minecraft_version: &str,
+    loader: &str,
+) -> Result<Option<ModVersion>, Box<dyn std::error::Error>> {
+    Ok(get_mod_versions(project_id, minecraft_version, loader)?.into_iter().next())
+}
+
+/// Все версии проекта, от новых к старым
+fn get_all_versions(project_id: &str) -> Result<Vec<ModVersion>, Box<dyn std::error::Error>> {
+    let url = format!("{}/project/{}/version", MODRINTH_API, project_id);
+    let versions = download::get_json(&url, &[])?;
+
+    Ok(versions.as_array()
+        .map(|versions| versions.iter().filter_map(parse_version).collect())
+        .unwrap_or_default())
+}
+
+/// Разбор версии из ответа API: основной файл версии (или первый, если основной не отмечен)
+fn parse_version(version: &Value) -> Option<ModVersion> {
+    let files = version["files"].as_array()?;
+    let file = files.iter()
+        .find(|f| f["primary"].as_bool() == Some(true))
+        .or_else(|| files.first())?;
+
+    let dependencies = version["dependencies"].as_array()
+        .map(|deps| deps.iter().filter_map(parse_dependency).collect())
+        .unwrap_or_default();
+
+    Some(ModVersion {
+        id: version["id"].as_str().unwrap_or("").to_string(),
+        project_id: version["project_id"].as_str().unwrap_or("").to_string(),
+        name: version["name"].as_str().unwrap_or("Без названия").to_string(),
+        filename: file["filename"].as_str().unwrap_or("mod.jar").to_string(),
+        url: file["url"].as_str().unwrap_or("").to_string(),
+        hashes: FileHashes {
+            sha512: file["hashes"]["sha512"].as_str().map(|s| s.to_lowercase()),
+            sha1: file["hashes"]["sha1"].as_str().map(|s| s.to_lowercase()),
+            ..FileHashes::default()
+        },
+        dependencies,
+        published: version["date_published"]
+            .as_str()
+            .and_then(|date| chrono::DateTime::parse_from_rfc3339(date).ok())
+            .map_or(0, |date| date.timestamp()),
+    })
+}
+
+/// Разбор одной зависимости версии
+fn parse_dependency(dependency: &Value) -> Option<Dependency> {
+    let kind = match dependency["dependency_type"].as_str()? {
+        "required" => DependencyKind::Required,
+        "optional" => DependencyKind::Optional,
+        "incompatible" => DependencyKind::Incompatible,
+        "embedded" => DependencyKind::Embedded,
+        _ => return None,
+    };
+
+    Some(Dependency {
+        project_id: dependency["project_id"].as_str().map(|s| s.to_string()),
+        version_id: dependency["version_id"].as_str().map(|s| s.to_string()),
+        kind,
+    })
+}
+
+/// Получение конкретной версии по id
+fn get_version(version_id: &str) -> Result<ModVersion, Box<dyn std::error::Error>> {
+    let url = format!("{}/version/{}", MODRINTH_API, version_id);
+    let version = download::get_json(&url, &[])?;
+
+    parse_version(&version).ok_or_else(|| format!("Версия {} без файлов", version_id).into())
+}
+
+/// Моды из папки модов, найденные на Modrinth по хешам: id проекта -> имя файла
+fn installed_projects(mods_path: &Path) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    let mut projects = HashMap::new();
+
+    // Моды, установленные через stm
+    for file in &Manifest::load(mods_path).files {
+        if let Source::Modrinth { project_id } = &file.source {
+            if mods_path.join(&file.file_name).exists() {
+                projects.insert(project_id.clone(), file.file_name.clone());
+            }
+        }
     }
-    
-    pb.finish_with_message(format!("󰄬 Скачано: {}", filename));
-    Ok(filename.to_string())
-}
\ No newline at end of file
+
+    // Остальные — по SHA-512 файлов
+    let hashes = hash_mods_dir(mods_path, false)?;
+    if !hashes.is_empty() {
+        let versions = lookup_versions(hashes.keys())?;
+        if let Value::Object(versions) = versions {
+            for (hash, version) in versions {
+                if let Some(project_id) = version["project_id"].as_str() {
+                    projects.insert(project_id.to_string(), hashes[&hash].clone());
+                }
+            }
+        }
+    }
+
+    Ok(projects)
+}
+
+/// Обход обязательных зависимостей версии для той же версии Minecraft и лоадера.
+/// Уже установленные проекты пропускаются.
+fn resolve_dependencies(
+    root: ModVersion,
+    minecraft_version: &str,
+    loader: &str,
+    installed: &HashMap<String, String>,
+) -> Result<Resolution, Box<dyn std::error::Error>> {
+    let mut resolution = Resolution {
+        to_install: Vec::new(),
+        optional: Vec::new(),
+        conflicts: Vec::new(),
+    };
+    let mut seen: HashSet<String> = installed.keys().cloned().collect();
+    seen.insert(root.project_id.clone());
+
+    let mut queue = vec![root];
+    while let Some(version) = queue.pop() {
+        for dependency in &version.dependencies {
+            let project_id = match &dependency.project_id {
+                Some(project_id) => project_id.clone(),
+                None => match &dependency.version_id {
+                    Some(version_id) => get_version(version_id)?.project_id,
+                    None => continue,
+                },
+            };
+
+            match dependency.kind {
+                DependencyKind::Required if !seen.contains(&project_id) => {
+                    seen.insert(project_id.clone());
+                    queue.push(pick_dependency_version(
+                        &project_id,
+                        dependency.version_id.as_deref(),
+                        minecraft_version,
+                        loader,
+                    )?);
+                }
+                DependencyKind::Optional if !seen.contains(&project_id)
+                    && !resolution.optional.contains(&project_id) =>
+                {
+                    resolution.optional.push(project_id);
+                }
+                DependencyKind::Incompatible => {
+                    if let Some(file_name) = installed.get(&project_id) {
+                        resolution.conflicts.push((project_id, file_name.clone()));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        resolution.to_install.push(version);
+    }
+
+    // Необязательные зависимости, которые уже стали обязательными, не предлагаем
+    resolution.optional.retain(|id| !resolution.to_install.iter().any(|v| &v.project_id == id));
+    Ok(resolution)
+}
+
+/// Выбор версии зависимости: указанная в зависимости или самая свежая совместимая
+fn pick_dependency_version(
+    project_id: &str,
+    version_id: Option<&str>,
+    minecraft_version: &str,
+    loader: &str,
+) -> Result<ModVersion, Box<dyn std::error::Error>> {
+    if let Some(version_id) = version_id {
+        return get_version(version_id);
+    }
+
+    latest_compatible_version(project_id, minecraft_version, loader)?
+        .ok_or_else(|| {
+            format!(
+                "Нет версии зависимости {} для {} с лоадером {}",
+                project_id, minecraft_version, loader
+            )
+            .into()
+        })
+}
+
+/// Названия проектов по id (для показа зависимостей)
+fn project_titles(ids: &[String]) -> HashMap<String, String> {
+    if ids.is_empty() {
+        return HashMap::new();
+    }
+
+    let url = format!("{}/projects", MODRINTH_API);
+    let ids_json = serde_json::to_string(ids).unwrap_or_default();
+
+    let projects = match download::get_json(&url, &[("ids", &ids_json)]) {
+        Ok(projects) => projects,
+        Err(_) => return HashMap::new(),
+    };
+
+    projects.as_array()
+        .map(|projects| {
+            projects.iter()
+                .filter_map(|p| Some((p["id"].as_str()?.to_string(), p["title"].as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
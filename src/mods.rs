@@ -1,23 +1,42 @@
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::io;
 use std::time::Duration;
 use indicatif::{ProgressBar, ProgressStyle, MultiProgress};
 
-/// Установка модов с отображением прогресса
+use crate::manifest::{self, ManagedFile, Manifest, Source};
+use crate::plan::{Action, InstallPlan, PlanEntry};
+
+/// Список .jar файлов в корне репозитория сборки
+pub fn pack_jars(repo_dir: &Path) -> io::Result<Vec<PathBuf>> {
+    Ok(fs::read_dir(repo_dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().and_then(|e| e.to_str()) == Some("jar"))
+        .collect())
+}
+
+/// Установка модов по плану с отображением прогресса.
+///
+/// Новая папка модов собирается рядом со старой, проверяется по хешам
+/// и подменяет старую целиком. При любой ошибке старая папка остаётся
+/// нетронутой. Каждый скопированный или оставленный мод сборки записывается
+/// в манифест с источником `source` и версией `version`.
 pub fn install_mods_with_progress(
-    repo_dir: &Path, 
     mods_dir: &Path, 
+    plan: &InstallPlan,
     multi_progress: &MultiProgress,
+    manifest: &mut Manifest,
+    source: &Source,
+    version: &str,
 ) -> io::Result<u32> {
-    // Получаем список файлов для установки
-    let files: Vec<_> = fs::read_dir(repo_dir)?
-        .filter_map(|e| e.ok())
-        .map(|e| e.path())
-        .filter(|p| p.is_file() && p.extension().and_then(|e| e.to_str()) == Some("jar"))
+    // Файлы сборки: копируемые, оставленные и пропускаемые
+    let files: Vec<&PlanEntry> = plan.entries.iter()
+        .filter(|e| e.source_path.is_some() || e.action == Action::Skip)
         .collect();
     
-    if files.is_empty() {
+    if !plan.has_changes() {
+        record_pack_files(&files, manifest, source, version);
         return Ok(0);
     }
     
@@ -28,67 +47,274 @@ pub fn install_mods_with_progress(
             .unwrap()
             .tick_strings(&["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"])
     );
-    pb.set_message("Начинаю установку...");
+    pb.set_message("Подготавливаю новую папку модов...");
     pb.enable_steady_tick(Duration::from_millis(80));
     
+    let staged = begin_transaction(mods_dir)?;
+    let result = stage_plan(&staged, plan, &files, &pb);
+
+    let installed_count = match result.and_then(|count| commit_transaction(mods_dir, &staged).map(|_| count)) {
+        Ok(count) => count,
+        Err(e) => {
+            fs::remove_dir_all(&staged).ok();
+            pb.finish_with_message(format!("Установка отменена, папка модов не изменена: {}", e));
+            return Err(e);
+        }
+    };
+
+    for entry in plan.with_action(Action::Remove) {
+        manifest.remove(&entry.file_name);
+    }
+    record_pack_files(&files, manifest, source, version);
+    
+    pb.finish_with_message("Установка завершена!");
+    Ok(installed_count)
+}
+
+/// Применение плана к подготовленной копии папки модов с проверкой хешей.
+/// Возвращает количество скопированных модов.
+fn stage_plan(staged: &Path, plan: &InstallPlan, files: &[&PlanEntry], pb: &ProgressBar) -> io::Result<u32> {
+    for entry in plan.with_action(Action::Remove) {
+        let path = staged.join(&entry.file_name);
+        if path.is_file() {
+            fs::remove_file(&path)?;
+        }
+    }
+
     let mut installed_count = 0;
     
     // Устанавливаем каждый мод
-    for (i, file_path) in files.iter().enumerate() {
-        let file_name = file_path.file_name().unwrap().to_string_lossy();
+    for (i, entry) in files.iter().enumerate() {
+        let file_name = &entry.file_name;
         
         // Пропускаем моды с амперсандом в начале
-        if file_name.starts_with('&') {
+        if entry.action == Action::Skip {
             pb.set_message(format!("Пропускаю: {}", file_name));
-            pb.inc(1);
             continue;
         }
         
-        let target = mods_dir.join(file_path.file_name().unwrap());
-        
         // Обновляем сообщение каждые 5 файлов
         if i % 5 == 0 {
             pb.set_message(format!("Установлено {}/{}", i, files.len()));
         }
         
-        // Копируем файл
-        match fs::copy(&file_path, &target) {
-            Ok(_) => {
-                installed_count += 1;
-            }
-            Err(e) => {
-                pb.println(format!("󰅖 Ошибка при установке {}: {}", file_name, e));
+        let target = staged.join(file_name);
+
+        // Копируем файл, если он изменился
+        if let (Action::Add | Action::Replace, Some(source_path)) = (entry.action, &entry.source_path) {
+            replace_file(source_path, &target).map_err(|e| {
+                io::Error::new(e.kind(), format!("Ошибка при установке {}: {}", file_name, e))
+            })?;
+            installed_count += 1;
+        }
+
+        // Проверяем, что в новой папке лежит именно файл сборки
+        if let Some(expected) = &entry.sha256 {
+            if &manifest::sha256_file(&target)? != expected {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Хеш {} не совпадает с файлом сборки", file_name),
+                ));
             }
         }
-        
-        pb.inc(1);
     }
-    
-    pb.finish_with_message(format!("Установка завершена!"));
+
     Ok(installed_count)
 }
 
-/// Очистка всех .jar файлов в директории
-pub fn clean_mods_dir(mods_dir: &Path) -> io::Result<u32> {
+/// Запись модов сборки из плана в манифест
+fn record_pack_files(files: &[&PlanEntry], manifest: &mut Manifest, source: &Source, version: &str) {
+    for entry in files.iter().filter(|e| e.action != Action::Skip) {
+        manifest.record(ManagedFile {
+            file_name: entry.file_name.clone(),
+            source: source.clone(),
+            version: version.to_string(),
+            sha256: entry.sha256.clone().unwrap_or_default(),
+        });
+    }
+}
+
+/// Копирование файла с заменой. Старый файл сначала удаляется, а не перезаписывается:
+/// на него может ссылаться жёсткая ссылка из снимка папки модов.
+fn replace_file(source: &Path, target: &Path) -> io::Result<u64> {
+    if target.exists() {
+        fs::remove_file(target)?;
+    }
+    fs::copy(source, target)
+}
+
+/// Удаление модов сборки, которые план помечает на удаление.
+/// Моды, добавленные игроком, в план на удаление не попадают.
+/// Как и установка, выполняется целиком или не выполняется вовсе.
+pub fn clean_mods_dir(mods_dir: &Path, plan: &InstallPlan, manifest: &mut Manifest) -> io::Result<u32> {
+    let staged = begin_transaction(mods_dir)?;
     let mut removed_count = 0;
+
+    let result = plan.with_action(Action::Remove).try_for_each(|entry| {
+        let path = staged.join(&entry.file_name);
+        if path.is_file() {
+            fs::remove_file(&path)?;
+            removed_count += 1;
+        }
+        Ok(())
+    });
+
+    if let Err(e) = result.and_then(|_| commit_transaction(mods_dir, &staged)) {
+        fs::remove_dir_all(&staged).ok();
+        return Err(e);
+    }
+
+    for entry in plan.with_action(Action::Remove) {
+        manifest.remove(&entry.file_name);
+    }
     
-    if !mods_dir.exists() {
+    Ok(removed_count)
+}
+
+/// Папка рядом с папкой модов: `.mods.stm-new` или `.mods.stm-old`
+fn sibling_dir(mods_dir: &Path, suffix: &str) -> PathBuf {
+    let name = mods_dir.file_name().unwrap_or_default().to_string_lossy();
+    mods_dir.with_file_name(format!(".{}.stm-{}", name, suffix))
+}
+
+/// Начало транзакции: копия папки модов из жёстких ссылок рядом с ней.
+/// Изменения вносятся в копию, оригинал не трогается до подмены.
+fn begin_transaction(mods_dir: &Path) -> io::Result<PathBuf> {
+    recover_interrupted(mods_dir)?;
+
+    let staged = sibling_dir(mods_dir, "new");
+    if staged.exists() {
+        fs::remove_dir_all(&staged)?;
+    }
+    link_tree(mods_dir, &staged)?;
+    Ok(staged)
+}
+
+/// Подмена папки модов содержимым другого дерева (например, снимка).
+/// Копия из жёстких ссылок собирается рядом и подменяет папку целиком,
+/// поэтому при ошибке папка модов остаётся прежней. `before_commit` вызывается,
+/// когда копия готова; его ошибка отменяет подмену. Возвращает количество файлов.
+pub fn replace_with_tree(
+    mods_dir: &Path,
+    from: &Path,
+    before_commit: impl FnOnce() -> io::Result<()>,
+) -> io::Result<u32> {
+    recover_interrupted(mods_dir)?;
+
+    let staged = sibling_dir(mods_dir, "new");
+    if staged.exists() {
+        fs::remove_dir_all(&staged)?;
+    }
+
+    let result = link_tree(from, &staged).and_then(|count| {
+        before_commit()?;
+        if mods_dir.exists() {
+            commit_transaction(mods_dir, &staged)?;
+        } else {
+            fs::rename(&staged, mods_dir)?;
+        }
+        Ok(count)
+    });
+    if result.is_err() {
+        fs::remove_dir_all(&staged).ok();
+    }
+    result
+}
+
+/// Подмена папки модов подготовленной копией.
+/// Между двумя переименованиями папки модов нет, но это состояние
+/// однозначно восстанавливается `recover_interrupted`.
+fn commit_transaction(mods_dir: &Path, staged: &Path) -> io::Result<()> {
+    let old = sibling_dir(mods_dir, "old");
+    if old.exists() {
+        fs::remove_dir_all(&old)?;
+    }
+
+    fs::rename(mods_dir, &old)?;
+    if let Err(e) = fs::rename(staged, mods_dir) {
+        fs::rename(&old, mods_dir)?;
+        return Err(e);
+    }
+
+    fs::remove_dir_all(&old).ok();
+    Ok(())
+}
+
+/// Восстановление после прерванной установки (например, при падении процесса):
+/// если папки модов нет, но осталась старая — возвращаем старую,
+/// недособранные копии удаляем. Возвращает true, если что-то пришлось чинить.
+pub fn recover_interrupted(mods_dir: &Path) -> io::Result<bool> {
+    let staged = sibling_dir(mods_dir, "new");
+    let old = sibling_dir(mods_dir, "old");
+    let mut recovered = false;
+
+    if old.exists() {
+        if mods_dir.exists() {
+            // Подмена прошла, не успели удалить старую папку
+            fs::remove_dir_all(&old)?;
+        } else {
+            fs::rename(&old, mods_dir)?;
+        }
+        recovered = true;
+    }
+
+    if staged.exists() {
+        fs::remove_dir_all(&staged)?;
+        recovered = true;
+    }
+
+    Ok(recovered)
+}
+
+/// Рекурсивное копирование дерева жёсткими ссылками
+/// (или обычным копированием, если ссылку создать нельзя).
+/// Возвращает количество файлов.
+pub fn link_tree(from: &Path, to: &Path) -> io::Result<u32> {
+    fs::create_dir_all(to)?;
+    let mut count = 0;
+
+    if !from.exists() {
         return Ok(0);
     }
-    
-    for entry in fs::read_dir(mods_dir)? {
+
+    for entry in fs::read_dir(from)? {
         let entry = entry?;
         let path = entry.path();
-        
-        if path.is_file() {
-            if let Some(ext) = path.extension() {
-                if ext == "jar" {
-                    fs::remove_file(&path)?;
-                    removed_count += 1;
-                }
+        let target = to.join(entry.file_name());
+
+        if path.is_dir() {
+            count += link_tree(&path, &target)?;
+        } else {
+            if fs::hard_link(&path, &target).is_err() {
+                fs::copy(&path, &target)?;
             }
+            count += 1;
         }
     }
-    
-    Ok(removed_count)
-}
\ No newline at end of file
+
+    Ok(count)
+}
+
+/// Поиск существующих папок с модами внутри папки Minecraft
+pub fn find_mods_dirs(minecraft_path: &Path) -> Vec<PathBuf> {
+    // Возможные пути к папкам с модами
+    let possible_mods_paths = [
+        minecraft_path.join("mods"),
+        minecraft_path.join("minecraft").join("mods"),
+        minecraft_path.join(".minecraft").join("mods"),
+    ];
+
+    possible_mods_paths
+        .into_iter()
+        .filter(|path| path.exists())
+        .collect()
+}
+
+/// Папка модов для неинтерактивного режима: первая найденная
+/// или `mods` в корне папки Minecraft
+pub fn default_mods_dir(minecraft_path: &Path) -> PathBuf {
+    find_mods_dirs(minecraft_path)
+        .into_iter()
+        .next()
+        .unwrap_or_else(|| minecraft_path.join("mods"))
+}
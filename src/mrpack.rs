@@ -0,0 +1,898 @@
+use console::Term;
+use indicatif::MultiProgress;
+use inquire::{Select, Text};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::backup;
+use crate::cache;
+use crate::download::{Download, DownloadQueue, FileHashes};
+use crate::git_ops::BuildType;
+use crate::launchers;
+use crate::manifest::{self, ManagedFile, Manifest, Source};
+use crate::modrinth;
+use crate::ui;
+
+/// Файл с описанием сборки внутри .mrpack
+const INDEX_FILE: &str = "modrinth.index.json";
+/// Папка архива, содержимое которой копируется в папку игры как есть
+const OVERRIDES_DIR: &str = "overrides";
+/// Папка модов относительно папки игры
+const MODS_DIR: &str = "mods";
+
+/// `modrinth.index.json` — описание сборки в формате Modrinth
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackIndex {
+    pub format_version: u32,
+    pub game: String,
+    pub version_id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    pub files: Vec<PackFile>,
+    /// Версии игры и лоадера: `minecraft`, `fabric-loader`, `forge`, `quilt-loader`, `neoforge`
+    pub dependencies: BTreeMap<String, String>,
+}
+
+/// Файл сборки, который скачивается при установке
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PackFile {
+    /// Путь относительно папки игры, например `mods/sodium.jar`
+    pub path: String,
+    /// `sha1` и `sha512`
+    pub hashes: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<BTreeMap<String, String>>,
+    pub downloads: Vec<String>,
+    pub file_size: u64,
+}
+
+/// Что записать в экспортируемую сборку
+pub struct ExportOptions {
+    pub name: String,
+    pub version_id: String,
+    pub game_version: String,
+    /// fabric, forge, quilt или neoforge
+    pub loader: String,
+    pub loader_version: String,
+}
+
+/// Итог экспорта
+pub struct ExportReport {
+    /// Файлы, которые будут скачаны с Modrinth
+    pub downloads: u32,
+    /// Файлы, положенные в архив как есть
+    pub overrides: u32,
+}
+
+/// Ключ лоадера в `dependencies` индекса
+fn loader_key(loader: &str) -> &str {
+    match loader {
+        "fabric" => "fabric-loader",
+        "quilt" => "quilt-loader",
+        other => other,
+    }
+}
+
+/// Экспорт папки модов в `.mrpack`.
+/// Моды, найденные на Modrinth по хешу, записываются ссылками на скачивание,
+/// всё остальное (в том числе моды сборки из git) кладётся в `overrides/mods`.
+pub fn export(mods_dir: &Path, output: &Path, options: &ExportOptions) -> Result<ExportReport, String> {
+    // Все файлы папки модов, кроме служебных
+    let mut local_files = Vec::new();
+    for entry in WalkDir::new(mods_dir).into_iter().filter_map(|e| e.ok()) {
+        let relative = match entry.path().strip_prefix(mods_dir) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(_) => continue,
+        };
+        let hidden = relative
+            .components()
+            .any(|c| c.as_os_str().to_string_lossy().starts_with('.'));
+
+        if entry.file_type().is_file() && !hidden {
+            local_files.push(relative);
+        }
+    }
+    local_files.sort();
+
+    // Поиск .jar файлов на Modrinth по SHA-512
+    let mut sha512s = BTreeMap::new();
+    for relative in local_files.iter().filter(|p| is_root_jar(p)) {
+        let hash = manifest::sha512_file(&mods_dir.join(relative))
+            .map_err(|e| format!("Ошибка чтения {}: {}", relative.display(), e))?;
+        sha512s.insert(relative.clone(), hash);
+    }
+    let hashes: Vec<String> = sha512s.values().cloned().collect();
+    let urls = modrinth::download_urls(&hashes)
+        .map_err(|e| format!("Ошибка поиска модов на Modrinth: {}", e))?;
+
+    let mut index = PackIndex {
+        format_version: 1,
+        game: "minecraft".to_string(),
+        version_id: options.version_id.clone(),
+        name: options.name.clone(),
+        summary: None,
+        files: Vec::new(),
+        dependencies: BTreeMap::from([
+            ("minecraft".to_string(), options.game_version.clone()),
+            (loader_key(&options.loader).to_string(), options.loader_version.clone()),
+        ]),
+    };
+    let mut overrides = Vec::new();
+
+    for relative in local_files {
+        let path = mods_dir.join(&relative);
+        let url = sha512s.get(&relative).and_then(|hash| Some((hash, urls.get(hash)?)));
+
+        match url {
+            Some((sha512, url)) => {
+                let sha1 = manifest::sha1_file(&path).map_err(|e| e.to_string())?;
+                index.files.push(PackFile {
+                    path: format!("mods/{}", zip_path(&relative)),
+                    hashes: BTreeMap::from([
+                        ("sha1".to_string(), sha1),
+                        ("sha512".to_string(), sha512.clone()),
+                    ]),
+                    env: None,
+                    downloads: vec![url.clone()],
+                    file_size: fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+                });
+            }
+            None => overrides.push(relative),
+        }
+    }
+
+    write_archive(mods_dir, output, &index, &overrides)
+        .map_err(|e| format!("Ошибка записи {}: {}", output.display(), e))?;
+
+    Ok(ExportReport {
+        downloads: index.files.len() as u32,
+        overrides: overrides.len() as u32,
+    })
+}
+
+/// .jar в корне папки модов — только такие ищутся на Modrinth
+fn is_root_jar(relative: &Path) -> bool {
+    relative.components().count() == 1
+        && relative.extension().and_then(|e| e.to_str()) == Some("jar")
+}
+
+/// Путь внутри архива всегда через `/`
+fn zip_path(relative: &Path) -> String {
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Запись архива: сначала во временный файл, затем переименование
+fn write_archive(
+    mods_dir: &Path,
+    output: &Path,
+    index: &PackIndex,
+    overrides: &[PathBuf],
+) -> io::Result<()> {
+    let partial = output.with_extension("mrpack.part");
+    let result = (|| {
+        let mut zip = ZipWriter::new(File::create(&partial)?);
+        let options = FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+        zip.start_file(INDEX_FILE, options)?;
+        let json = serde_json::to_vec_pretty(index).map_err(io::Error::other)?;
+        zip.write_all(&json)?;
+
+        for relative in overrides {
+            zip.start_file(format!("{}/mods/{}", OVERRIDES_DIR, zip_path(relative)), options)?;
+            io::copy(&mut File::open(mods_dir.join(relative))?, &mut zip)?;
+        }
+
+        zip.finish()?;
+        Ok(())
+    })();
+
+    match result {
+        Ok(()) => fs::rename(&partial, output),
+        Err(e) => {
+            let _ = fs::remove_file(&partial);
+            Err(e)
+        }
+    }
+}
+
+/// Экспорт экземпляра в .mrpack с вопросами пользователю
+pub fn export_pack(folder: &ui::GameFolder) {
+    let mods_path = match folder.mods_path() {
+        Some(path) => path,
+        None => return,
+    };
+
+    let term = Term::stdout();
+    let _ = term.clear_screen();
+    println!("󰏗 Экспорт в .mrpack");
+    println!("=============================\n");
+
+    let game_dir = mods_path.parent().unwrap_or(folder.game_dir());
+    let (game_version, loader) = match modrinth::game_version_and_loader(game_dir) {
+        Some(answer) => answer,
+        None => {
+            println!("󰅖 Отменено");
+            return;
+        }
+    };
+
+    // Версию лоадера из файлов лаунчера предлагаем по умолчанию
+    let detected_loader_version = launchers::detect_game(game_dir)
+        .filter(|info| info.loader.as_deref() == Some(loader))
+        .and_then(|info| info.loader_version)
+        .unwrap_or_default();
+
+    let answers = (|| {
+        let loader_version = Text::new(&format!("󰝚 Версия {}:", loader))
+            .with_default(&detected_loader_version)
+            .with_help_message("Например: 0.15.11 для Fabric или 47.2.0 для Forge")
+            .prompt()
+            .ok()?;
+        let name = Text::new("󰝚 Название сборки:")
+            .with_default("StoryTime")
+            .prompt()
+            .ok()?;
+        let version_id = Text::new("󰝚 Версия сборки:")
+            .with_default("1.0.0")
+            .prompt()
+            .ok()?;
+        let default_output = folder.game_dir().join(format!("{}-{}.mrpack", name, version_id));
+        let output = Text::new("󰝚 Куда сохранить:")
+            .with_default(&default_output.to_string_lossy())
+            .prompt()
+            .ok()?;
+        Some((loader_version, name, version_id, PathBuf::from(output)))
+    })();
+
+    let (loader_version, name, version_id, output) = match answers {
+        Some(answers) => answers,
+        None => {
+            println!("󰅖 Отменено");
+            return;
+        }
+    };
+
+    let options = ExportOptions {
+        name,
+        version_id,
+        game_version,
+        loader: loader.to_string(),
+        loader_version,
+    };
+
+    println!("󰇚 Ищу моды на Modrinth...");
+    match export(&mods_path, &output, &options) {
+        Ok(report) => {
+            println!("󰄬 Сборка сохранена: {}", output.display());
+            println!("   С Modrinth: {}, в overrides: {}", report.downloads, report.overrides);
+        }
+        Err(e) => println!("󰅖 {}", e),
+    }
+    println!("󰝚 Нажмите Enter чтобы продолжить...");
+    let _ = std::io::stdin().read_line(&mut String::new());
+}
+
+/// Итог установки .mrpack
+pub struct InstallReport {
+    pub name: String,
+    pub version_id: String,
+    /// Скачанные файлы
+    pub downloaded: u32,
+    /// Файлы, пропущенные из-за стороны (клиент или сервер)
+    pub skipped: u32,
+    /// Файлы, скопированные из overrides
+    pub overrides: u32,
+    /// Моды прошлой версии сборки, которых нет в новой
+    pub removed: u32,
+    /// Файлы, которые не удалось скачать или удалить: (путь, ошибка)
+    pub failed: Vec<(String, String)>,
+    /// Версии игры и лоадера, которые нужны сборке
+    pub dependencies: BTreeMap<String, String>,
+}
+
+impl InstallReport {
+    /// Лоадер сборки (fabric, forge, quilt или neoforge) по её `dependencies`
+    pub fn loader(&self) -> Option<&'static str> {
+        ["fabric", "quilt", "forge", "neoforge"]
+            .into_iter()
+            .find(|loader| self.dependencies.contains_key(loader_key(loader)))
+    }
+
+    /// Записан ли в папку игры хоть один файл, даже если часть не скачалась
+    pub fn wrote_files(&self) -> bool {
+        self.downloaded > 0 || self.overrides > 0
+    }
+}
+
+/// Установка .mrpack из файла или по ссылке в папку игры, в которой лежит `mods_dir`.
+/// Файлы из индекса скачиваются и проверяются по хешам, файлы, не нужные
+/// выбранной стороне (`env.client`/`env.server` = `unsupported`), пропускаются,
+/// затем поверх копируются `overrides` и `client-overrides`/`server-overrides`.
+pub fn install(
+    source: &str,
+    mods_dir: &Path,
+    build_type: BuildType,
+    concurrency: usize,
+    backup_limit: usize,
+) -> Result<InstallReport, String> {
+    let archive = fetch_archive(source, &FileHashes::default(), concurrency)?;
+    install_archive(&archive, mods_dir, build_type, concurrency, backup_limit)
+}
+
+/// Архив сборки: локальный файл или скачанный во временную папку.
+/// Скачанный файл удаляется, когда архив больше не нужен.
+struct FetchedArchive {
+    path: PathBuf,
+    downloaded: bool,
+}
+
+impl Deref for FetchedArchive {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for FetchedArchive {
+    fn drop(&mut self) {
+        if self.downloaded {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Архив по пути или ссылке: локальный файл как есть, ссылка — скачивается во временную папку
+fn fetch_archive(source: &str, hashes: &FileHashes, concurrency: usize) -> Result<FetchedArchive, String> {
+    if !source.starts_with("http://") && !source.starts_with("https://") {
+        let path = PathBuf::from(source.strip_prefix("file://").unwrap_or(source));
+        if !path.is_file() {
+            return Err(format!("Файл {} не найден", source));
+        }
+        return Ok(FetchedArchive { path, downloaded: false });
+    }
+
+    let filename = source
+        .split('?')
+        .next()
+        .and_then(|s| s.rsplit('/').next())
+        .filter(|s| !s.is_empty())
+        .unwrap_or("pack.mrpack");
+    // Своё имя на каждый процесс: две установки одновременно не мешают друг другу
+    let destination = std::env::temp_dir().join(format!("stm-{}-{}", std::process::id(), filename));
+    let archive = FetchedArchive { path: destination.clone(), downloaded: true };
+
+    let mut queue = DownloadQueue::new(concurrency);
+    queue.push(Download {
+        url: source.to_string(),
+        destination,
+        hashes: hashes.clone(),
+    });
+
+    queue
+        .run(&MultiProgress::new())
+        .pop()
+        .unwrap_or_else(|| Err("скачивание не запускалось".to_string()))
+        .map(|_| archive)
+        .map_err(|e| format!("Ошибка скачивания сборки: {}", e))
+}
+
+fn install_archive(
+    archive_path: &Path,
+    mods_dir: &Path,
+    build_type: BuildType,
+    concurrency: usize,
+    backup_limit: usize,
+) -> Result<InstallReport, String> {
+    let (mut archive, index) = open_archive(archive_path)?;
+
+    let game_dir = mods_dir.parent().unwrap_or(mods_dir).to_path_buf();
+    let side = side_name(build_type);
+
+    // Что скачивать: проверяем пути и хеши до любых изменений
+    let mut downloads = Vec::new();
+    let mut skipped = 0;
+    for file in &index.files {
+        if !supports_side(file, side) {
+            skipped += 1;
+            continue;
+        }
+
+        let relative = safe_path(&file.path)?;
+        downloads.push((file.path.clone(), pack_download(file, game_dir.join(relative))?));
+    }
+
+    if mods_dir.exists() {
+        backup::snapshot(mods_dir, backup_limit)
+            .map_err(|e| format!("Не удалось сохранить снимок, установка отменена: {}", e))?;
+    }
+
+    // Скачивание файлов
+    let mut queue = DownloadQueue::new(concurrency);
+    let mut paths = Vec::new();
+    // Файлы новой версии сборки, в том числе те, что не удастся скачать
+    let mut current = HashSet::new();
+    for (path, download) in downloads {
+        if let Some(parent) = download.destination.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Ошибка создания {}: {}", parent.display(), e))?;
+        }
+        paths.push(path);
+        current.insert(download.destination.clone());
+        queue.push(download);
+    }
+
+    let mut manifest = Manifest::load(mods_dir);
+    let source = Source::Mrpack { name: index.name.clone() };
+    let mut downloaded = 0;
+    let mut failed = Vec::new();
+
+    for (path, result) in paths.into_iter().zip(queue.run(&MultiProgress::new())) {
+        match result {
+            Ok(destination) => {
+                downloaded += 1;
+                record_mod(&mut manifest, mods_dir, &destination, &source, &index.version_id);
+            }
+            Err(e) => failed.push((path, format!("не скачан: {}", e))),
+        }
+    }
+
+    // Overrides: общие, затем для выбранной стороны
+    let mut overrides = 0;
+    for prefix in [OVERRIDES_DIR, &format!("{}-{}", side, OVERRIDES_DIR)] {
+        overrides += extract_overrides(&mut archive, prefix, &game_dir, |destination| {
+            current.insert(destination.to_path_buf());
+            record_mod(&mut manifest, mods_dir, destination, &source, &index.version_id);
+        })?;
+    }
+
+    // Моды прошлой версии этой сборки, которых нет в новой, иначе они
+    // остались бы рядом с новыми версиями тех же модов
+    let stale: Vec<String> = manifest
+        .files
+        .iter()
+        .filter(|f| f.source == source && !current.contains(&mods_dir.join(&f.file_name)))
+        .map(|f| f.file_name.clone())
+        .collect();
+    let mut removed = 0;
+    for file_name in stale {
+        let path = mods_dir.join(&file_name);
+        let disabled = mods_dir.join(format!("{}.disabled", file_name));
+        let result = [path, disabled]
+            .iter()
+            .filter(|p| p.exists())
+            .try_for_each(fs::remove_file);
+        match result {
+            Ok(()) => {
+                manifest.remove(&file_name);
+                removed += 1;
+            }
+            Err(e) => failed.push((format!("mods/{}", file_name), format!("не удалось удалить: {}", e))),
+        }
+    }
+
+    manifest
+        .save(mods_dir)
+        .map_err(|e| format!("Ошибка сохранения манифеста: {}", e))?;
+
+    Ok(InstallReport {
+        name: index.name,
+        version_id: index.version_id,
+        downloaded,
+        skipped,
+        overrides,
+        removed,
+        failed,
+        dependencies: index.dependencies,
+    })
+}
+
+/// Моды сборки .mrpack для стороны в отдельной папке в кеше, чтобы ставить
+/// её как обычную сборку: файлы из `mods/` индекса и моды из overrides.
+/// Уже скачанные файлы с верным хешем не качаются заново.
+/// Возвращает папку и версию сборки.
+pub fn resolve_mods(source: &str, build_type: BuildType, concurrency: usize) -> Result<(PathBuf, String), String> {
+    let archive_file = fetch_archive(source, &FileHashes::default(), concurrency)?;
+    let (mut archive, index) = open_archive(&archive_file)?;
+    let side = side_name(build_type);
+
+    let name = cache::repo_dir(source).file_name().unwrap_or_default().to_string_lossy().to_string();
+    let output = cache::derived_dir("mrpack", &format!("{}-{}", name, side));
+    fs::create_dir_all(&output).map_err(|e| format!("Ошибка создания {}: {}", output.display(), e))?;
+
+    let mut wanted = HashSet::new();
+    let mut queue = DownloadQueue::new(concurrency);
+    for file in &index.files {
+        let relative = safe_path(&file.path)?;
+        let name = match relative.strip_prefix(MODS_DIR) {
+            Ok(name) if is_root_jar(name) => name.to_path_buf(),
+            _ => continue,
+        };
+        if !supports_side(file, side) {
+            continue;
+        }
+
+        let download = pack_download(file, output.join(&name))?;
+        wanted.insert(name.into_os_string());
+        if download.destination.exists() && download.hashes.verify(&download.destination).is_ok() {
+            continue;
+        }
+        queue.push(download);
+    }
+
+    let failed: Vec<String> = queue
+        .run(&MultiProgress::new())
+        .into_iter()
+        .filter_map(|result| result.err())
+        .collect();
+    if !failed.is_empty() {
+        return Err(format!("Не удалось скачать моды сборки: {}", failed.join("; ")));
+    }
+
+    // Моды, лежащие в архиве как есть
+    for prefix in [OVERRIDES_DIR.to_string(), format!("{}-{}", side, OVERRIDES_DIR)] {
+        extract_overrides(&mut archive, &format!("{}/{}", prefix, MODS_DIR), &output, |path| {
+            if let Some(name) = path.file_name() {
+                wanted.insert(name.to_os_string());
+            }
+        })?;
+    }
+
+    // Моды, которых больше нет в сборке
+    for entry in fs::read_dir(&output).map_err(|e| e.to_string())? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if path.is_file() && !wanted.contains(path.file_name().unwrap_or_default()) {
+            fs::remove_file(&path).map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok((output, index.version_id))
+}
+
+/// Архив .mrpack и его индекс
+fn open_archive(archive_path: &Path) -> Result<(ZipArchive<File>, PackIndex), String> {
+    let file = File::open(archive_path).map_err(|e| format!("Ошибка открытия архива: {}", e))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| format!("Архив повреждён: {}", e))?;
+
+    let index: PackIndex = {
+        let entry = archive
+            .by_name(INDEX_FILE)
+            .map_err(|_| format!("В архиве нет {}", INDEX_FILE))?;
+        serde_json::from_reader(entry).map_err(|e| format!("Ошибка чтения {}: {}", INDEX_FILE, e))?
+    };
+    Ok((archive, index))
+}
+
+fn side_name(build_type: BuildType) -> &'static str {
+    match build_type {
+        BuildType::Client => "client",
+        BuildType::Server => "server",
+    }
+}
+
+/// Нужен ли файл стороне: `env.client`/`env.server` = `unsupported` — не нужен
+fn supports_side(file: &PackFile, side: &str) -> bool {
+    file.env
+        .as_ref()
+        .and_then(|env| env.get(side))
+        .is_none_or(|value| value != "unsupported")
+}
+
+/// Скачивание файла из индекса; без ссылки или без хеша — ошибка
+fn pack_download(file: &PackFile, destination: PathBuf) -> Result<Download, String> {
+    let url = file
+        .downloads
+        .first()
+        .ok_or_else(|| format!("У файла {} нет ссылки на скачивание", file.path))?;
+    let hashes = FileHashes {
+        sha512: file.hashes.get("sha512").cloned(),
+        sha1: file.hashes.get("sha1").cloned(),
+        ..FileHashes::default()
+    };
+    if hashes.is_empty() {
+        return Err(format!("У файла {} нет хеша в индексе", file.path));
+    }
+
+    Ok(Download {
+        url: url.clone(),
+        destination,
+        hashes,
+    })
+}
+
+/// Путь из архива относительно папки игры. Абсолютные пути и `..` запрещены,
+/// чтобы сборка не могла записать файлы за пределы папки игры.
+pub fn safe_path(path: &str) -> Result<PathBuf, String> {
+    let relative = PathBuf::from(path);
+    // `\` и `:` проверяются отдельно: на Linux `C:\mods` и `..\x` — обычные
+    // имена, а на Windows — диск и выход из папки
+    let safe = !path.is_empty()
+        && !path.contains(['\\', ':'])
+        && relative
+            .components()
+            .all(|c| matches!(c, std::path::Component::Normal(_)));
+
+    if safe {
+        Ok(relative)
+    } else {
+        Err(format!("Недопустимый путь в сборке: {}", path))
+    }
+}
+
+/// Копирование содержимого папки архива `prefix/` в папку игры.
+/// Возвращает количество файлов.
+pub fn extract_overrides(
+    archive: &mut ZipArchive<File>,
+    prefix: &str,
+    game_dir: &Path,
+    mut on_file: impl FnMut(&Path),
+) -> Result<u32, String> {
+    let mut count = 0;
+    let prefix = format!("{}/", prefix);
+
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| e.to_string())?;
+        let name = entry.name().to_string();
+        let relative = match name.strip_prefix(&prefix) {
+            Some(relative) if !relative.is_empty() && !entry.is_dir() => safe_path(relative)?,
+            _ => continue,
+        };
+
+        let destination = game_dir.join(relative);
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        // Старый файл удаляется, а не перезаписывается: на него может ссылаться снимок
+        if destination.exists() {
+            fs::remove_file(&destination).map_err(|e| e.to_string())?;
+        }
+        let mut out = File::create(&destination)
+            .map_err(|e| format!("Ошибка записи {}: {}", destination.display(), e))?;
+        io::copy(&mut entry, &mut out).map_err(|e| e.to_string())?;
+
+        on_file(&destination);
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+/// Запись мода из сборки в манифест (файлы вне папки модов не записываются)
+pub fn record_mod(manifest: &mut Manifest, mods_dir: &Path, path: &Path, source: &Source, version: &str) {
+    if path.parent() != Some(mods_dir) {
+        return;
+    }
+
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+    if let Ok(sha256) = manifest::sha256_file(path) {
+        manifest.record(ManagedFile {
+            file_name,
+            source: source.clone(),
+            version: version.to_string(),
+            sha256,
+        });
+    }
+}
+
+/// Установка .mrpack с вопросами пользователю: файл, ссылка или поиск на Modrinth
+pub fn install_pack(folder: &ui::GameFolder, concurrency: usize, backup_limit: usize) {
+    let mods_path = match folder.mods_path() {
+        Some(path) => path,
+        None => return,
+    };
+    let build_type = match ui::select_build_type() {
+        Some(build_type) => build_type,
+        None => return,
+    };
+
+    let options = vec!["󰈔 Файл или ссылка", "󰚨 Найти на Modrinth"];
+    let archive = match Select::new("󰝚 Откуда взять сборку?", options).prompt() {
+        Ok("󰚨 Найти на Modrinth") => modrinth::choose_modpack()
+            .ok_or_else(|| "Отменено".to_string())
+            .and_then(|pack| fetch_archive(&pack.url, &pack.hashes, concurrency)),
+        Ok(_) => match Text::new("󰝚 Путь к .mrpack или ссылка:").prompt() {
+            Ok(source) if !source.trim().is_empty() => fetch_archive(source.trim(), &FileHashes::default(), concurrency),
+            _ => Err("Отменено".to_string()),
+        },
+        Err(_) => Err("Отменено".to_string()),
+    };
+
+    let result = archive.and_then(|archive| {
+        println!("󰇚 Устанавливаю сборку...");
+        install_archive(&archive, &mods_path, build_type, concurrency, backup_limit)
+    });
+    let wrote_files = result.as_ref().is_ok_and(InstallReport::wrote_files);
+    print_install_report(result);
+    if wrote_files {
+        ui::check_duplicates(&mods_path, backup_limit);
+    }
+
+    println!("󰝚 Нажмите Enter чтобы продолжить...");
+    let _ = std::io::stdin().read_line(&mut String::new());
+}
+
+/// Вывод итога установки .mrpack. Возвращает false, если что-то не установилось.
+pub fn print_install_report(result: Result<InstallReport, String>) -> bool {
+    let report = match result {
+        Ok(report) => report,
+        Err(e) => {
+            println!("󰅖 {}", e);
+            return false;
+        }
+    };
+
+    println!("󰄬 Сборка {} {} установлена", report.name, report.version_id);
+    println!(
+        "   Скачано: {}, из overrides: {}, пропущено для этой стороны: {}",
+        report.downloaded, report.overrides, report.skipped
+    );
+    if report.removed > 0 {
+        println!("   Удалено модов прошлой версии: {}", report.removed);
+    }
+    let requirements: Vec<String> = report
+        .dependencies
+        .iter()
+        .map(|(name, version)| format!("{} {}", name, version))
+        .collect();
+    println!("   Нужно: {}", requirements.join(", "));
+
+    for (path, e) in &report.failed {
+        println!("󰅖 {}: {}", path, e);
+    }
+    report.failed.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("stm-mrpack-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    /// .mrpack без скачиваемых файлов: всё содержимое в overrides
+    fn write_pack(path: &Path, version_id: &str, entries: &[(&str, &str)]) {
+        let index = serde_json::json!({
+            "formatVersion": 1,
+            "game": "minecraft",
+            "versionId": version_id,
+            "name": "Test Pack",
+            "files": [],
+            "dependencies": { "minecraft": "1.20.1", "fabric-loader": "0.15.11" },
+        });
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        zip.start_file(INDEX_FILE, FileOptions::default()).unwrap();
+        zip.write_all(index.to_string().as_bytes()).unwrap();
+        for (name, content) in entries {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn pack_file(env: Option<(&str, &str)>) -> PackFile {
+        PackFile {
+            path: "mods/a.jar".to_string(),
+            hashes: BTreeMap::new(),
+            env: env.map(|(client, server)| {
+                BTreeMap::from([("client".to_string(), client.to_string()), ("server".to_string(), server.to_string())])
+            }),
+            downloads: Vec::new(),
+            file_size: 0,
+        }
+    }
+
+    #[test]
+    fn safe_path_rejects_escapes() {
+        assert_eq!(safe_path("mods/sodium.jar").unwrap(), PathBuf::from("mods/sodium.jar"));
+        assert_eq!(safe_path("config/a b/c.toml").unwrap(), PathBuf::from("config/a b/c.toml"));
+
+        for path in [
+            "",
+            "..",
+            "../evil.jar",
+            "mods/../../evil.jar",
+            "./mods/a.jar",
+            "/etc/passwd",
+            "/mods/a.jar",
+            "C:\\Windows\\evil.dll",
+            "C:/Windows/evil.dll",
+            "C:evil.jar",
+            "mods\\..\\..\\evil.jar",
+            "\\\\server\\share\\evil.jar",
+        ] {
+            assert!(safe_path(path).is_err(), "{:?}", path);
+        }
+    }
+
+    #[test]
+    fn unsupported_side_is_skipped() {
+        assert!(supports_side(&pack_file(None), "client"));
+        assert!(supports_side(&pack_file(None), "server"));
+
+        let client_only = pack_file(Some(("required", "unsupported")));
+        assert!(supports_side(&client_only, "client"));
+        assert!(!supports_side(&client_only, "server"));
+
+        let server_only = pack_file(Some(("unsupported", "optional")));
+        assert!(!supports_side(&server_only, "client"));
+        assert!(supports_side(&server_only, "server"));
+    }
+
+    #[test]
+    fn overrides_cannot_escape_the_game_folder() {
+        let root = temp_root("escape");
+        let archive_path = root.join("evil.mrpack");
+        write_pack(
+            &archive_path,
+            "1",
+            &[("overrides/config/ok.toml", "ok"), ("overrides/../../evil.txt", "evil")],
+        );
+        let game_dir = root.join("game");
+        let (mut archive, _) = open_archive(&archive_path).unwrap();
+
+        assert!(extract_overrides(&mut archive, OVERRIDES_DIR, &game_dir, |_| {}).is_err());
+        assert!(!root.join("evil.txt").exists());
+        assert!(!root.parent().unwrap().join("evil.txt").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn upgrade_removes_mods_of_the_previous_version() {
+        let root = temp_root("upgrade");
+        let mods_dir = root.join("game").join(MODS_DIR);
+        fs::create_dir_all(&mods_dir).unwrap();
+        fs::write(mods_dir.join("player.jar"), "player").unwrap();
+
+        let v1 = root.join("v1.mrpack");
+        write_pack(&v1, "1.0", &[("overrides/mods/a-1.0.jar", "a1"), ("overrides/mods/b.jar", "b")]);
+        let report = install_archive(&v1, &mods_dir, BuildType::Client, 1, 5).unwrap();
+        assert_eq!((report.overrides, report.removed), (2, 0));
+
+        // Пользователь отключил мод, который уйдёт из следующей версии
+        fs::rename(mods_dir.join("b.jar"), mods_dir.join("b.jar.disabled")).unwrap();
+
+        let v2 = root.join("v2.mrpack");
+        write_pack(&v2, "2.0", &[("overrides/mods/a-2.0.jar", "a2"), ("client-overrides/mods/c.jar", "c")]);
+        let report = install_archive(&v2, &mods_dir, BuildType::Client, 1, 5).unwrap();
+        assert_eq!((report.overrides, report.removed), (2, 2));
+        assert!(report.failed.is_empty());
+
+        let mut files: Vec<String> = fs::read_dir(&mods_dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+        assert_eq!(files, ["a-2.0.jar", "c.jar", "player.jar"]);
+
+        let mut recorded: Vec<(String, String)> = Manifest::load(&mods_dir)
+            .files
+            .into_iter()
+            .map(|f| (f.file_name, f.version))
+            .collect();
+        recorded.sort();
+        assert_eq!(
+            recorded,
+            [("a-2.0.jar".to_string(), "2.0".to_string()), ("c.jar".to_string(), "2.0".to_string())]
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,522 @@
+use indicatif::MultiProgress;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::cache;
+use crate::config::CurseForgeSettings;
+use crate::curseforge;
+use crate::download::{Download, DownloadQueue, FileHashes};
+use crate::git_ops::BuildType;
+use crate::mrpack;
+
+/// Главный файл сборки packwiz
+const PACK_FILE: &str = "pack.toml";
+/// Расширение файлов с описанием мода
+const META_SUFFIX: &str = ".pw.toml";
+/// Папка сборки, из которой берутся моды
+const MODS_DIR: &str = "mods";
+
+/// `pack.toml`
+#[derive(Deserialize)]
+struct Pack {
+    #[serde(default)]
+    version: Option<String>,
+    index: IndexRef,
+}
+
+#[derive(Deserialize)]
+struct IndexRef {
+    /// Путь к `index.toml` относительно `pack.toml`
+    file: String,
+    #[serde(rename = "hash-format")]
+    hash_format: Option<String>,
+    hash: Option<String>,
+}
+
+/// `index.toml`: все файлы сборки с хешами
+#[derive(Deserialize)]
+struct Index {
+    #[serde(rename = "hash-format")]
+    hash_format: String,
+    #[serde(default)]
+    files: Vec<IndexFile>,
+}
+
+#[derive(Deserialize)]
+struct IndexFile {
+    file: String,
+    hash: String,
+    #[serde(rename = "hash-format")]
+    hash_format: Option<String>,
+    /// Файл — описание мода `.pw.toml`, а не сам файл
+    #[serde(default)]
+    metafile: bool,
+}
+
+/// Описание мода `*.pw.toml`
+#[derive(Deserialize)]
+struct ModMeta {
+    filename: String,
+    /// `client`, `server` или `both`
+    #[serde(default = "default_side")]
+    side: String,
+    download: ModDownload,
+    #[serde(default)]
+    update: ModUpdate,
+}
+
+#[derive(Deserialize)]
+struct ModDownload {
+    url: Option<String>,
+    #[serde(rename = "hash-format")]
+    hash_format: String,
+    hash: String,
+    /// `url` (по умолчанию) или `metadata:curseforge` — ссылку нужно получить из API
+    #[serde(default)]
+    mode: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct ModUpdate {
+    curseforge: Option<CurseForgeUpdate>,
+}
+
+#[derive(Deserialize)]
+struct CurseForgeUpdate {
+    #[serde(rename = "project-id")]
+    project_id: u64,
+    #[serde(rename = "file-id")]
+    file_id: u64,
+}
+
+fn default_side() -> String {
+    "both".to_string()
+}
+
+/// Сборка в формате packwiz, если в корне репозитория есть `pack.toml`
+pub fn is_packwiz(repo_dir: &Path) -> bool {
+    repo_dir.join(PACK_FILE).is_file()
+}
+
+/// Сборка модов packwiz в готовую папку с .jar файлами для выбранной стороны.
+/// Файлы скачиваются (ссылки Modrinth и URL — напрямую, CurseForge — через API),
+/// проверяются по хешам и кешируются: повторно качаются только изменившиеся.
+/// Возвращает папку, которую можно устанавливать как обычную сборку.
+pub fn resolve(
+    repo_dir: &Path,
+    build_type: BuildType,
+    curseforge_settings: &CurseForgeSettings,
+    concurrency: usize,
+) -> Result<PathBuf, String> {
+    let output = resolved_dir(repo_dir, build_type);
+    resolve_into(repo_dir, &output, build_type, curseforge_settings, concurrency)?;
+    Ok(output)
+}
+
+/// Сборка модов packwiz в папку `output`
+fn resolve_into(
+    repo_dir: &Path,
+    output: &Path,
+    build_type: BuildType,
+    curseforge_settings: &CurseForgeSettings,
+    concurrency: usize,
+) -> Result<(), String> {
+    let pack: Pack = read_toml(&repo_dir.join(PACK_FILE))?;
+    let index_path = repo_dir.join(mrpack::safe_path(&pack.index.file)?);
+    let index: Index = read_toml(&index_path)?;
+    let index_dir = index_path.parent().unwrap_or(repo_dir);
+
+    fs::create_dir_all(output).map_err(|e| format!("Ошибка создания {}: {}", output.display(), e))?;
+
+    let mut wanted = HashSet::new();
+    let mut queue = DownloadQueue::new(concurrency);
+    let mut curseforge_files = Vec::new();
+
+    for entry in &index.files {
+        let relative = mrpack::safe_path(&entry.file)?;
+        // Устанавливаются только моды, остальные файлы сборки остаются в репозитории
+        if !relative.starts_with(MODS_DIR) {
+            continue;
+        }
+
+        let path = index_dir.join(&relative);
+        let hash_format = entry.hash_format.as_deref().unwrap_or(&index.hash_format);
+        let hashes = file_hashes(hash_format, &entry.hash)
+            .map_err(|e| format!("{}: {}", entry.file, e))?;
+        hashes
+            .verify(&path)
+            .map_err(|e| format!("{}: {}", entry.file, e))?;
+
+        if !entry.metafile && !entry.file.ends_with(META_SUFFIX) {
+            // Мод лежит в репозитории как есть
+            let name = file_name(&relative);
+            let target = output.join(&name);
+            if target.exists() {
+                fs::remove_file(&target).map_err(|e| e.to_string())?;
+            }
+            fs::copy(&path, &target).map_err(|e| format!("Ошибка копирования {}: {}", name, e))?;
+            wanted.insert(name);
+            continue;
+        }
+
+        let meta: ModMeta = read_toml(&path)?;
+        if !side_matches(&meta.side, build_type) {
+            continue;
+        }
+
+        // Моды кладутся прямо в папку, вложенные пути не поддерживаются
+        if mrpack::safe_path(&meta.filename)?.components().count() != 1 {
+            return Err(format!("{}: имя файла мода с папкой: {}", entry.file, meta.filename));
+        }
+        let target = output.join(&meta.filename);
+        let hashes = file_hashes(&meta.download.hash_format, &meta.download.hash)
+            .map_err(|e| format!("{}: {}", entry.file, e))?;
+        wanted.insert(meta.filename.clone());
+
+        // Файл уже скачан при прошлой установке
+        if target.exists() && !hashes.is_empty() && hashes.verify(&target).is_ok() {
+            continue;
+        }
+
+        match (&meta.download.url, meta.download.mode.as_deref(), &meta.update.curseforge) {
+            (Some(url), None | Some("url"), _) => queue.push(Download {
+                url: url.clone(),
+                destination: target,
+                hashes,
+            }),
+            (_, Some("metadata:curseforge"), Some(cf)) => {
+                curseforge_files.push(((cf.project_id, cf.file_id), target, hashes));
+            }
+            _ => return Err(format!("{}: неизвестный способ скачивания", entry.file)),
+        }
+    }
+
+    // Ссылки на файлы CurseForge через API
+    let ids: Vec<(u64, u64)> = curseforge_files.iter().map(|(ids, _, _)| *ids).collect();
+    let infos = curseforge::resolve_files(&ids, curseforge_settings, concurrency);
+    for ((ids, target, hashes), info) in curseforge_files.into_iter().zip(infos) {
+        let info = info.map_err(|e| {
+            format!("CurseForge: проект {}, файл {}: {}", ids.0, ids.1, e)
+        })?;
+        queue.push(Download {
+            url: info.download_url,
+            destination: target,
+            hashes,
+        });
+    }
+
+    run_queue(queue)?;
+
+    // Моды, которых больше нет в сборке или которые не нужны этой стороне
+    for entry in fs::read_dir(output).map_err(|e| e.to_string())? {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if !wanted.contains(&file_name(&path)) {
+            fs::remove_file(&path).map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Сборка packwiz по ссылке на `pack.toml` (http(s) или `file://`).
+/// Удалённая сборка сначала копируется в кеш: `pack.toml`, `index.toml`
+/// и файлы из `mods/` с проверкой хешей, затем собирается как из репозитория.
+/// Возвращает папку с модами и версию сборки (хеш индекса).
+pub fn resolve_url(
+    url: &str,
+    build_type: BuildType,
+    curseforge_settings: &CurseForgeSettings,
+    concurrency: usize,
+) -> Result<(PathBuf, String), String> {
+    let pack_dir = match url.strip_prefix("file://") {
+        Some(path) => {
+            let path = PathBuf::from(path);
+            if path.is_dir() {
+                path
+            } else {
+                path.parent().map(Path::to_path_buf).unwrap_or(path)
+            }
+        }
+        None => mirror(url, concurrency)?,
+    };
+
+    if !is_packwiz(&pack_dir) {
+        return Err(format!("В {} нет {}", pack_dir.display(), PACK_FILE));
+    }
+    let pack: Pack = read_toml(&pack_dir.join(PACK_FILE))?;
+    let version = pack.index.hash.or(pack.version).unwrap_or_default();
+
+    let mods = resolve(&pack_dir, build_type, curseforge_settings, concurrency)?;
+    Ok((mods, version))
+}
+
+/// Копия удалённой сборки в кеше: только файлы, нужные для установки модов
+fn mirror(url: &str, concurrency: usize) -> Result<PathBuf, String> {
+    let base = &url[..url.rfind('/').map_or(0, |i| i + 1)];
+    let name = cache::repo_dir(url).file_name().unwrap_or_default().to_string_lossy().to_string();
+    let dir = cache::derived_dir("packwiz-src", &name);
+    fs::create_dir_all(&dir).map_err(|e| format!("Ошибка создания {}: {}", dir.display(), e))?;
+
+    // pack.toml без хеша: по нему узнаём, изменилась ли сборка
+    let mut queue = DownloadQueue::new(concurrency);
+    queue.push(Download {
+        url: url.to_string(),
+        destination: dir.join(PACK_FILE),
+        hashes: FileHashes::default(),
+    });
+    run_queue(queue)?;
+
+    let pack: Pack = read_toml(&dir.join(PACK_FILE))?;
+    let index_path = dir.join(mrpack::safe_path(&pack.index.file)?);
+    let index_hashes = match (&pack.index.hash_format, &pack.index.hash) {
+        (Some(format), Some(hash)) => file_hashes(format, hash)?,
+        _ => FileHashes::default(),
+    };
+    if let Some(parent) = index_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let mut queue = DownloadQueue::new(concurrency);
+    queue.push(Download {
+        url: format!("{}{}", base, pack.index.file),
+        destination: index_path.clone(),
+        hashes: index_hashes,
+    });
+    run_queue(queue)?;
+
+    // Файлы модов: уже скачанные и не изменившиеся пропускаются
+    let index: Index = read_toml(&index_path)?;
+    let index_base = match pack.index.file.rfind('/') {
+        Some(i) => format!("{}{}", base, &pack.index.file[..=i]),
+        None => base.to_string(),
+    };
+    let index_dir = index_path.parent().unwrap_or(&dir);
+    let mut queue = DownloadQueue::new(concurrency);
+    for entry in &index.files {
+        let relative = mrpack::safe_path(&entry.file)?;
+        if !relative.starts_with(MODS_DIR) {
+            continue;
+        }
+
+        let target = index_dir.join(&relative);
+        let hash_format = entry.hash_format.as_deref().unwrap_or(&index.hash_format);
+        let hashes = file_hashes(hash_format, &entry.hash)
+            .map_err(|e| format!("{}: {}", entry.file, e))?;
+        if target.exists() && !hashes.is_empty() && hashes.verify(&target).is_ok() {
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        queue.push(Download {
+            url: format!("{}{}", index_base, entry.file),
+            destination: target,
+            hashes,
+        });
+    }
+    run_queue(queue)?;
+
+    Ok(dir)
+}
+
+/// Запуск очереди скачивания; ошибка, если не скачался хотя бы один файл
+fn run_queue(queue: DownloadQueue) -> Result<(), String> {
+    let failed: Vec<String> = queue
+        .run(&MultiProgress::new())
+        .into_iter()
+        .filter_map(|result| result.err())
+        .collect();
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Не удалось скачать файлы сборки: {}", failed.join("; ")))
+    }
+}
+
+/// Папка с готовыми модами для стороны в `derived` кеша, отдельно от клонов
+fn resolved_dir(repo_dir: &Path, build_type: BuildType) -> PathBuf {
+    let name = repo_dir.file_name().unwrap_or_default().to_string_lossy();
+    let side = match build_type {
+        BuildType::Client => "client",
+        BuildType::Server => "server",
+    };
+    cache::derived_dir("packwiz", &format!("{}-{}", name, side))
+}
+
+/// Нужен ли мод стороне: `both` — всем, `client`/`server` — только своей
+fn side_matches(side: &str, build_type: BuildType) -> bool {
+    !matches!(
+        (side, build_type),
+        ("client", BuildType::Server) | ("server", BuildType::Client)
+    )
+}
+
+/// Хеш в известном формате. md5 и murmur2 проверить нечем — такой файл
+/// не устанавливается, а не ставится без проверки
+fn file_hashes(format: &str, hash: &str) -> Result<FileHashes, String> {
+    let hash = Some(hash.to_lowercase());
+    match format {
+        "sha512" => Ok(FileHashes { sha512: hash, ..FileHashes::default() }),
+        "sha256" => Ok(FileHashes { sha256: hash, ..FileHashes::default() }),
+        "sha1" => Ok(FileHashes { sha1: hash, ..FileHashes::default() }),
+        _ => Err(format!("хеш {} не поддерживается", format)),
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().to_string()
+}
+
+fn read_toml<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Ошибка чтения {}: {}", path.display(), e))?;
+    toml::from_str(&content).map_err(|e| format!("Ошибка разбора {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curseforge::tests::{serve_api, JAR};
+    use sha1::{Digest, Sha1};
+    use sha2::Sha256;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("stm-packwiz-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        root
+    }
+
+    fn write(path: &Path, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn sha256(content: &[u8]) -> String {
+        format!("{:x}", Sha256::digest(content))
+    }
+
+    /// Репозиторий packwiz: `files` — пути и содержимое, все попадают в индекс
+    fn write_repo(repo: &Path, files: &[(&str, String)]) {
+        let mut index = String::from("hash-format = \"sha256\"\n");
+        for (file, content) in files {
+            write(&repo.join(file), content);
+            index.push_str(&format!(
+                "\n[[files]]\nfile = \"{}\"\nhash = \"{}\"\nmetafile = {}\n",
+                file,
+                sha256(content.as_bytes()),
+                file.ends_with(META_SUFFIX)
+            ));
+        }
+        write(&repo.join("index.toml"), &index);
+        write(
+            &repo.join(PACK_FILE),
+            "name = \"Test\"\nversion = \"1.0\"\n\n[index]\nfile = \"index.toml\"\nhash-format = \"sha256\"\nhash = \"00\"\n",
+        );
+    }
+
+    fn url_meta(filename: &str, side: &str, url: &str) -> String {
+        format!(
+            "name = \"{0}\"\nfilename = \"{0}\"\nside = \"{1}\"\n\n[download]\nurl = \"{2}\"\nhash-format = \"sha1\"\nhash = \"{3:x}\"\n",
+            filename,
+            side,
+            url,
+            Sha1::digest(JAR)
+        )
+    }
+
+    fn settings(api_url: String) -> CurseForgeSettings {
+        CurseForgeSettings { api_url, api_key: None }
+    }
+
+    fn names(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir).unwrap().map(|e| file_name(&e.unwrap().path())).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn side_matches_both_and_own_side() {
+        assert!(side_matches("both", BuildType::Client));
+        assert!(side_matches("both", BuildType::Server));
+        assert!(side_matches("client", BuildType::Client));
+        assert!(!side_matches("client", BuildType::Server));
+        assert!(side_matches("server", BuildType::Server));
+        assert!(!side_matches("server", BuildType::Client));
+    }
+
+    #[test]
+    fn unverifiable_hashes_are_rejected() {
+        assert_eq!(file_hashes("sha1", "ABC").unwrap().sha1.as_deref(), Some("abc"));
+        assert!(matches!(file_hashes("md5", "abc"), Err(e) if e.contains("md5")));
+        assert!(file_hashes("murmur2", "123").is_err());
+    }
+
+    #[test]
+    fn resolve_downloads_copies_and_filters_by_side() {
+        let root = temp_root("resolve");
+        let repo = root.join("repo");
+        let output = root.join("out");
+        let api = serve_api();
+        let url = format!("{}/files/a.jar", api);
+
+        write_repo(
+            &repo,
+            &[
+                ("mods/raw.jar", "raw jar".to_string()),
+                ("mods/client.pw.toml", url_meta("client.jar", "client", &url)),
+                ("mods/server.pw.toml", url_meta("server.jar", "server", &url)),
+                (
+                    "mods/cf.pw.toml",
+                    format!(
+                        "name = \"CF\"\nfilename = \"cf.jar\"\n\n[download]\nmode = \"metadata:curseforge\"\nhash-format = \"sha1\"\nhash = \"{:x}\"\n\n[update.curseforge]\nproject-id = 1\nfile-id = 10\n",
+                        Sha1::digest(JAR)
+                    ),
+                ),
+                ("config/test.toml", "enabled = true".to_string()),
+            ],
+        );
+        // Мод, который убрали из сборки
+        write(&output.join("old.jar"), "old");
+
+        resolve_into(&repo, &output, BuildType::Client, &settings(api.clone()), 2).unwrap();
+        assert_eq!(names(&output), ["cf.jar", "client.jar", "raw.jar"]);
+        assert_eq!(fs::read(output.join("cf.jar")).unwrap(), JAR);
+        assert_eq!(fs::read(output.join("client.jar")).unwrap(), JAR);
+        assert_eq!(fs::read_to_string(output.join("raw.jar")).unwrap(), "raw jar");
+
+        resolve_into(&repo, &output, BuildType::Server, &settings(api), 2).unwrap();
+        assert_eq!(names(&output), ["cf.jar", "raw.jar", "server.jar"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn resolve_rejects_bad_metadata() {
+        let root = temp_root("reject");
+        let repo = root.join("repo");
+        let output = root.join("out");
+        let api = serve_api();
+        let url = format!("{}/files/a.jar", api);
+
+        // Имя файла мода с папкой
+        write_repo(&repo, &[("mods/nested.pw.toml", url_meta("sub/nested.jar", "both", &url))]);
+        let error = resolve_into(&repo, &output, BuildType::Client, &settings(api.clone()), 1).unwrap_err();
+        assert!(error.contains("sub/nested.jar"), "{}", error);
+
+        // Хеш, который нечем проверить
+        let md5 = url_meta("md5.jar", "both", &url).replace("\"sha1\"", "\"md5\"");
+        write_repo(&repo, &[("mods/md5.pw.toml", md5)]);
+        let error = resolve_into(&repo, &output, BuildType::Client, &settings(api.clone()), 1).unwrap_err();
+        assert!(error.contains("md5"), "{}", error);
+        assert!(!output.join("md5.jar").exists());
+
+        // Файл в репозитории не совпадает с хешем индекса
+        write_repo(&repo, &[("mods/raw.jar", "raw jar".to_string())]);
+        fs::write(repo.join("mods/raw.jar"), "changed").unwrap();
+        assert!(resolve_into(&repo, &output, BuildType::Client, &settings(api), 1).is_err());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::manifest::{self, Manifest};
+use crate::mods;
+
+/// Что произойдёт с файлом при установке
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    /// Новый мод сборки
+    Add,
+    /// Мод с тем же именем, но другим содержимым
+    Replace,
+    /// Мод сборки, которого больше нет в сборке
+    Remove,
+    /// Файл остаётся как есть
+    Keep,
+    /// Мод с `&` в начале имени — сборка его не устанавливает
+    Skip,
+}
+
+/// Один файл в плане установки
+pub struct PlanEntry {
+    pub file_name: String,
+    pub action: Action,
+    /// Файл сборки, из которого копировать (нет у чужих модов и удаляемых файлов)
+    pub source_path: Option<PathBuf>,
+    /// Размер файла, который окажется (или уже лежит) в папке mods
+    pub size: u64,
+    /// SHA-256 файла сборки
+    pub sha256: Option<String>,
+}
+
+/// План установки сборки: что добавить, заменить, удалить и оставить.
+/// Сначала показывается пользователю, затем по нему же выполняется установка.
+pub struct InstallPlan {
+    pub entries: Vec<PlanEntry>,
+}
+
+impl InstallPlan {
+    /// Сравнение модов сборки в `repo_dir` с папкой модов.
+    /// При `clean_install` все моды сборки копируются заново.
+    pub fn build(
+        repo_dir: &Path,
+        mods_dir: &Path,
+        manifest: &Manifest,
+        clean_install: bool,
+    ) -> io::Result<Self> {
+        let mut entries = Vec::new();
+        let mut pack_names = HashSet::new();
+
+        for source_path in mods::pack_jars(repo_dir)? {
+            let file_name = match source_path.file_name() {
+                Some(name) => name.to_string_lossy().to_string(),
+                None => continue,
+            };
+            let size = fs::metadata(&source_path)?.len();
+
+            if file_name.starts_with('&') {
+                entries.push(PlanEntry {
+                    file_name,
+                    action: Action::Skip,
+                    source_path: None,
+                    size,
+                    sha256: None,
+                });
+                continue;
+            }
+
+            let sha256 = manifest::sha256_file(&source_path)?;
+            let target = mods_dir.join(&file_name);
+
+            let action = if !target.exists() {
+                Action::Add
+            } else if clean_install || manifest::sha256_file(&target)? != sha256 {
+                Action::Replace
+            } else {
+                Action::Keep
+            };
+
+            pack_names.insert(file_name.clone());
+            entries.push(PlanEntry {
+                file_name,
+                action,
+                source_path: Some(source_path),
+                size,
+                sha256: Some(sha256),
+            });
+        }
+
+        // Моды сборки из манифеста, которых больше нет в сборке
+        push_stale(mods_dir, manifest, &mut pack_names, &mut entries);
+        push_foreign(mods_dir, &pack_names, &mut entries)?;
+
+        entries.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+        Ok(InstallPlan { entries })
+    }
+
+    /// План удаления всех модов сборки из манифеста
+    pub fn clean(mods_dir: &Path, manifest: &Manifest) -> io::Result<Self> {
+        let mut entries = Vec::new();
+        let mut pack_names = HashSet::new();
+
+        push_stale(mods_dir, manifest, &mut pack_names, &mut entries);
+        push_foreign(mods_dir, &pack_names, &mut entries)?;
+
+        entries.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+        Ok(InstallPlan { entries })
+    }
+
+    /// Файлы с нужным действием
+    pub fn with_action(&self, action: Action) -> impl Iterator<Item = &PlanEntry> {
+        self.entries.iter().filter(move |e| e.action == action)
+    }
+
+    /// Сколько байт будет скопировано в папку модов
+    pub fn download_bytes(&self) -> u64 {
+        self.entries
+            .iter()
+            .filter(|e| matches!(e.action, Action::Add | Action::Replace))
+            .map(|e| e.size)
+            .sum()
+    }
+
+    /// Размер папки модов после установки
+    pub fn disk_bytes(&self) -> u64 {
+        self.entries
+            .iter()
+            .filter(|e| matches!(e.action, Action::Add | Action::Replace | Action::Keep))
+            .map(|e| e.size)
+            .sum()
+    }
+
+    /// Есть ли что записывать или удалять
+    pub fn has_changes(&self) -> bool {
+        self.entries
+            .iter()
+            .any(|e| matches!(e.action, Action::Add | Action::Replace | Action::Remove))
+    }
+}
+
+/// Удаление модов сборки из манифеста, которых нет среди `pack_names`
+fn push_stale(
+    mods_dir: &Path,
+    manifest: &Manifest,
+    pack_names: &mut HashSet<String>,
+    entries: &mut Vec<PlanEntry>,
+) {
+    for file in manifest.pack_files() {
+        if pack_names.contains(&file.file_name) {
+            continue;
+        }
+
+        let size = fs::metadata(mods_dir.join(&file.file_name))
+            .map(|m| m.len())
+            .unwrap_or(0);
+        pack_names.insert(file.file_name.clone());
+        entries.push(PlanEntry {
+            file_name: file.file_name.clone(),
+            action: Action::Remove,
+            source_path: None,
+            size,
+            sha256: None,
+        });
+    }
+}
+
+/// Всё остальное в папке модов — моды игрока, их не трогаем
+fn push_foreign(
+    mods_dir: &Path,
+    pack_names: &HashSet<String>,
+    entries: &mut Vec<PlanEntry>,
+) -> io::Result<()> {
+    if !mods_dir.exists() {
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(mods_dir)? {
+        let path = entry?.path();
+        let file_name = match path.file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => continue,
+        };
+
+        if !path.is_file() || !file_name.ends_with(".jar") || pack_names.contains(&file_name) {
+            continue;
+        }
+
+        entries.push(PlanEntry {
+            file_name,
+            action: Action::Keep,
+            source_path: None,
+            size: fs::metadata(&path)?.len(),
+            sha256: None,
+        });
+    }
+
+    Ok(())
+}
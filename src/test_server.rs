@@ -0,0 +1,71 @@
+//! Локальный HTTP-сервер для тестов: подмена API CurseForge и файлов для скачивания
+
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+/// Ответ на один запрос
+pub struct Reply {
+    pub status: &'static str,
+    pub body: Vec<u8>,
+    /// Отдать тело и замолчать, не закрывая соединение: сервер «завис» посреди ответа
+    pub stall: bool,
+}
+
+impl Reply {
+    pub fn ok(body: impl Into<Vec<u8>>) -> Self {
+        Reply { status: "200 OK", body: body.into(), stall: false }
+    }
+
+    pub fn not_found() -> Self {
+        Reply { status: "404 Not Found", body: Vec::new(), stall: false }
+    }
+}
+
+/// Запуск сервера на свободном порту; `handler` получает адрес сервера
+/// (для ссылок в ответах) и путь запроса.
+/// Возвращает адрес вида `http://127.0.0.1:<порт>`
+pub fn serve(handler: impl Fn(&str, &str) -> Reply + Send + Sync + 'static) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    let handler = std::sync::Arc::new(handler);
+
+    let address = base.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let handler = handler.clone();
+            let address = address.clone();
+            thread::spawn(move || respond(stream, |path| handler(&address, path)));
+        }
+    });
+
+    base
+}
+
+fn respond(mut stream: TcpStream, handler: impl Fn(&str) -> Reply) {
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        match stream.read(&mut buf) {
+            Ok(0) | Err(_) => return,
+            Ok(n) => request.extend_from_slice(&buf[..n]),
+        }
+    }
+    let request = String::from_utf8_lossy(&request);
+    let path = request.split_whitespace().nth(1).unwrap_or_default();
+
+    let reply = handler(path);
+    // Зависший ответ обещает больше, чем отдаёт
+    let length = if reply.stall { reply.body.len() + 1024 } else { reply.body.len() };
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        reply.status, length
+    );
+    let _ = stream.write_all(head.as_bytes());
+    let _ = stream.write_all(&reply.body);
+    let _ = stream.flush();
+    if reply.stall {
+        thread::sleep(Duration::from_secs(60));
+    }
+}
@@ -1,10 +1,20 @@
 use inquire::{Select, Confirm, Text};
 use std::path::{Path, PathBuf};
 use std::fs;
-use dirs;
 use walkdir::WalkDir;
 use console::Term;
 
+use crate::backup;
+use crate::cache::JarChange;
+use crate::config::{Config, Instance, PackSource, SourceKind};
+use crate::duplicates::{self, Duplicate, Keep};
+use crate::git_ops::BuildType;
+use crate::inventory::{self, Filter, InstalledMod};
+use crate::launchers;
+use crate::modrinth;
+use crate::plan::{Action, InstallPlan};
+use crate::mods;
+
 /// Вывод баннера приложения
 pub fn print_banner() {
     println!(r#"
@@ -20,15 +30,32 @@ StoryTime Hub - Лаунчер для Minecraft
 }
 
 /// Отображение главного меню
-pub fn main_menu() -> Option<String> {
+pub fn main_menu(dry_run: bool) -> Option<String> {
     let term = Term::stdout();
     let _ = term.clear_screen();
     print_banner();
     
+    let dry_run_toggle = if dry_run {
+        "󰙨 Пробный запуск: вкл"
+    } else {
+        "󰙨 Пробный запуск: выкл"
+    };
+
     let options = vec![
         "󰆽 Установить моды",
         "󱂵 Переустановить моды",
+        "󰆴 Удалить моды сборки",
+        "󰕌 Откатить моды к снимку",
+        "󰋗 Мои моды",
         "󰚨 Загрузить моды с Modrinth",
+        "󰚰 Проверить обновления модов",
+        "󰏗 Установить сборку .mrpack",
+        "󰏗 Экспортировать в .mrpack",
+        "󰏗 Импортировать сборку CurseForge",
+        "󰘬 Сменить канал обновлений",
+        "󰒓 Источники сборок",
+        "󰆍 Экземпляры",
+        dry_run_toggle,
         "󰒓 Установить папку по умолчанию",
         "󰅖 Выйти",
     ];
@@ -56,7 +83,7 @@ pub fn ask_minecraft_folder() -> Option<PathBuf> {
         .ok()?;
 
     match choice {
-        "󰝚 Автоматический поиск (глубокий поиск)" => find_minecraft_linux(),
+        "󰝚 Автоматический поиск (глубокий поиск)" => find_minecraft_folders(),
         "󰒓 Ввести путь вручную" => ask_path_manual(),
         _ => None,
     }
@@ -85,70 +112,272 @@ pub fn ask_minecraft_folder_with_default(default_path: Option<&PathBuf>) -> Opti
     ask_minecraft_folder()
 }
 
-/// Глубокий поиск папок Minecraft на Linux
-fn find_minecraft_linux() -> Option<PathBuf> {
+/// Папка, выбранная в меню
+pub enum GameFolder {
+    /// Сохранённый экземпляр: папка модов уже известна
+    Instance(PathBuf),
+    /// Папка Minecraft: папку модов ещё нужно выбрать
+    Minecraft(PathBuf),
+}
+
+impl GameFolder {
+    /// Папка игры, рядом с которой лежат файлы лаунчера
+    pub fn game_dir(&self) -> &Path {
+        match self {
+            GameFolder::Instance(mods_path) => mods_path.parent().unwrap_or(mods_path),
+            GameFolder::Minecraft(path) => path,
+        }
+    }
+
+    /// Папка модов: у экземпляра — сохранённая, иначе выбор среди найденных
+    pub fn mods_path(&self) -> Option<PathBuf> {
+        match self {
+            GameFolder::Instance(mods_path) => Some(mods_path.clone()),
+            GameFolder::Minecraft(path) => select_instance(path),
+        }
+    }
+
+    /// Папка модов без вопросов: сохранённая или первая найденная
+    pub fn default_mods_path(&self) -> PathBuf {
+        match self {
+            GameFolder::Instance(mods_path) => mods_path.clone(),
+            GameFolder::Minecraft(path) => mods::default_mods_dir(path),
+        }
+    }
+}
+
+/// Запрос папки Minecraft: сохранённые экземпляры, а если их нет или
+/// нужна другая папка — папка по умолчанию и поиск
+pub fn ask_game_folder(config: &Config) -> Option<GameFolder> {
+    const OTHER: &str = "󰝚 Другая папка...";
+
+    if !config.instances.is_empty() {
+        let term = Term::stdout();
+        let _ = term.clear_screen();
+        print_banner();
+
+        let mut options: Vec<String> = config.instances.iter()
+            .map(|instance| format!("󰆍 {}", format_instance(instance)))
+            .collect();
+        options.push(OTHER.to_string());
+
+        let choice = Select::new("󰝚 Выберите экземпляр:", options.clone())
+            .with_page_size(15)
+            .prompt()
+            .ok()?;
+
+        if let Some(index) = options.iter().position(|option| *option == choice && choice != OTHER) {
+            return Some(GameFolder::Instance(config.instances[index].mods_path()));
+        }
+    }
+
+    let path = match config.get_default_path() {
+        Some(default_path) => ask_minecraft_folder_with_default(Some(&default_path)),
+        None => ask_minecraft_folder(),
+    };
+    path.map(GameFolder::Minecraft)
+}
+
+/// Строка экземпляра для списков: имя, папка модов, версия игры и сборка
+pub fn format_instance(instance: &Instance) -> String {
+    let mut line = format!("{} — {}", instance.name, instance.mods_path().display());
+    if let Some(game_version) = &instance.game_version {
+        line.push_str(&format!(" · {}", game_version));
+        if let Some(loader) = &instance.loader {
+            line.push_str(&format!(" {}", loader));
+        }
+    }
+    if let Some(source) = &instance.source {
+        line.push_str(&format!(" · {}", source));
+    }
+    line
+}
+
+/// Добавление, изменение и удаление сохранённых экземпляров
+pub fn manage_instances(config: &mut Config) {
+    const ADD: &str = "󰐕 Добавить экземпляр";
+    const BACK: &str = "󰌍 Назад";
+
+    loop {
+        let term = Term::stdout();
+        let _ = term.clear_screen();
+        print_banner();
+
+        let mut options: Vec<String> = config.instances.iter()
+            .map(|instance| format!("󰆍 {}", format_instance(instance)))
+            .collect();
+        options.push(ADD.to_string());
+        options.push(BACK.to_string());
+
+        let choice = match Select::new("󰝚 Экземпляры:", options.clone()).with_page_size(15).prompt() {
+            Ok(choice) if choice != BACK => choice,
+            _ => return,
+        };
+
+        let result = if choice == ADD {
+            let mods_path = match ask_minecraft_folder().and_then(|path| select_instance(&path)) {
+                Some(path) => path,
+                None => continue,
+            };
+            let mut instance = Instance::from_mods_path(&mods_path);
+            if let Some(found) = mods_path.parent().and_then(launchers::identify) {
+                instance.name = found.name;
+            }
+            match ask_instance(instance, &config.sources) {
+                Some(instance) => config.add_instance(instance),
+                None => continue,
+            }
+        } else {
+            let index = match options.iter().position(|option| *option == choice) {
+                Some(index) => index,
+                None => continue,
+            };
+            let existing = config.instances[index].clone();
+
+            let action = Select::new(
+                &format!("󰝚 {}:", existing.name),
+                vec!["󰏫 Изменить", "󰆴 Удалить", BACK],
+            )
+            .prompt();
+
+            match action {
+                Ok("󰏫 Изменить") => match ask_instance(existing.clone(), &config.sources) {
+                    Some(instance) => config.update_instance(&existing.name, instance),
+                    None => continue,
+                },
+                Ok("󰆴 Удалить") => {
+                    let confirmed = Confirm::new(&format!(
+                        "󰝚 Забыть экземпляр {}? Файлы останутся на месте",
+                        existing.name
+                    ))
+                    .with_default(false)
+                    .prompt()
+                    .unwrap_or(false);
+                    if !confirmed {
+                        continue;
+                    }
+                    config.remove_instance(&existing.name)
+                }
+                _ => continue,
+            }
+        };
+
+        if let Err(e) = result {
+            println!("󰅖 {}", e);
+            println!("󰝚 Нажмите Enter чтобы продолжить...");
+            let _ = std::io::stdin().read_line(&mut String::new());
+        }
+    }
+}
+
+/// Запрос имени, сборки, версии игры и лоадера экземпляра
+fn ask_instance(mut instance: Instance, sources: &[PackSource]) -> Option<Instance> {
+    const NO_SOURCE: &str = "󰅖 Без сборки";
+
+    let name = Text::new("󰝚 Имя экземпляра:")
+        .with_default(&instance.name)
+        .prompt()
+        .ok()?;
+    let name = name.trim();
+    if name.is_empty() {
+        println!("󰅖 Имя не может быть пустым");
+        return None;
+    }
+    instance.name = name.to_string();
+
+    let mut options = vec![NO_SOURCE.to_string()];
+    options.extend(sources.iter().map(format_source));
+    let current = instance.source
+        .as_ref()
+        .and_then(|name| sources.iter().position(|s| s.name == *name))
+        .map_or(0, |i| i + 1);
+    let choice = Select::new("󰝚 Сборка, которой следует экземпляр:", options.clone())
+        .with_starting_cursor(current)
+        .prompt()
+        .ok()?;
+    match options.iter().position(|option| *option == choice) {
+        Some(index) if index > 0 => {
+            let source = &sources[index - 1];
+            instance.source = Some(source.name.clone());
+            instance.build_type = Some(source.side);
+        }
+        _ => {
+            instance.source = None;
+            instance.build_type = None;
+        }
+    }
+
+    let game_version = Text::new("󰝚 Версия Minecraft (пусто — не указана):")
+        .with_default(instance.game_version.as_deref().unwrap_or(""))
+        .prompt()
+        .ok()?;
+    instance.game_version = Some(game_version.trim().to_string()).filter(|v| !v.is_empty());
+
+    const NO_LOADER: &str = "Не указан";
+    let mut loaders = vec![NO_LOADER];
+    loaders.extend(modrinth::LOADERS);
+    let current = instance.loader
+        .as_deref()
+        .and_then(|loader| loaders.iter().position(|known| *known == loader))
+        .unwrap_or(0);
+    let loader = Select::new("󰝚 Лоадер:", loaders)
+        .with_starting_cursor(current)
+        .prompt()
+        .ok()?;
+    instance.loader = Some(loader.to_string()).filter(|_| loader != NO_LOADER);
+
+    Some(instance)
+}
+
+/// Поиск папок Minecraft: экземпляры известных лаунчеров,
+/// затем глубокий поиск в обычных местах
+fn find_minecraft_folders() -> Option<PathBuf> {
     println!("󰇚 Ищу папки Minecraft...");
-    
-    let mut found_folders = Vec::new();
-    
-    // Стандартные пути для различных лаунчеров на Linux
-    let search_paths = get_linux_search_paths();
-    
-    for base_path in search_paths {
+
+    let instances = launchers::discover();
+    let launcher_roots: Vec<PathBuf> = launchers::adapters()
+        .iter()
+        .flat_map(|adapter| adapter.data_roots())
+        .collect();
+
+    let mut other_folders = Vec::new();
+    for base_path in get_search_paths() {
         if base_path.exists() {
-            find_minecraft_in_directory(&base_path, &mut found_folders);
+            find_minecraft_in_directory(&base_path, &mut other_folders);
         }
     }
-    
-    if found_folders.is_empty() {
+
+    // Папки лаунчеров уже показаны как их экземпляры
+    other_folders.retain(|path| {
+        !launcher_roots.iter().any(|root| path.starts_with(root))
+            && !instances.iter().any(|i| path.starts_with(&i.instance_dir) || *path == i.game_dir)
+    });
+
+    if instances.is_empty() && other_folders.is_empty() {
         println!("󰅖 Папки Minecraft не найдены");
         return ask_path_manual();
     }
-    
-    // Сортируем по приоритету
-    found_folders.sort_by(|a, b| {
-        let a_priority = get_path_priority(a);
-        let b_priority = get_path_priority(b);
-        b_priority.cmp(&a_priority)
-    });
-    
-    // Удаляем дубликаты
-    found_folders.dedup();
-    
-    println!("󰄬 Найдено {} папок Minecraft", found_folders.len());
-    
+
+    println!("󰄬 Найдено {} папок Minecraft", instances.len() + other_folders.len());
+
     // Предлагаем выбор пользователю
-    select_folder_from_list(&found_folders)
+    let mut folders: Vec<(String, PathBuf)> = instances
+        .into_iter()
+        .map(|i| (format!("󰆍 {}", i.label()), i.game_dir))
+        .collect();
+    folders.extend(
+        other_folders
+            .into_iter()
+            .map(|path| (format!("󰉋 {}", simplify_path_display(&path)), path)),
+    );
+    select_folder_from_list(&folders)
 }
 
-/// Получение путей для поиска на Linux
-fn get_linux_search_paths() -> Vec<PathBuf> {
+/// Обычные места, где может лежать папка Minecraft вне лаунчеров
+fn get_search_paths() -> Vec<PathBuf> {
     let mut paths = Vec::new();
     
     if let Some(home) = dirs::home_dir() {
-        // Официальный лаунчер
-        paths.push(home.join(".minecraft"));
-        
-        // Prism Launcher
-        paths.push(home.join(".local/share/PrismLauncher/instances"));
-        paths.push(home.join(".var/app/org.prismlauncher.PrismLauncher/data/PrismLauncher/instances"));
-        
-        // MultiMC
-        paths.push(home.join(".local/share/multimc/instances"));
-        
-        // ATLauncher
-        paths.push(home.join(".local/share/atlutut/instances"));
-        
-        // CurseForge
-        paths.push(home.join(".local/share/curseforge/minecraft/Instances"));
-        
-        // GDLauncher
-        paths.push(home.join(".local/share/gdlauncher/instances"));
-        
-        // TLauncher
-        paths.push(home.join(".local/share/tlauncher/instances"));
-        paths.push(home.join(".tlauncher"));
-        
         // Просто папка .minecraft в разных местах
         paths.push(home.clone());
         paths.push(home.join("Games"));
@@ -228,71 +457,27 @@ fn is_minecraft_folder(path: &Path) -> bool {
     is_named_minecraft || has_minecraft_content
 }
 
-/// Приоритет для сортировки путей
-fn get_path_priority(path: &Path) -> i32 {
-    let path_str = path.display().to_string();
-    
-    if path_str.contains(".minecraft") { 100 }
-    else if path_str.contains("PrismLauncher") { 90 }
-    else if path_str.contains("multimc") { 80 }
-    else if path_str.contains("tlauncher") { 70 }
-    else if path_str.contains("curseforge") { 60 }
-    else if path_str.contains("instances") { 50 }
-    else if path.join("mods").exists() { 40 }
-    else { 10 }
-}
-
-/// Выбор папки из списка найденных
-fn select_folder_from_list(folders: &[PathBuf]) -> Option<PathBuf> {
-    let mut options: Vec<String> = folders.iter()
-        .enumerate()
-        .map(|(i, path)| {
-            let display_path = simplify_path_display(path);
-            let launcher = detect_launcher_type(path);
-            format!("{}. {} [{}]", i + 1, display_path, launcher)
-        })
-        .collect();
-    
-    options.push("󰒓 Ввести путь вручную".to_string());
-    options.push("󰅖 Отмена".to_string());
+/// Выбор папки из списка найденных: (подпись, путь)
+fn select_folder_from_list(folders: &[(String, PathBuf)]) -> Option<PathBuf> {
+    const MANUAL: &str = "󰒓 Ввести путь вручную";
+    const CANCEL: &str = "󰅖 Отмена";
+
+    let mut options: Vec<String> = folders.iter().map(|(label, _)| label.clone()).collect();
+    options.push(MANUAL.to_string());
+    options.push(CANCEL.to_string());
     
     let choice = Select::new("󰝚 Выберите папку Minecraft:", options)
         .with_page_size(15)
         .prompt()
         .ok()?;
     
-    if choice == "󰒓 Ввести путь вручную" {
+    if choice == MANUAL {
         return ask_path_manual();
     }
     
-    if choice == "󰅖 Отмена" {
-        return None;
-    }
-    
-    // Извлекаем индекс из выбора
-    if let Some(index_str) = choice.split('.').next() {
-        if let Ok(index) = index_str.parse::<usize>() {
-            if index > 0 && index <= folders.len() {
-                return Some(folders[index - 1].clone());
-            }
-        }
-    }
-    
-    None
-}
-
-/// Определение типа лаунчера по пути
-fn detect_launcher_type(path: &Path) -> &'static str {
-    let path_str = path.display().to_string();
-    
-    if path_str.contains("PrismLauncher") { "Prism" }
-    else if path_str.contains("multimc") { "MultiMC" }
-    else if path_str.contains("tlauncher") { "TLauncher" }
-    else if path_str.contains("curseforge") { "CurseForge" }
-    else if path_str.contains("atlutut") { "ATLauncher" }
-    else if path_str.contains("gdlauncher") { "GDLauncher" }
-    else if path_str.contains(".minecraft") { "Официальный" }
-    else { "Неизвестно" }
+    folders.iter()
+        .find(|(label, _)| *label == choice)
+        .map(|(_, path)| path.clone())
 }
 
 /// Упрощение отображения пути
@@ -325,7 +510,7 @@ fn ask_path_manual() -> Option<PathBuf> {
     
     if path_buf.exists() && path_buf.is_dir() {
         if is_minecraft_folder(&path_buf) {
-            return Some(path_buf);
+            Some(path_buf)
         } else {
             let use_anyway = Confirm::new("󰝚 Эта папка не похожа на папку Minecraft. Использовать её?")
                 .with_default(false)
@@ -333,9 +518,9 @@ fn ask_path_manual() -> Option<PathBuf> {
                 .unwrap_or(false);
             
             if use_anyway {
-                return Some(path_buf);
+                Some(path_buf)
             } else {
-                return ask_path_manual();
+                ask_path_manual()
             }
         }
     } else {
@@ -349,37 +534,36 @@ fn ask_path_manual() -> Option<PathBuf> {
                 println!("󰅖 Ошибка создания папки: {}", e);
                 return ask_path_manual();
             }
-            return Some(path_buf);
+            Some(path_buf)
         } else {
-            return ask_path_manual();
+            ask_path_manual()
         }
     }
 }
 
-/// Выбор экземпляра/папки для установки модов
+/// Выбор экземпляра/папки для установки модов.
+/// Экземпляры лаунчеров показываются по именам: «Prism › StoryTime S3».
 pub fn select_instance(minecraft_path: &Path) -> Option<PathBuf> {
     let term = Term::stdout();
     let _ = term.clear_screen();
     print_banner();
     
-    // Возможные пути к папкам с модами
-    let possible_mods_paths = vec![
-        minecraft_path.join("mods"),
-        minecraft_path.join("minecraft").join("mods"),
-        minecraft_path.join(".minecraft").join("mods"),
-    ];
-    
-    let mut available_paths = Vec::new();
-    
-    // Проверяем каждый возможный путь
-    for path in possible_mods_paths {
-        if path.exists() {
-            available_paths.push(path.clone());
+    // Экземпляры лаунчеров внутри выбранной папки (например, папки данных Prism)
+    let mut available: Vec<(String, PathBuf)> = launchers::discover()
+        .into_iter()
+        .filter(|i| i.game_dir.starts_with(minecraft_path) || i.instance_dir == minecraft_path)
+        .map(|i| (format!("󰆍 {}", i.label()), i.game_dir.join("mods")))
+        .collect();
+
+    // Существующие папки с модами, не принадлежащие найденным экземплярам
+    for path in mods::find_mods_dirs(minecraft_path) {
+        if !available.iter().any(|(_, known)| *known == path) {
+            available.push((format!("󰝚 {}", path.display()), path));
         }
     }
     
     // Если ничего не найдено, предлагаем создать папку mods
-    if available_paths.is_empty() {
+    if available.is_empty() {
         let mods_path = minecraft_path.join("mods");
         let create_mods = Confirm::new("󰝚 Папка mods не найдена. Создать в текущей директории?")
             .with_default(true)
@@ -387,7 +571,7 @@ pub fn select_instance(minecraft_path: &Path) -> Option<PathBuf> {
             .unwrap_or(false);
         
         if create_mods {
-            if let Err(_) = fs::create_dir_all(&mods_path) {
+            if fs::create_dir_all(&mods_path).is_err() {
                 return None;
             }
             return Some(mods_path);
@@ -396,30 +580,25 @@ pub fn select_instance(minecraft_path: &Path) -> Option<PathBuf> {
     }
     
     // Если только один вариант, выбираем его
-    if available_paths.len() == 1 {
-        return Some(available_paths[0].clone());
+    if available.len() == 1 {
+        return Some(available[0].1.clone());
     }
     
     // Предлагаем выбор пользователю
-    let options: Vec<String> = available_paths.iter()
-        .map(|path| format!("󰝚 {}", path.display()))
-        .collect();
+    let options: Vec<String> = available.iter().map(|(label, _)| label.clone()).collect();
     
     let choice = Select::new("󰝚 Выберите папку для установки модов:", options)
+        .with_page_size(15)
         .prompt()
         .ok()?;
     
-    for path in &available_paths {
-        if format!("󰝚 {}", path.display()) == choice {
-            return Some(path.clone());
-        }
-    }
-    
-    None
+    available.into_iter()
+        .find(|(label, _)| *label == choice)
+        .map(|(_, path)| path)
 }
 
 /// Выбор типа сборки (клиентская или серверная)
-pub fn select_build_type() -> Option<String> {
+pub fn select_build_type() -> Option<BuildType> {
     let term = Term::stdout();
     let _ = term.clear_screen();
     print_banner();
@@ -429,8 +608,436 @@ pub fn select_build_type() -> Option<String> {
         "󰑓 Серверная сборка",
     ];
 
-    Select::new("󰝚 Выберите тип сборки для установки:", options)
+    let choice = Select::new("󰝚 Выберите тип сборки для установки:", options)
         .prompt()
-        .ok()
-        .map(|s| s.to_string())
-}
\ No newline at end of file
+        .ok()?;
+
+    if choice == "󰌌 Клиентская сборка" {
+        Some(BuildType::Client)
+    } else {
+        Some(BuildType::Server)
+    }
+}
+
+/// Строка источника для списков: имя, формат, сторона и адрес
+pub fn format_source(source: &PackSource) -> String {
+    let side = match source.side {
+        BuildType::Client => "клиент",
+        BuildType::Server => "сервер",
+    };
+    let branch = source.branch
+        .as_ref()
+        .map(|b| format!(" @{}", b))
+        .unwrap_or_default();
+    format!("{} ({}, {}) — {}{}", source.name, source.kind.label(), side, source.url, branch)
+}
+
+/// Выбор источника сборки
+pub fn select_source(sources: &[PackSource]) -> Option<PackSource> {
+    let term = Term::stdout();
+    let _ = term.clear_screen();
+    print_banner();
+
+    if sources.is_empty() {
+        println!("󰅖 Нет источников сборок. Добавьте источник в меню «Источники сборок»");
+        return None;
+    }
+
+    let options: Vec<String> = sources.iter()
+        .map(|source| match source.side {
+            BuildType::Client => format!("󰌌 {}", format_source(source)),
+            BuildType::Server => format!("󰑓 {}", format_source(source)),
+        })
+        .collect();
+
+    let choice = Select::new("󰝚 Выберите сборку для установки:", options.clone())
+        .prompt()
+        .ok()?;
+
+    options.iter()
+        .position(|option| *option == choice)
+        .map(|i| sources[i].clone())
+}
+
+/// Добавление, изменение и удаление источников сборок
+pub fn manage_sources(config: &mut Config) {
+    const ADD: &str = "󰐕 Добавить источник";
+    const BACK: &str = "󰌍 Назад";
+
+    loop {
+        let term = Term::stdout();
+        let _ = term.clear_screen();
+        print_banner();
+
+        let mut options: Vec<String> = config.sources.iter()
+            .map(|source| format!("󰒓 {}", format_source(source)))
+            .collect();
+        options.push(ADD.to_string());
+        options.push(BACK.to_string());
+
+        let choice = match Select::new("󰝚 Источники сборок:", options.clone()).prompt() {
+            Ok(choice) if choice != BACK => choice,
+            _ => return,
+        };
+
+        let result = if choice == ADD {
+            match ask_source(None) {
+                Some(source) => config.add_source(source),
+                None => continue,
+            }
+        } else {
+            let index = match options.iter().position(|option| *option == choice) {
+                Some(index) => index,
+                None => continue,
+            };
+            let existing = config.sources[index].clone();
+
+            let action = Select::new(
+                &format!("󰝚 {}:", existing.name),
+                vec!["󰏫 Изменить", "󰆴 Удалить", BACK],
+            )
+            .prompt();
+
+            match action {
+                Ok("󰏫 Изменить") => match ask_source(Some(&existing)) {
+                    Some(source) => config.update_source(&existing.name, source),
+                    None => continue,
+                },
+                Ok("󰆴 Удалить") => {
+                    let confirmed = Confirm::new(&format!("󰝚 Удалить источник {}?", existing.name))
+                        .with_default(false)
+                        .prompt()
+                        .unwrap_or(false);
+                    if !confirmed {
+                        continue;
+                    }
+                    config.remove_source(&existing.name)
+                }
+                _ => continue,
+            }
+        };
+
+        if let Err(e) = result {
+            println!("󰅖 {}", e);
+            println!("󰝚 Нажмите Enter чтобы продолжить...");
+            let _ = std::io::stdin().read_line(&mut String::new());
+        }
+    }
+}
+
+/// Запрос полей источника; `existing` подставляется как значения по умолчанию
+fn ask_source(existing: Option<&PackSource>) -> Option<PackSource> {
+    let name = Text::new("󰝚 Имя источника:")
+        .with_default(existing.map(|s| s.name.as_str()).unwrap_or(""))
+        .prompt()
+        .ok()?;
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        println!("󰅖 Имя не может быть пустым");
+        return None;
+    }
+
+    let url = Text::new("󰝚 Адрес:")
+        .with_default(existing.map(|s| s.url.as_str()).unwrap_or(""))
+        .with_help_message("git-репозиторий, ссылка на pack.toml или .mrpack; можно file://")
+        .prompt()
+        .ok()?;
+    let url = url.trim().to_string();
+    if url.is_empty() {
+        println!("󰅖 Адрес не может быть пустым");
+        return None;
+    }
+
+    let kinds = [SourceKind::Git, SourceKind::Manifest, SourceKind::Mrpack];
+    let current_kind = existing.map(|s| s.kind).unwrap_or_default();
+    let kind = Select::new("󰝚 Формат источника:", kinds.iter().map(|k| k.label()).collect())
+        .with_starting_cursor(kinds.iter().position(|k| *k == current_kind).unwrap_or(0))
+        .prompt()
+        .ok()?;
+    let kind = kinds.into_iter().find(|k| k.label() == kind)?;
+
+    let branch = if kind == SourceKind::Git {
+        let branch = Text::new("󰝚 Ветка (пусто — ветка по умолчанию):")
+            .with_default(existing.and_then(|s| s.branch.as_deref()).unwrap_or(""))
+            .prompt()
+            .ok()?;
+        let branch = branch.trim();
+        (!branch.is_empty()).then(|| branch.to_string())
+    } else {
+        None
+    };
+
+    let side = select_build_type()?;
+
+    Some(PackSource { name, url, kind, branch, side })
+}
+
+/// Выбор версии сборки.
+/// Возвращает `Some(None)` для последней версии, `Some(Some(ref))` для закреплённой
+/// и `None` при отмене.
+pub fn select_git_ref(tags: &[String], pinned: Option<&str>) -> Option<Option<String>> {
+    let term = Term::stdout();
+    let _ = term.clear_screen();
+    print_banner();
+
+    const LATEST: &str = "󰚰 Последняя версия";
+    const MANUAL: &str = "󰒓 Ввести тег, ветку или коммит";
+
+    let mut options = Vec::new();
+    if let Some(pinned) = pinned {
+        options.push(format!("󰐃 Оставить закреплённую: {}", pinned));
+    }
+    options.push(LATEST.to_string());
+    options.extend(tags.iter().map(|tag| format!("󰓹 {}", tag)));
+    options.push(MANUAL.to_string());
+
+    let choice = Select::new("󰝚 Выберите версию сборки:", options)
+        .with_page_size(15)
+        .prompt()
+        .ok()?;
+
+    if choice == LATEST {
+        return Some(None);
+    }
+
+    if choice == MANUAL {
+        let git_ref = Text::new("󰝚 Введите тег, ветку или коммит:")
+            .with_help_message("Например: v2.3, main или a1b2c3d")
+            .prompt()
+            .ok()?;
+        let git_ref = git_ref.trim();
+        return Some((!git_ref.is_empty()).then(|| git_ref.to_string()));
+    }
+
+    if choice.starts_with("󰐃 ") {
+        return Some(pinned.map(|p| p.to_string()));
+    }
+
+    tags.iter()
+        .find(|tag| format!("󰓹 {}", tag) == choice)
+        .map(|tag| Some(tag.clone()))
+}
+
+/// Выбор канала обновлений
+pub fn select_channel(channels: &[String], current: &str) -> Option<String> {
+    let term = Term::stdout();
+    let _ = term.clear_screen();
+    print_banner();
+
+    let options: Vec<String> = channels.iter()
+        .map(|name| {
+            if name == current {
+                format!("󰄬 {} (текущий)", name)
+            } else {
+                format!("󰘬 {}", name)
+            }
+        })
+        .collect();
+
+    let choice = Select::new("󰝚 Выберите канал обновлений:", options)
+        .prompt()
+        .ok()?;
+
+    channels.iter()
+        .find(|name| choice == format!("󰄬 {} (текущий)", name) || choice == format!("󰘬 {}", name))
+        .cloned()
+}
+
+/// Вывод списка изменений модов сборки
+pub fn print_jar_changes(changes: &[JarChange]) {
+    if changes.is_empty() {
+        println!("󰄬 Моды сборки не изменятся");
+        return;
+    }
+
+    println!("󰝚 Изменения модов сборки:");
+    for change in changes {
+        match change {
+            JarChange::Added(name) => println!("  + {}", name),
+            JarChange::Removed(name) => println!("  - {}", name),
+            JarChange::Changed(name) => println!("  ~ {}", name),
+        }
+    }
+}
+
+/// Экран «Мои моды»: таблица модов экземпляра с поиском
+pub fn show_mods(folder: &GameFolder) {
+    let mods_path = match folder.mods_path() {
+        Some(path) => path,
+        None => return,
+    };
+
+    let mods = match inventory::scan(&mods_path) {
+        Ok(mods) => mods,
+        Err(e) => {
+            println!("󰅖 Ошибка чтения {}: {}", mods_path.display(), e);
+            println!("󰝚 Нажмите Enter чтобы продолжить...");
+            let _ = std::io::stdin().read_line(&mut String::new());
+            return;
+        }
+    };
+
+    let mut filter = Filter::default();
+    loop {
+        let term = Term::stdout();
+        let _ = term.clear_screen();
+        print_banner();
+
+        println!("󰋗 Моды в {}", mods_path.display());
+        if let Some(query) = &filter.query {
+            println!("󰍉 Поиск: {}", query);
+        }
+        println!();
+        let shown: Vec<InstalledMod> = mods.iter().filter(|m| filter.matches(m)).cloned().collect();
+        inventory::print_table(&shown);
+        println!();
+
+        let query = Text::new("󰍉 Поиск:")
+            .with_help_message("Название, id, файл или источник. Пустой ввод — сбросить поиск или выйти")
+            .prompt();
+        match query {
+            Ok(query) if !query.trim().is_empty() => filter.query = Some(query.trim().to_string()),
+            Ok(_) if filter.query.is_some() => filter.query = None,
+            _ => return,
+        }
+    }
+}
+
+/// Вывод модов, которые лежат в папке несколькими jar
+pub fn print_duplicates(duplicates: &[Duplicate]) {
+    println!("󰀦 Один мод лежит в нескольких jar — игра может не запуститься:");
+    for duplicate in duplicates {
+        println!("  {}:", duplicate.id);
+        for jar in &duplicate.jars {
+            let version = jar.version.as_deref().unwrap_or("версия неизвестна");
+            println!("    - {} ({}, {})", jar.file_name, version, jar.origin.label());
+        }
+    }
+}
+
+/// Проверка дубликатов после установки с выбором, какие jar оставить.
+/// Перед удалением делается снимок, так что выбор можно откатить.
+pub fn check_duplicates(mods_path: &Path, backup_limit: usize) {
+    const NEWEST: &str = "󰚰 Оставить самые новые версии";
+    const PACK: &str = "󰏗 Оставить файлы сборки";
+    const SKIP: &str = "󰅖 Ничего не удалять";
+
+    let duplicates = match duplicates::find(mods_path) {
+        Ok(duplicates) if !duplicates.is_empty() => duplicates,
+        Ok(_) => return,
+        Err(e) => {
+            println!("󰅖 Ошибка проверки дубликатов: {}", e);
+            return;
+        }
+    };
+
+    println!();
+    print_duplicates(&duplicates);
+
+    let mut options = vec![NEWEST];
+    if duplicates.iter().any(Duplicate::has_pack_jar) {
+        options.push(PACK);
+    }
+    options.push(SKIP);
+
+    let keep = match Select::new("󰝚 Что оставить?", options)
+        .with_help_message("Если файла сборки нет, остаётся самая новая версия")
+        .prompt()
+    {
+        Ok(NEWEST) => Keep::Newest,
+        Ok(PACK) => Keep::Pack,
+        _ => return,
+    };
+
+    if let Err(e) = backup::snapshot(mods_path, backup_limit) {
+        println!("󰅖 Ошибка создания снимка: {}", e);
+        return;
+    }
+    match duplicates::resolve(mods_path, &duplicates, keep) {
+        Ok(removed) => {
+            for file_name in &removed {
+                println!("  - {}", file_name);
+            }
+            println!("󰄬 Удалено дубликатов: {}", removed.len());
+        }
+        Err(e) => println!("󰅖 Ошибка удаления дубликатов: {}", e),
+    }
+}
+
+/// Размер в человекочитаемом виде
+pub fn format_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        return format!("{} Б", bytes);
+    }
+
+    let bytes = bytes as f64;
+    if bytes >= 1024.0 * 1024.0 {
+        format!("{:.1} МБ", bytes / 1024.0 / 1024.0)
+    } else {
+        format!("{:.0} КБ", bytes / 1024.0)
+    }
+}
+
+/// Вывод плана установки
+pub fn print_plan(plan: &InstallPlan) {
+    let sections = [
+        (Action::Add, "󰐕 Будут добавлены:", "+"),
+        (Action::Replace, "󰑐 Будут заменены:", "~"),
+        (Action::Remove, "󰆴 Будут удалены:", "-"),
+        (Action::Skip, "󰒭 Пропускаются (& в начале имени):", "&"),
+        (Action::Keep, "󰄬 Остаются без изменений:", "="),
+    ];
+
+    for (action, title, mark) in sections {
+        let entries: Vec<_> = plan.with_action(action).collect();
+        if entries.is_empty() {
+            continue;
+        }
+
+        println!("{} {}", title, entries.len());
+        for entry in entries {
+            println!("  {} {} ({})", mark, entry.file_name, format_size(entry.size));
+        }
+    }
+
+    println!();
+    println!("󰇚 Будет скопировано: {}", format_size(plan.download_bytes()));
+    println!("󰋊 Размер папки модов после установки: {}", format_size(plan.disk_bytes()));
+}
+
+/// Отчёт пробного запуска: какие файлы были бы удалены, скопированы и пропущены
+pub fn print_dry_run(plan: &InstallPlan, mods_dir: &Path) {
+    println!("󰙨 Пробный запуск — папка модов не изменится");
+
+    for entry in &plan.entries {
+        let target = mods_dir.join(&entry.file_name);
+        match entry.action {
+            Action::Remove => println!("  󰆴 удалить      {}", target.display()),
+            Action::Add | Action::Replace => {
+                let source = entry.source_path.as_deref().unwrap_or(Path::new("?"));
+                let verb = if entry.action == Action::Add { "скопировать" } else { "заменить   " };
+                println!("  󰆏 {}  {} -> {}", verb, source.display(), target.display());
+            }
+            Action::Skip => println!("  󰒭 пропустить   {} (имя начинается с &)", entry.file_name),
+            Action::Keep => println!("  󰄬 оставить     {}", target.display()),
+        }
+    }
+
+    println!();
+    println!("󰇚 Было бы скопировано: {}", format_size(plan.download_bytes()));
+    println!("󰋊 Размер папки модов после установки: {}", format_size(plan.disk_bytes()));
+}
+
+/// Показ плана установки и запрос подтверждения
+pub fn confirm_plan(plan: &InstallPlan) -> bool {
+    print_plan(plan);
+
+    if !plan.has_changes() {
+        println!("󰄬 Моды уже в актуальном состоянии");
+    }
+
+    Confirm::new("󰝚 Продолжить установку?")
+        .with_default(true)
+        .prompt()
+        .unwrap_or(false)
+}